@@ -0,0 +1,229 @@
+//! Evolves birds without a window, e.g. over SSH:
+//!
+//! ```text
+//! learn-to-fly-cli --config experiment.toml --seed 42 --generations 500 \
+//!     --history history.csv --checkpoint run.snapshot.json --champion champion.json
+//! ```
+//!
+//! and later, to run 500 more generations from the last checkpoint:
+//!
+//! ```text
+//! learn-to-fly-cli --resume run.snapshot.json --generations 500 --checkpoint run.snapshot.json
+//! ```
+//!
+//! `sweep` compares configs instead, running every point of an
+//! `ExperimentSpec` with several seeds:
+//!
+//! ```text
+//! learn-to-fly-cli sweep mutation.toml --results results.csv
+//! ```
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use clap::builder::RangedU64ValueParser;
+use clap::{Args, Parser, Subcommand};
+use lib_simulation::{
+    ExperimentSpec, ExportFormat, HistoryWriter, RunController, Simulation, SimulationBuilder, SimulationConfig, StopCondition,
+};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Evolves birds without a window", args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Runs one simulation, what happens without a subcommand
+    Run(RunArgs),
+    /// Runs every point of a parameter sweep with several seeds and compares
+    /// their final fitness
+    Sweep(SweepArgs),
+}
+
+#[derive(Debug, Args)]
+struct RunArgs {
+    /// Simulation config, as a .toml, .json or .ron file; defaults otherwise
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Seed of the simulation RNG, random if not given
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Number of generations to run
+    #[arg(long, default_value_t = 100)]
+    generations: usize,
+
+    /// Resumes the snapshot written by an earlier `--checkpoint`, with its
+    /// own config and RNG state
+    #[arg(long, conflicts_with_all = ["config", "seed"])]
+    resume: Option<PathBuf>,
+
+    /// Writes the statistics of every generation, as CSV (.csv) or JSON
+    /// Lines (.jsonl)
+    #[arg(long)]
+    history: Option<PathBuf>,
+
+    /// Writes a snapshot to resume the run from
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+
+    /// Generations between two checkpoints; one is always written at the end
+    #[arg(long, default_value_t = 10, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    checkpoint_every: usize,
+
+    /// Writes the brain of the fittest bird, as JSON
+    #[arg(long)]
+    champion: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct SweepArgs {
+    /// Experiment spec, as a .toml or .json file
+    spec: PathBuf,
+
+    /// Writes the results table as CSV
+    #[arg(long)]
+    results: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Some(Command::Run(args)) => run(args),
+        Some(Command::Sweep(args)) => sweep(args),
+        None => run(cli.run),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: RunArgs) -> Result<(), Box<dyn Error>> {
+    let mut simulation = match &args.resume {
+        Some(path) => Simulation::load(File::open(path)?)?,
+        None => {
+            let config = match &args.config {
+                Some(path) => SimulationConfig::load(path)?,
+                None => SimulationConfig::default(),
+            };
+            let builder = SimulationBuilder::from_config(config)?;
+            match args.seed {
+                Some(seed) => builder.seed(seed).build()?,
+                None => builder.build()?,
+            }
+        }
+    };
+    println!(
+        "Running {} generations from generation {} with seed {}",
+        args.generations,
+        simulation.generation() + 1,
+        simulation.seed()
+    );
+
+    // The history file always covers the whole run, resumed or not
+    let mut history = match &args.history {
+        Some(path) => {
+            let mut writer = HistoryWriter::new(BufWriter::new(File::create(path)?), history_format(path)?, &simulation.run_header())?;
+            writer.write_history(simulation.history())?;
+            Some(writer)
+        }
+        None => None,
+    };
+
+    let started = Instant::now();
+    let controller = RunController::new(StopCondition::Generations(1));
+    for run_generation in 1..=args.generations {
+        simulation.run(&controller)?;
+        let generation = simulation.generation() - 1;
+        let statistics = simulation.history().last().expect("A generation just ended");
+        println!(
+            "generation {:>5}  avg {:>7.2}  max {:>7.2}  diversity {:.3}  ({:.1?})",
+            generation + 1,
+            statistics.avg_score,
+            statistics.max_score,
+            statistics.mean_pairwise_distance,
+            started.elapsed()
+        );
+        if let Some(writer) = &mut history {
+            writer.write_generation(generation, statistics)?;
+            writer.flush()?;
+        }
+        if let Some(path) = &args.checkpoint {
+            if run_generation.is_multiple_of(args.checkpoint_every) || run_generation == args.generations {
+                save_checkpoint(&simulation, path)?;
+            }
+        }
+    }
+
+    if let Some(path) = &args.champion {
+        match simulation.champion() {
+            Some(champion) => {
+                fs::write(path, champion.to_json()?)?;
+                println!("Champion {} of generation {} (fitness {}) written to {}", champion.id, champion.generation + 1, champion.fitness, path.display());
+            }
+            None => eprintln!("No generation ended, there is no champion to write"),
+        }
+    }
+    Ok(())
+}
+
+fn sweep(args: SweepArgs) -> Result<(), Box<dyn Error>> {
+    let spec = ExperimentSpec::load(&args.spec)?;
+    let points = spec.points()?.len();
+    let total = points * spec.seeds as usize;
+    println!("Running {} points with {} seeds each, {} generations per run", points, spec.seeds, spec.generations);
+
+    let done = AtomicUsize::new(0);
+    let results = spec.run_with_progress(|outcome| {
+        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+        println!(
+            "[{:>4}/{}] point {} seed {}: avg {:.2} best {}",
+            done, total, outcome.point + 1, outcome.seed, outcome.avg_fitness, outcome.best_fitness
+        );
+    })?;
+    println!();
+    print!("{}", results);
+
+    if let Some(path) = &args.results {
+        let mut out = BufWriter::new(File::create(path)?);
+        results.write_csv(&mut out)?;
+        out.flush()?;
+        println!("Results written to {}", path.display());
+    }
+    Ok(())
+}
+
+fn history_format(path: &Path) -> Result<ExportFormat, String> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("csv") => Ok(ExportFormat::Csv),
+        Some("jsonl") => Ok(ExportFormat::JsonLines),
+        _ => Err(format!("cannot tell the format of {}, expected a .csv or .jsonl file", path.display())),
+    }
+}
+
+// Writes next to `path` first, so that a run killed while saving still
+// leaves the previous checkpoint in one piece
+fn save_checkpoint(simulation: &Simulation, path: &Path) -> Result<(), Box<dyn Error>> {
+    let partial = path.with_extension("partial");
+    let mut out = BufWriter::new(File::create(&partial)?);
+    simulation.save(&mut out)?;
+    out.flush()?;
+    drop(out);
+    fs::rename(&partial, path)?;
+    Ok(())
+}
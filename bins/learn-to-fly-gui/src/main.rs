@@ -1,13 +1,14 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 use eframe::egui;
-use egui::{pos2, Color32, Pos2, Stroke};
+use egui::{pos2, Color32, Pos2, Sense, Stroke};
 use emath::RectTransform;
-use rand;
-use rand::RngCore;
-use lib_simulation::{Animal, Food, Simulation, Statistics};
-use nalgebra::geometry::Point2;
-use nalgebra::Rotation2;
-use std::f32::consts::PI;
+use lib_simulation::{
+    Animal, AnimalId, BoxedCrossover, BoxedSelection, Exhaustion, ExportFormat, Food, HistoryWriter, Obstacle, RankSelection,
+    RouletteWheelSelection, Simulation, SimulationBuilder, SimulationConfig, SimulationEvent, Snapshot, SnapshotError, SinglePointCrossover, Statistics,
+    StatisticsHistory, Topology, TournamentSelection, UniformCrossover, Vision,
+};
+use std::f32::consts::{PI, TAU};
+use std::sync::mpsc::Receiver;
 use std::time::Duration;
 
 fn main() -> Result<(), eframe::Error> {
@@ -25,68 +26,376 @@ fn main() -> Result<(), eframe::Error> {
     )
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SelectionKind {
+    RouletteWheel,
+    Tournament,
+    Rank,
+}
+
+impl SelectionKind {
+    const ALL: [Self; 3] = [Self::RouletteWheel, Self::Tournament, Self::Rank];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::RouletteWheel => "Roulette wheel",
+            Self::Tournament => "Tournament (3 birds)",
+            Self::Rank => "Rank",
+        }
+    }
+
+    fn boxed(&self) -> BoxedSelection {
+        match self {
+            Self::RouletteWheel => Box::new(RouletteWheelSelection {}),
+            Self::Tournament => Box::new(TournamentSelection::new(3).expect("Tournament size is valid")),
+            Self::Rank => Box::new(RankSelection),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CrossoverKind {
+    Uniform,
+    SinglePoint,
+}
+
+impl CrossoverKind {
+    const ALL: [Self; 2] = [Self::Uniform, Self::SinglePoint];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Uniform => "Uniform",
+            Self::SinglePoint => "Single point",
+        }
+    }
+
+    fn boxed(&self) -> BoxedCrossover {
+        match self {
+            Self::Uniform => Box::new(UniformCrossover),
+            Self::SinglePoint => Box::new(SinglePointCrossover),
+        }
+    }
+}
+
 struct LearnToFlyApp {
-    rng: Box<dyn RngCore>,
+    // Seed the simulation is rebuilt with
+    seed: u64,
     simulation: Simulation,
-    birds: u32,
-    food: u32,
-    mut_coeff: f32,
-    mut_chance: f32,
-    generation_length: u32,
+    config: SimulationConfig,
+    config_error: Option<String>,
+    // Why the last generation could not be evolved, if it could not
+    evolution_error: Option<String>,
+    selection: SelectionKind,
+    crossover: CrossoverKind,
+    events: Receiver<SimulationEvent>,
+    generation: usize,
+    selected_bird: Option<AnimalId>,
     last_gen_statistics: Option<Statistics>
 }
 
 impl Default for LearnToFlyApp {
     fn default() -> Self {
-        let mut rng = Box::new(rand::thread_rng());
-        let mut_chance = 0.1;
-        let mut_coeff = 0.5;
-        let generation_length = 1000;
+        let seed = rand::random();
+        let config = SimulationConfig::default();
+        let simulation = Simulation::from_config(seed, config.clone()).expect("Default config is valid");
         Self {
-            rng: rng.clone(),
-            simulation: Simulation::random(&mut rng, 10, 15, mut_chance, mut_coeff, generation_length),
-            birds: 10,
-            food: 15,
-            mut_chance,
-            mut_coeff,
-            generation_length,
+            seed,
+            events: simulation.subscribe(),
+            simulation,
+            config,
+            config_error: None,
+            evolution_error: None,
+            selection: SelectionKind::RouletteWheel,
+            crossover: CrossoverKind::Uniform,
+            generation: 0,
+            selected_bird: None,
             last_gen_statistics: None
         }
     }
 }
 
 impl LearnToFlyApp {
-    fn place_food(food: &Food, screen_transform: RectTransform ) -> epaint::Shape {
+    fn place_food(food: &Food, color: Color32, screen_transform: RectTransform ) -> epaint::Shape {
         let food_pos = food.position();
-        epaint::Shape::Circle(epaint::CircleShape{ center: screen_transform.transform_pos_clamped(pos2(food_pos.x, food_pos.y)), radius: 2.0, fill: Color32::BLUE, stroke: Stroke::NONE})
+        epaint::Shape::Circle(epaint::CircleShape{ center: screen_transform.transform_pos_clamped(pos2(food_pos.x, food_pos.y)), radius: 2.0, fill: color, stroke: Stroke::NONE})
+    }
+
+    fn place_obstacle(obstacle: &Obstacle, screen_transform: RectTransform) -> epaint::Shape {
+        let fill = Color32::DARK_GRAY;
+        let to_screen = |x: f32, y: f32| screen_transform.transform_pos(pos2(x, y));
+        match obstacle {
+            Obstacle::Circle { center, radius } => {
+                let radius = radius * screen_transform.scale().x;
+                epaint::Shape::circle_filled(to_screen(center.x, center.y), radius, fill)
+            }
+            Obstacle::Rectangle { center, size, angle } => {
+                let rotation = nalgebra::Rotation2::new(*angle);
+                let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                    .into_iter()
+                    .map(|(x, y)| {
+                        let corner = center + rotation * nalgebra::Vector2::new(x * size.x, y * size.y) / 2.0;
+                        to_screen(corner.x, corner.y)
+                    })
+                    .collect();
+                epaint::Shape::convex_polygon(corners, fill, Stroke::NONE)
+            }
+            Obstacle::Polyline { points } => {
+                epaint::Shape::line(points.iter().map(|p| to_screen(p.x, p.y)).collect(), Stroke::new(2.0, fill))
+            }
+        }
     }
 
-    fn place_bird(animal: &Animal, screen_transform: RectTransform ) -> epaint::Shape {
+    fn place_bird(animal: &Animal, color: Color32, screen_transform: RectTransform ) -> epaint::Shape {
         let segment_size = 0.01;
         let animal_pos = animal.position();
         let animal_rot = animal.rotation().angle();
-        let vertices = vec![
+        let vertices = [
             pos2(animal_pos.x + (animal_rot + 2.0 / 3.0 * PI).cos() *  segment_size , animal_pos.y + (animal_rot + 2.0 / 3.0 * PI).sin() * segment_size),
             pos2(animal_pos.x + (animal_rot + 4.0 / 3.0 * PI).cos() * segment_size, animal_pos.y + (animal_rot + 4.0 / 3.0 * PI).sin() * segment_size),
             pos2(animal_pos.x + animal_rot.cos() * segment_size, animal_pos.y + animal_rot.sin() * segment_size)
         ];
         let traingle_shape = epaint::PathShape::convex_polygon(
             vertices.iter().map(|&p| screen_transform.transform_pos(p)).collect(),
-            Color32::GREEN,
+            color,
             Stroke::NONE
         );
         epaint::Shape::Path(traingle_shape)
     }
+
+    // Average (white) and max (green) score of every generation so far
+    fn plot_history(ui: &mut egui::Ui, history: &StatisticsHistory) {
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width().min(400.0), 80.0), Sense::hover());
+        ui.painter().rect_stroke(rect, 0.0, Stroke::new(1.0, Color32::GRAY));
+        let top_score = history.iter().map(|s| s.max_score).fold(1.0, f32::max);
+        let generations = (history.len().max(2) - 1) as f32;
+        let to_screen = emath::RectTransform::from_to(egui::Rect::from_x_y_ranges(0.0..=generations, top_score..=0.0), rect.shrink(2.0));
+        for (series, color) in [(history.series(|s| s.avg_score), Color32::WHITE), (history.series(|s| s.max_score), Color32::GREEN)] {
+            let points = series.iter().enumerate().map(|(g, &score)| to_screen.transform_pos(pos2(g as f32, score))).collect();
+            ui.painter().add(epaint::Shape::line(points, Stroke::new(1.0, color)));
+        }
+    }
+
+    fn bird_at(&self, position: Pos2) -> Option<AnimalId> {
+        let select_radius = 0.03;
+        self.simulation.world().animals().iter()
+            .map(|a| (a.id(), (a.position().x - position.x).hypot(a.position().y - position.y)))
+            .filter(|&(_, distance)| distance < select_radius)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(id, _)| id)
+    }
+
+    fn last_champion(&self) -> Option<AnimalId> {
+        self.simulation.genealogy().records()
+            .filter(|r| r.score.is_some() && r.generation + 1 == self.generation)
+            .max_by_key(|r| r.score)
+            .map(|r| r.id)
+    }
+
+    fn export_lineage(&self, id: AnimalId, extension: &str) {
+        let ancestry = self.simulation.genealogy().ancestry(id);
+        let content = match extension {
+            "dot" => Ok(ancestry.to_dot()),
+            _ => ancestry.to_json().map_err(|e| e.to_string()),
+        };
+        let path = format!("lineage-{}.{}", id.0, extension);
+        match content.and_then(|c| std::fs::write(&path, c).map_err(|e| e.to_string())) {
+            Ok(_) => println!("Lineage of {} written to {}", id, path),
+            Err(e) => eprintln!("Cannot export lineage of {}: {}", id, e),
+        }
+    }
+
+    fn export_history(&self, format: ExportFormat, path: &str) {
+        let header = self.simulation.run_header();
+        let result = std::fs::File::create(path)
+            .map(std::io::BufWriter::new)
+            .and_then(|file| HistoryWriter::new(file, format, &header))
+            .and_then(|mut writer| {
+                writer.write_history(self.simulation.history())?;
+                writer.flush()
+            });
+        match result {
+            Ok(_) => println!("History written to {}", path),
+            Err(e) => eprintln!("Cannot export history: {}", e),
+        }
+    }
+
+    fn save_snapshot(&self, path: &str) {
+        let result = std::fs::File::create(path)
+            .map_err(SnapshotError::from)
+            .and_then(|file| self.simulation.save(std::io::BufWriter::new(file)));
+        match result {
+            Ok(_) => println!("Snapshot written to {}", path),
+            Err(e) => eprintln!("Cannot save snapshot: {}", e),
+        }
+    }
+
+    // Resumes the snapshot at `path` with the selection and crossover picked
+    // in the options
+    fn load_snapshot(&mut self, path: &str) {
+        let result = std::fs::File::open(path)
+            .map_err(SnapshotError::from)
+            .and_then(|file| Snapshot::load(std::io::BufReader::new(file)))
+            .and_then(|snapshot| {
+                SimulationBuilder::from_config(snapshot.config().clone())?
+                    .selection(self.selection.boxed())
+                    .crossover(self.crossover.boxed())
+                    .restore(snapshot)
+            });
+        match result {
+            Ok(simulation) => {
+                self.config = simulation.config().clone();
+                self.seed = simulation.seed();
+                self.generation = simulation.generation();
+                self.last_gen_statistics = simulation.history().last().copied();
+                self.events = simulation.subscribe();
+                self.selected_bird = None;
+                self.simulation = simulation;
+                println!("Snapshot loaded from {}", path);
+            }
+            Err(e) => eprintln!("Cannot load snapshot: {}", e),
+        }
+    }
+
+    fn lineage_panel(&mut self, ui: &mut egui::Ui, id: AnimalId) {
+        ui.heading(format!("Bird {}", id));
+        let genealogy = self.simulation.genealogy();
+        if let Some(record) = genealogy.get(id) {
+            ui.label(format!("Born in generation {}", record.generation + 1));
+            ui.label(format!("Mutated genes: {}", record.mutated_genes));
+            if let Some(score) = record.score {
+                ui.label(format!("Score: {}", score));
+            }
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Export DOT").clicked() {
+                self.export_lineage(id, "dot");
+            }
+            if ui.button("Export JSON").clicked() {
+                self.export_lineage(id, "json");
+            }
+            if ui.button("Close").clicked() {
+                self.selected_bird = None;
+            }
+        });
+        ui.add_space(10.0);
+        ui.label("Ancestors:");
+        let genealogy = self.simulation.genealogy();
+        let mut ancestors: Vec<_> = genealogy.ancestors(id).into_iter().filter_map(|a| genealogy.get(a)).collect();
+        ancestors.sort_by_key(|r| std::cmp::Reverse((r.generation, r.score)));
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for ancestor in ancestors {
+                let score = ancestor.score.map_or("-".to_string(), |s| s.to_string());
+                ui.label(format!("{}  gen {}  score {}", ancestor.id, ancestor.generation + 1, score));
+            }
+        });
+    }
+
+    // Sliders for the knobs of `config` not shown by default, returns whether
+    // any of them changed
+    fn config_sliders(ui: &mut egui::Ui, config: &mut SimulationConfig) -> bool {
+        let mut changed = false;
+        let mut slider = |ui: &mut egui::Ui, label: &str, slider: egui::Slider| {
+            ui.horizontal(|ui| {
+                let label = ui.label(label);
+                changed |= ui.add(slider).labelled_by(label.id).changed();
+            });
+        };
+        slider(ui, "Generation Length:", egui::Slider::new(&mut config.evolution.generation_length, 100..=5000));
+        slider(ui, "Mutation Chance:", egui::Slider::new(&mut config.evolution.mutation_chance, 0.0..=1.0));
+        slider(ui, "Mutation Coefficient:", egui::Slider::new(&mut config.evolution.mutation_coeff, 0.0..=2.0));
+        slider(ui, "Min Speed:", egui::Slider::new(&mut config.animal.speed_min, 0.0..=0.01));
+        slider(ui, "Max Speed:", egui::Slider::new(&mut config.animal.speed_max, 0.0..=0.02));
+        slider(ui, "Speed Acceleration:", egui::Slider::new(&mut config.animal.speed_accel, 0.0..=1.0));
+        slider(ui, "Rotation Acceleration:", egui::Slider::new(&mut config.animal.rotation_accel, 0.0..=TAU));
+        slider(ui, "Eat Radius:", egui::Slider::new(&mut config.world.eat_radius, 0.0..=0.05));
+        slider(ui, "Collision Radius:", egui::Slider::new(&mut config.world.collision_radius, 0.0..=0.05));
+        slider(ui, "Field of View Range:", egui::Slider::new(&mut config.eye.fov_range, 0.05..=1.0));
+        slider(ui, "Field of View Angle:", egui::Slider::new(&mut config.eye.fov_angle, 0.1..=TAU));
+        slider(ui, "Eye Cells:", egui::Slider::new(&mut config.eye.cells, 1..=30));
+        let mut raycast = matches!(config.eye.vision, Vision::Raycast { .. });
+        let mut toggled = ui.checkbox(&mut raycast, "Raycast Eyes").changed();
+        if toggled {
+            config.eye.vision = if raycast { Vision::Raycast { hit_radius: 0.01 } } else { Vision::Cone };
+        }
+        if let Vision::Raycast { hit_radius } = &mut config.eye.vision {
+            slider(ui, "Ray Hit Radius:", egui::Slider::new(hit_radius, 0.001..=0.05));
+        }
+        slider(ui, "Food Regrowth Delay:", egui::Slider::new(&mut config.food.regrowth_delay, 0..=1000));
+        slider(ui, "Food Lifetime:", egui::Slider::new(&mut config.food.lifetime, 0..=5000));
+        slider(ui, "Predators:", egui::Slider::new(&mut config.predators.count, 0..=20));
+        slider(ui, "Catch Radius:", egui::Slider::new(&mut config.predators.catch_radius, 0.0..=0.05));
+        toggled |= ui.checkbox(&mut config.energy.enabled, "Energy").changed();
+        if config.energy.enabled {
+            slider(ui, "Metabolism:", egui::Slider::new(&mut config.energy.metabolism, 0.0..=0.01));
+            slider(ui, "Food Energy:", egui::Slider::new(&mut config.energy.food_energy, 0.0..=1.0));
+            toggled |= ui.checkbox(&mut config.reproduction.enabled, "Births in the World").changed();
+            if config.reproduction.enabled {
+                slider(ui, "Max Age:", egui::Slider::new(&mut config.reproduction.max_age, 100..=10000));
+                slider(ui, "Max Population:", egui::Slider::new(&mut config.reproduction.max_population, 2..=500));
+            }
+            egui::ComboBox::from_label("Out of Energy")
+                .selected_text(config.energy.exhaustion.name())
+                .show_ui(ui, |ui| {
+                    for exhaustion in Exhaustion::ALL {
+                        changed |= ui.selectable_value(&mut config.energy.exhaustion, exhaustion, exhaustion.name()).changed();
+                    }
+                });
+        } else {
+            // Birds cannot give birth without energy
+            config.reproduction.enabled = false;
+        }
+        egui::ComboBox::from_label("World Edges")
+            .selected_text(config.world.topology.name())
+            .show_ui(ui, |ui| {
+                for topology in Topology::ALL {
+                    changed |= ui.selectable_value(&mut config.world.topology, topology, topology.name()).changed();
+                }
+            });
+        changed |= toggled;
+        changed
+    }
+
+    fn rebuild_simulation(&mut self) {
+        let simulation = SimulationBuilder::from_config(self.config.clone()).and_then(|builder| {
+            builder
+                .selection(self.selection.boxed())
+                .crossover(self.crossover.boxed())
+                .seed(self.seed)
+                .build()
+        });
+        match simulation {
+            Ok(simulation) => {
+                self.simulation = simulation;
+                self.config_error = None;
+            }
+            // Keep the running simulation until the config makes sense again
+            Err(err) => {
+                self.config_error = Some(err.to_string());
+                return;
+            }
+        }
+        self.events = self.simulation.subscribe();
+        self.generation = 0;
+        self.selected_bird = None;
+        self.last_gen_statistics = None;
+        self.evolution_error = None;
+    }
 }
 
 impl eframe::App for LearnToFlyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        let step_res = self.simulation.step(&mut self.rng);
-        if step_res.is_some() {
-            self.last_gen_statistics = step_res;
+        if let Err(err) = self.simulation.step() {
+            self.evolution_error = Some(err.to_string());
+        }
+        for event in self.events.try_iter() {
+            if let SimulationEvent::GenerationEnd { generation, statistics } = event {
+                self.generation = generation + 1;
+                self.last_gen_statistics = Some(statistics);
+            }
         }
         let mut rebuild_simulation = false;
         egui::TopBottomPanel::bottom("config_panel").show(ctx, |ui| {
+            ui.heading(format!("Generation {}", self.generation + 1));
             ui.heading("Last Generation Statistics");
             if self.last_gen_statistics.is_some() {
                 ui.vertical(|ui| {
@@ -98,56 +407,176 @@ impl eframe::App for LearnToFlyApp {
                     });
                     ui.horizontal(|ui| {
                         ui.label("Max Score:");
-                        ui.label(format!("{}", stat.max_score));
+                        ui.label(format!("{:.2}", stat.max_score));
                     
                     });
                      ui.horizontal(|ui| {
                         ui.label("Min Score:");
-                        ui.label(format!("{}", stat.min_score));
+                        ui.label(format!("{:.2}", stat.min_score));
                     
-                    }) 
-
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Median Score:");
+                        ui.label(format!("{:.1}", stat.median_score));
+                        ui.label("Std. Deviation:");
+                        ui.label(format!("{:.2}", stat.std_dev_score));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Brain Diversity:");
+                        ui.label(format!("{:.3}", stat.mean_pairwise_distance));
+                        ui.label("Distinct Brains:");
+                        ui.label(format!("{}", stat.distinct_brains));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Food Eaten:");
+                        ui.label(format!("{}", stat.food_eaten));
+                        ui.label("Collisions:");
+                        ui.label(format!("{}", stat.collisions));
+                        ui.label("Mean Speed:");
+                        ui.label(format!("{:.4}", stat.mean_speed));
+                        ui.label("Starved:");
+                        ui.label(format!("{}", stat.starved));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Population:");
+                        ui.label(format!("{}", stat.population));
+                        ui.label("Births:");
+                        ui.label(format!("{}", stat.births));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Food Available:");
+                        ui.label(format!("{:.1}", stat.mean_food_available));
+                        ui.label("Regrown:");
+                        ui.label(format!("{}", stat.food_regrown));
+                        ui.label("Rotten:");
+                        ui.label(format!("{}", stat.food_rotten));
+                    });
+                    Self::plot_history(ui, self.simulation.history());
             });
             };
+            if ui.button("Where does the last champion come from?").clicked() {
+                self.selected_bird = self.last_champion();
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Export history (CSV)").clicked() {
+                    self.export_history(ExportFormat::Csv, "history.csv");
+                }
+                if ui.button("Export history (JSON Lines)").clicked() {
+                    self.export_history(ExportFormat::JsonLines, "history.jsonl");
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Save snapshot").clicked() {
+                    self.save_snapshot("simulation.snapshot.json");
+                }
+                if ui.button("Load snapshot").clicked() {
+                    self.load_snapshot("simulation.snapshot.json");
+                }
+            });
             ui.add_space(10.0);
 
             ui.heading("Simulation options");
             ui.horizontal(|ui| {
                 let bird_label = ui.label("Number of Birds: "); 
-                let bird_slider = ui.add(egui::Slider::new(&mut self.birds, 0..=100)).labelled_by(bird_label.id);
+                let bird_slider = ui.add(egui::Slider::new(&mut self.config.world.animals, 0..=100)).labelled_by(bird_label.id);
                 if bird_slider.changed() {
                     rebuild_simulation = true;
                 }
             });
             ui.horizontal(|ui| {
                 let food_label = ui.label("Number of Food: "); 
-                let food_slider = ui.add(egui::Slider::new(&mut self.food, 0..=50)).labelled_by(food_label.id);
+                let food_slider = ui.add(egui::Slider::new(&mut self.config.world.food, 0..=50)).labelled_by(food_label.id);
                 if food_slider.changed() {
                     rebuild_simulation = true;
                 }
             });
+            egui::ComboBox::from_label("Selection")
+                .selected_text(self.selection.label())
+                .show_ui(ui, |ui| {
+                    for kind in SelectionKind::ALL {
+                        rebuild_simulation |= ui.selectable_value(&mut self.selection, kind, kind.label()).changed();
+                    }
+                });
+            egui::ComboBox::from_label("Crossover")
+                .selected_text(self.crossover.label())
+                .show_ui(ui, |ui| {
+                    for kind in CrossoverKind::ALL {
+                        rebuild_simulation |= ui.selectable_value(&mut self.crossover, kind, kind.label()).changed();
+                    }
+                });
+            ui.horizontal(|ui| {
+                let seed_label = ui.label("Seed:");
+                ui.add(egui::DragValue::new(&mut self.seed)).labelled_by(seed_label.id);
+                // Same seed and options, same flight
+                rebuild_simulation |= ui.button("Replay").clicked();
+                if ui.button("New seed").clicked() {
+                    self.seed = rand::random();
+                    rebuild_simulation = true;
+                }
+            });
+            ui.collapsing("Advanced", |ui| {
+                rebuild_simulation |= Self::config_sliders(ui, &mut self.config);
+            });
+            for error in self.config_error.iter().chain(&self.evolution_error) {
+                ui.colored_label(Color32::RED, error);
+            }
             ui.add_space(10.0);
         });
+        if let Some(id) = self.selected_bird {
+            egui::SidePanel::right("lineage_panel").show(ctx, |ui| {
+                self.lineage_panel(ui, id);
+            });
+        }
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Learn To Fly Simulation");
             egui::containers::Frame::canvas(ui.style()).show(ui, |ui| {
                 ui.ctx().request_repaint();
 
                 let desired_size = egui::vec2(ui.available_width() * 1.0, ui.available_height() * 1.0);
-                let (_id, rect) = ui.allocate_space(desired_size);
+                let (rect, response) = ui.allocate_exact_size(desired_size, Sense::click());
                 
                 
                 let to_screen =
                     emath::RectTransform::from_to(egui::Rect::from_x_y_ranges(0.0..=1.0, 0.0..=1.0), rect.shrink(10.0));
 
 
-                let mut shapes = vec![];
+                if let Some(pointer) = response.interact_pointer_pos().filter(|_| response.clicked()) {
+                    self.selected_bird = self.bird_at(to_screen.inverse().transform_pos(pointer));
+                }
+                let ancestors = self.selected_bird
+                    .map(|id| self.simulation.genealogy().ancestors(id))
+                    .unwrap_or_default();
+
+                let mut shapes: Vec<_> = self.simulation.world().obstacles().iter().map(|o| Self::place_obstacle(o, to_screen)).collect();
+                let exhaustion = self.simulation.config().energy.exhaustion;
                 for i in self.simulation.world().animals() {
-                    shapes.push(Self::place_bird(i, to_screen ));
+                    if i.is_exhausted() && exhaustion == Exhaustion::Die {
+                        continue;
+                    }
+                    let color = if i.is_exhausted() {
+                        Color32::GRAY
+                    } else if Some(i.id()) == self.selected_bird {
+                        Color32::YELLOW
+                    } else if ancestors.contains(&i.id()) {
+                        Color32::GOLD
+                    } else {
+                        Color32::GREEN
+                    };
+                    shapes.push(Self::place_bird(i, color, to_screen ));
+                }
+
+                for predator in self.simulation.world().predators() {
+                    if predator.is_exhausted() && exhaustion == Exhaustion::Die {
+                        continue;
+                    }
+                    shapes.push(Self::place_bird(predator, Color32::RED, to_screen));
                 }
 
-                for i in self.simulation.world().food() {
-                    shapes.push(Self::place_food(i, to_screen ));
+                // Plain food keeps its usual colour, kinds set in the config their own
+                let kinds = &self.simulation.config().food.kinds;
+                for i in self.simulation.world().food().iter().filter(|f| f.is_available()) {
+                    let color = kinds.get(i.kind()).map_or(Color32::BLUE, |k| Color32::from_rgb(k.color[0], k.color[1], k.color[2]));
+                    shapes.push(Self::place_food(i, color, to_screen ));
                 }
 
                 ui.painter().extend(shapes);
@@ -156,10 +585,9 @@ impl eframe::App for LearnToFlyApp {
 
 
         });
-        ctx.request_repaint_after(Duration::from_millis(1000 / 60 as u64));
+        ctx.request_repaint_after(Duration::from_millis(1000 / 60));
         if rebuild_simulation {
-            self.simulation = Simulation::random(&mut self.rng, self.birds as usize, self.food as usize, self.mut_chance, self.mut_coeff, self.generation_length);
-            self.last_gen_statistics = None;
+            self.rebuild_simulation();
         }
 
     }
@@ -0,0 +1,214 @@
+use nalgebra::{DMatrix, DVector, SymmetricEigen};
+use rand::{Rng, RngCore};
+
+use crate::{Chromosome, EvolutionObserver, GenerationSummary, GeneticError, Individual, Optimizer};
+
+/// Covariance matrix adaptation evolution strategy.
+///
+/// Children are sampled from a multivariate normal distribution, whose mean,
+/// step size and covariance are updated from the best half of every scored
+/// population. The first population passed to `evolve` only seeds the mean;
+/// later ones must be the individuals it returned, in the same order.
+///
+/// Children have no parents, so observers only hear about generation ends.
+pub struct CmaEs {
+    initial_sigma: f32,
+    state: Option<State>,
+    observers: Vec<Box<dyn EvolutionObserver + Send + Sync>>,
+    generation: usize,
+}
+
+struct State {
+    mean: DVector<f64>,
+    sigma: f64,
+    covariance: DMatrix<f64>,
+    // Eigen decomposition of `covariance`: B, sqrt of the eigenvalues and C^-1/2
+    eigenvectors: DMatrix<f64>,
+    scales: DVector<f64>,
+    inv_sqrt: DMatrix<f64>,
+    path_c: DVector<f64>,
+    path_sigma: DVector<f64>,
+    // Number of updates so far, and at which one the decomposition was last refreshed
+    updates: usize,
+    decomposed_at: usize,
+}
+
+// Default strategy parameters for `n` genes and `lambda` children
+struct Parameters {
+    weights: Vec<f64>,
+    mu_eff: f64,
+    c_c: f64,
+    c_sigma: f64,
+    c_1: f64,
+    c_mu: f64,
+    damping: f64,
+    chi_n: f64,
+}
+
+impl Parameters {
+    fn new(n: usize, lambda: usize) -> Self {
+        let n = n as f64;
+        let mu = lambda / 2;
+        let raw: Vec<f64> = (1..=mu).map(|i| (mu as f64 + 0.5).ln() - (i as f64).ln()).collect();
+        let total: f64 = raw.iter().sum();
+        let weights: Vec<f64> = raw.iter().map(|w| w / total).collect();
+        let mu_eff = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+
+        let c_c = (4.0 + mu_eff / n) / (n + 4.0 + 2.0 * mu_eff / n);
+        let c_sigma = (mu_eff + 2.0) / (n + mu_eff + 5.0);
+        let c_1 = 2.0 / ((n + 1.3).powi(2) + mu_eff);
+        let c_mu = (1.0 - c_1).min(2.0 * (mu_eff - 2.0 + 1.0 / mu_eff) / ((n + 2.0).powi(2) + mu_eff));
+        let damping = 1.0 + 2.0 * (((mu_eff - 1.0) / (n + 1.0)).sqrt() - 1.0).max(0.0) + c_sigma;
+        let chi_n = n.sqrt() * (1.0 - 1.0 / (4.0 * n) + 1.0 / (21.0 * n * n));
+        Self { weights, mu_eff, c_c, c_sigma, c_1, c_mu, damping, chi_n }
+    }
+}
+
+impl CmaEs {
+    /// `initial_sigma` is the step size children are first sampled with
+    pub fn new(initial_sigma: f32) -> Result<Self, GeneticError> {
+        if !(initial_sigma.is_finite() && initial_sigma > 0.0) {
+            return Err(GeneticError::InvalidParameter { name: "initial sigma", value: initial_sigma, expected: "positive" });
+        }
+        Ok(Self { initial_sigma, state: None, observers: vec![], generation: 0 })
+    }
+
+    pub fn with_observer(mut self, observer: impl EvolutionObserver + Send + Sync + 'static) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Current step size, `None` before the first generation
+    pub fn sigma(&self) -> Option<f32> {
+        self.state.as_ref().map(|s| s.sigma as f32)
+    }
+
+    fn notify(&self, event: impl Fn(&dyn EvolutionObserver)) {
+        for observer in &self.observers {
+            event(observer.as_ref());
+        }
+    }
+}
+
+impl State {
+    fn new(mean: DVector<f64>, sigma: f64) -> Self {
+        let n = mean.len();
+        Self {
+            mean,
+            sigma,
+            covariance: DMatrix::identity(n, n),
+            eigenvectors: DMatrix::identity(n, n),
+            scales: DVector::from_element(n, 1.0),
+            inv_sqrt: DMatrix::identity(n, n),
+            path_c: DVector::zeros(n),
+            path_sigma: DVector::zeros(n),
+            updates: 0,
+            decomposed_at: 0,
+        }
+    }
+
+    fn update(&mut self, ranked: &[DVector<f64>], parameters: &Parameters) {
+        let n = self.mean.len() as f64;
+        let Parameters { weights, mu_eff, c_c, c_sigma, c_1, c_mu, damping, chi_n } = parameters;
+        let steps: Vec<DVector<f64>> = ranked.iter().map(|x| (x - &self.mean) / self.sigma).collect();
+        let step = steps.iter().zip(weights).fold(DVector::zeros(self.mean.len()), |acc, (y, w)| acc + y * *w);
+        self.mean += &step * self.sigma;
+        self.updates += 1;
+
+        self.path_sigma = &self.path_sigma * (1.0 - c_sigma) + &self.inv_sqrt * &step * (c_sigma * (2.0 - c_sigma) * mu_eff).sqrt();
+        let norm = self.path_sigma.norm() / (1.0 - (1.0 - c_sigma).powi(2 * self.updates as i32)).sqrt() / chi_n;
+        let h_sigma = if norm < 1.4 + 2.0 / (n + 1.0) { 1.0 } else { 0.0 };
+        self.path_c = &self.path_c * (1.0 - c_c) + &step * (h_sigma * (c_c * (2.0 - c_c) * mu_eff).sqrt());
+
+        let rank_one = &self.path_c * self.path_c.transpose() + &self.covariance * ((1.0 - h_sigma) * c_c * (2.0 - c_c));
+        let rank_mu = steps.iter().zip(weights).fold(DMatrix::zeros(self.mean.len(), self.mean.len()), |acc, (y, w)| acc + y * y.transpose() * *w);
+        self.covariance = &self.covariance * (1.0 - c_1 - c_mu) + rank_one * *c_1 + rank_mu * *c_mu;
+        self.sigma *= ((c_sigma / damping) * (self.path_sigma.norm() / chi_n - 1.0)).exp();
+
+        // The decomposition is O(n^3), so it is only refreshed once the
+        // covariance had the time to drift away from it
+        if (self.updates - self.decomposed_at) as f64 > 1.0 / (c_1 + c_mu) / n / 10.0 {
+            self.decompose();
+        }
+    }
+
+    fn decompose(&mut self) {
+        self.decomposed_at = self.updates;
+        self.covariance = (&self.covariance + self.covariance.transpose()) * 0.5;
+        let eigen = SymmetricEigen::new(self.covariance.clone());
+        self.scales = eigen.eigenvalues.map(|v| v.max(1e-20).sqrt());
+        self.eigenvectors = eigen.eigenvectors;
+        let inv_scales = DMatrix::from_diagonal(&self.scales.map(|s| 1.0 / s));
+        self.inv_sqrt = &self.eigenvectors * inv_scales * self.eigenvectors.transpose();
+    }
+
+    fn sample(&self, rng: &mut dyn RngCore) -> Chromosome {
+        let z = DVector::from_fn(self.mean.len(), |_, _| standard_normal(rng));
+        let x = &self.mean + &self.eigenvectors * z.component_mul(&self.scales) * self.sigma;
+        x.iter().map(|&g| g as f32).collect()
+    }
+}
+
+// Box-Muller transform
+fn standard_normal(rng: &mut dyn RngCore) -> f64 {
+    let u: f64 = 1.0 - rng.gen::<f64>();
+    let v: f64 = rng.gen();
+    (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+}
+
+fn to_vector(chromosome: &Chromosome) -> DVector<f64> {
+    DVector::from_iterator(chromosome.genes().len(), chromosome.iter().map(|&g| g as f64))
+}
+
+impl<T: Individual> Optimizer<T> for CmaEs {
+    fn evolve(&mut self, population: &[T], rng: &mut dyn RngCore) -> Result<Vec<T>, GeneticError> {
+        if population.is_empty() {
+            return Err(GeneticError::EmptyPopulation);
+        }
+        if population.len() < 2 {
+            return Err(GeneticError::PopulationTooSmall { size: population.len(), required: 2 });
+        }
+        let genes = self.state.as_ref().map_or(population[0].chromosome().genes().len(), |s| s.mean.len());
+        if let Some(other) = population.iter().find(|i| i.chromosome().genes().len() != genes) {
+            return Err(GeneticError::ChromosomeLength { expected: genes, actual: other.chromosome().genes().len() });
+        }
+
+        let mut ranked: Vec<&T> = population.iter().collect();
+        ranked.sort_by(|a, b| b.fitness().total_cmp(&a.fitness()));
+        let parameters = Parameters::new(genes, population.len());
+        let best: Vec<DVector<f64>> = ranked.iter().take(parameters.weights.len()).map(|i| to_vector(i.chromosome())).collect();
+        match &mut self.state {
+            Some(state) => state.update(&best, &parameters),
+            None => {
+                let mean = best.iter().zip(&parameters.weights).fold(DVector::zeros(genes), |acc, (x, w)| acc + x * *w);
+                self.state = Some(State::new(mean, self.initial_sigma as f64));
+            }
+        }
+
+        let state = self.state.as_ref().expect("State is initialised above");
+        let offspring = (0..population.len()).map(|_| T::create(state.sample(rng))).collect();
+
+        if !self.observers.is_empty() {
+            let summary = GenerationSummary::from_population(self.generation, population);
+            self.notify(|o| o.on_generation_end(&summary));
+        }
+        self.generation += 1;
+        Ok(offspring)
+    }
+
+    fn generation(&self) -> usize {
+        self.generation
+    }
+
+    fn minimum_population(&self) -> usize {
+        2
+    }
+
+    fn add_observer(&mut self, observer: Box<dyn EvolutionObserver + Send + Sync>) {
+        self.observers.push(observer);
+    }
+
+    fn name(&self) -> &'static str {
+        "CMA-ES"
+    }
+}
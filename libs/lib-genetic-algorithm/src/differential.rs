@@ -0,0 +1,192 @@
+use rand::{seq::index, Rng, RngCore};
+
+use crate::{Chromosome, EvolutionObserver, GenerationSummary, GeneticError, Individual, Optimizer, Parent};
+
+/// How `DifferentialEvolution` builds the mutant vector of each child
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DifferentialStrategy {
+    /// `random + weight * (random - random)`, explores more
+    Rand1Bin,
+    /// `best + weight * (random - random)`, converges faster
+    Best1Bin,
+}
+
+/// Differential evolution with binomial crossover.
+///
+/// Every individual of the population is a target; its child (the trial) is
+/// the target crossed with a mutant vector built from other targets. Trials
+/// are scored by the caller between two calls to `evolve`, and the next call
+/// keeps, for each position, the better of the target and its trial. So the
+/// population handed to `evolve` must keep the order of the one it returned.
+pub struct DifferentialEvolution {
+    strategy: DifferentialStrategy,
+    weight: f32,
+    crossover_rate: f32,
+    // Current targets with their fitness, and whether each one is the
+    // individual at the same position in the population last passed in
+    targets: Vec<(Chromosome, f32, bool)>,
+    observers: Vec<Box<dyn EvolutionObserver + Send + Sync>>,
+    generation: usize,
+}
+
+impl DifferentialEvolution {
+    /// `weight` scales the difference vectors and must be in `(0, 2]`,
+    /// `crossover_rate` is the chance of taking each gene from the mutant
+    pub fn new(strategy: DifferentialStrategy, weight: f32, crossover_rate: f32) -> Result<Self, GeneticError> {
+        if !(weight > 0.0 && weight <= 2.0) {
+            return Err(GeneticError::InvalidParameter { name: "differential weight", value: weight, expected: "in (0, 2]" });
+        }
+        if !(0.0..=1.0).contains(&crossover_rate) {
+            return Err(GeneticError::InvalidParameter { name: "crossover rate", value: crossover_rate, expected: "between 0 and 1" });
+        }
+        Ok(Self { strategy, weight, crossover_rate, targets: vec![], observers: vec![], generation: 0 })
+    }
+
+    pub fn with_observer(mut self, observer: impl EvolutionObserver + Send + Sync + 'static) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    pub fn strategy(&self) -> DifferentialStrategy {
+        self.strategy
+    }
+
+    fn notify(&self, event: impl Fn(&dyn EvolutionObserver)) {
+        for observer in &self.observers {
+            event(observer.as_ref());
+        }
+    }
+
+    // Donors picked for each child besides its target and the best target
+    fn donors(&self) -> usize {
+        match self.strategy {
+            DifferentialStrategy::Rand1Bin => 3,
+            DifferentialStrategy::Best1Bin => 2,
+        }
+    }
+
+    // Room for a target, the best target and donors that are neither
+    fn minimum_population(&self) -> usize {
+        match self.strategy {
+            DifferentialStrategy::Rand1Bin => self.donors() + 1,
+            DifferentialStrategy::Best1Bin => self.donors() + 2,
+        }
+    }
+
+    // Keeps the better of each target and the trial bred from it
+    fn update_targets<T: Individual>(&mut self, population: &[T]) {
+        if self.targets.len() != population.len() {
+            self.targets = population.iter().map(|i| (i.chromosome().clone(), i.fitness(), true)).collect();
+            return;
+        }
+        for (target, trial) in self.targets.iter_mut().zip(population) {
+            let replaced = trial.fitness() >= target.1;
+            if replaced {
+                *target = (trial.chromosome().clone(), trial.fitness(), true);
+            } else {
+                target.2 = false;
+            }
+        }
+    }
+
+    fn breed(&self, rng: &mut dyn RngCore, child: usize, best: usize) -> Chromosome {
+        let mut excluded = vec![child];
+        if self.strategy == DifferentialStrategy::Best1Bin && best != child {
+            excluded.push(best);
+            excluded.sort_unstable();
+        }
+        // Distinct indices of targets other than the excluded ones
+        let picks: Vec<usize> = index::sample(rng, self.targets.len() - excluded.len(), self.donors())
+            .into_iter()
+            .map(|i| excluded.iter().fold(i, |i, &e| if i >= e { i + 1 } else { i }))
+            .collect();
+        let (base, a, b) = match self.strategy {
+            DifferentialStrategy::Rand1Bin => (picks[0], picks[1], picks[2]),
+            DifferentialStrategy::Best1Bin => (best, picks[0], picks[1]),
+        };
+        let [base_genes, a_genes, b_genes] = [base, a, b].map(|i| self.targets[i].0.genes());
+        let target = &self.targets[child].0;
+        let forced = rng.gen_range(0..target.genes().len().max(1));
+        let trial: Chromosome = target
+            .iter()
+            .enumerate()
+            .map(|(g, &gene)| {
+                if g == forced || rng.gen::<f32>() < self.crossover_rate {
+                    base_genes[g] + self.weight * (a_genes[g] - b_genes[g])
+                } else {
+                    gene
+                }
+            })
+            .collect();
+
+        if !self.observers.is_empty() {
+            // Parents are only reported while they are part of the population
+            // that was passed in, so that their index means something
+            for (parent, index) in [(Parent::First, child), (Parent::Second, base)] {
+                let (_, fitness, current) = self.targets[index];
+                if current {
+                    self.notify(|o| o.on_selection(child, parent, index, fitness));
+                }
+            }
+            let mutant: Chromosome = (0..target.genes().len())
+                .map(|g| base_genes[g] + self.weight * (a_genes[g] - b_genes[g]))
+                .collect();
+            self.notify(|o| o.on_crossover(child, target, &mutant, &trial));
+        }
+        trial
+    }
+}
+
+impl<T: Individual> Optimizer<T> for DifferentialEvolution {
+    fn evolve(&mut self, population: &[T], rng: &mut dyn RngCore) -> Result<Vec<T>, GeneticError> {
+        if population.is_empty() {
+            return Err(GeneticError::EmptyPopulation);
+        }
+        if population.len() < self.minimum_population() {
+            return Err(GeneticError::PopulationTooSmall { size: population.len(), required: self.minimum_population() });
+        }
+        let genes = population[0].chromosome().genes().len();
+        if let Some(other) = population.iter().find(|i| i.chromosome().genes().len() != genes) {
+            return Err(GeneticError::ChromosomeLength { expected: genes, actual: other.chromosome().genes().len() });
+        }
+        if self.targets.first().is_some_and(|(c, _, _)| c.genes().len() != genes) {
+            self.targets.clear();
+        }
+        self.update_targets(population);
+
+        let best = self.targets
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.1.total_cmp(&b.1))
+            .map_or(0, |(i, _)| i);
+        let offspring = (0..population.len())
+            .map(|child| T::create(self.breed(rng, child, best)))
+            .collect();
+
+        if !self.observers.is_empty() {
+            let summary = GenerationSummary::from_population(self.generation, population);
+            self.notify(|o| o.on_generation_end(&summary));
+        }
+        self.generation += 1;
+        Ok(offspring)
+    }
+
+    fn generation(&self) -> usize {
+        self.generation
+    }
+
+    fn minimum_population(&self) -> usize {
+        DifferentialEvolution::minimum_population(self)
+    }
+
+    fn add_observer(&mut self, observer: Box<dyn EvolutionObserver + Send + Sync>) {
+        self.observers.push(observer);
+    }
+
+    fn name(&self) -> &'static str {
+        match self.strategy {
+            DifferentialStrategy::Rand1Bin => "differential evolution (rand/1/bin)",
+            DifferentialStrategy::Best1Bin => "differential evolution (best/1/bin)",
+        }
+    }
+}
@@ -1,6 +1,18 @@
-use std::{ops::Index, vec::IntoIter};
+mod cma_es;
+mod differential;
+mod observer;
+mod optimizer;
+mod replacement;
+mod run;
 
-use rand::{distributions::WeightedError, seq::SliceRandom, Rng, RngCore};
+pub use self::{cma_es::*, differential::*, observer::*, optimizer::*, replacement::Replacement, run::*};
+use std::{fmt, ops::Index, sync::atomic::{AtomicUsize, Ordering}, vec::IntoIter};
+
+use rand::{distributions::{Distribution, Uniform, WeightedError}, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use thiserror::Error;
 
 
 pub trait Individual {
@@ -10,17 +22,58 @@ pub trait Individual {
 }
 
 pub trait Selection {
-    fn select<'a, T>(
-        &self,
-        rng: &mut dyn RngCore,
-        population: &'a [T],
-    ) -> Result<&'a T, GeneticError>
+    /// Index in `population` of the selected individual
+    fn select<T>(&self, rng: &mut dyn RngCore, population: &[T]) -> Result<usize, GeneticError>
     where
         T: Individual;
+
+    /// Like `select`, when the fitness of every individual of `population` is
+    /// already known, e.g. for a whole generation bred from it at once
+    fn select_with_fitness<T>(&self, rng: &mut dyn RngCore, population: &[T], _fitness: &[f32]) -> Result<usize, GeneticError>
+    where
+        T: Individual,
+    {
+        self.select(rng, population)
+    }
 }
 
+/// Object safe kind of `Selection`, which picks an individual knowing only the
+/// fitness of the whole population. Every `FitnessSelection` is a `Selection`,
+/// and `BoxedSelection` lets the selection method be chosen at runtime.
+pub trait FitnessSelection {
+    /// Index in `fitness` of the selected individual
+    fn select_index(&self, rng: &mut dyn RngCore, fitness: &[f32]) -> Result<usize, GeneticError>;
+}
 
-#[derive(Debug)]
+impl<F: FitnessSelection + ?Sized> Selection for F {
+    fn select<T>(&self, rng: &mut dyn RngCore, population: &[T]) -> Result<usize, GeneticError>
+    where
+        T: Individual,
+    {
+        let fitness: Vec<f32> = population.iter().map(Individual::fitness).collect();
+        self.select_index(rng, &fitness)
+    }
+
+    fn select_with_fitness<T>(&self, rng: &mut dyn RngCore, _population: &[T], fitness: &[f32]) -> Result<usize, GeneticError>
+    where
+        T: Individual,
+    {
+        self.select_index(rng, fitness)
+    }
+}
+
+impl<F: FitnessSelection + ?Sized> FitnessSelection for Box<F> {
+    fn select_index(&self, rng: &mut dyn RngCore, fitness: &[f32]) -> Result<usize, GeneticError> {
+        (**self).select_index(rng, fitness)
+    }
+}
+
+pub type BoxedSelection = Box<dyn FitnessSelection + Send + Sync>;
+pub type BoxedCrossover = Box<dyn Crossover + Send + Sync>;
+pub type BoxedMutation = Box<dyn Mutation + Send + Sync>;
+
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Chromosome {
     genes: Vec<f32>
 }
@@ -79,6 +132,17 @@ pub trait Crossover {
     ) -> Result<Chromosome, GeneticError>;
 }
 
+impl<C: Crossover + ?Sized> Crossover for Box<C> {
+    fn mix_parents(
+        &self,
+        rng: &mut dyn RngCore,
+        parent_a: &Chromosome,
+        parent_b: &Chromosome,
+    ) -> Result<Chromosome, GeneticError> {
+        (**self).mix_parents(rng, parent_a, parent_b)
+    }
+}
+
 pub struct UniformCrossover;
 
 impl Crossover for UniformCrossover {
@@ -89,7 +153,10 @@ impl Crossover for UniformCrossover {
         parent_b: &Chromosome,
     ) -> Result<Chromosome, GeneticError> {
         if parent_a.genes().len() != parent_b.genes().len() {
-            return Err(GeneticError::CrossoverError);
+            return Err(GeneticError::CrossoverError {
+                len_a: parent_a.genes().len(),
+                len_b: parent_b.genes().len()
+            });
         }
         Ok(Chromosome { 
             genes: parent_a.iter().zip(parent_b.iter()).map(|(&gene_a, &gene_b)| if rng.gen_bool(0.5) { gene_a } else { gene_b}).collect()
@@ -98,10 +165,39 @@ impl Crossover for UniformCrossover {
 }
 
 
+/// Takes the genes before a random cut point from the first parent, and the
+/// others from the second one
+pub struct SinglePointCrossover;
+
+impl Crossover for SinglePointCrossover {
+    fn mix_parents(
+        &self,
+        rng: &mut dyn RngCore,
+        parent_a: &Chromosome,
+        parent_b: &Chromosome,
+    ) -> Result<Chromosome, GeneticError> {
+        if parent_a.genes().len() != parent_b.genes().len() {
+            return Err(GeneticError::CrossoverError {
+                len_a: parent_a.genes().len(),
+                len_b: parent_b.genes().len()
+            });
+        }
+        let cut = rng.gen_range(0..=parent_a.genes().len());
+        Ok(parent_a.genes()[..cut].iter().chain(&parent_b.genes()[cut..]).copied().collect())
+    }
+}
+
+
 pub trait Mutation {
     fn mutate(&self, rng: &mut dyn RngCore, child: &mut Chromosome);
 }
 
+impl<M: Mutation + ?Sized> Mutation for Box<M> {
+    fn mutate(&self, rng: &mut dyn RngCore, child: &mut Chromosome) {
+        (**self).mutate(rng, child)
+    }
+}
+
 pub struct GaussianMutation {
     chance: f32,
     coeff: f32
@@ -109,8 +205,8 @@ pub struct GaussianMutation {
 
 impl GaussianMutation {
     pub fn new(chance: f32, coeff: f32) -> Result<Self, GeneticError> {
-        if chance < 0.0 || chance > 1.0 {
-            return Err(GeneticError::ValidationError)
+        if !(0.0..=1.0).contains(&chance) {
+            return Err(GeneticError::ValidationError { chance })
         }
         Ok(
             Self {
@@ -119,6 +215,14 @@ impl GaussianMutation {
             }
         )
     }
+
+    pub fn chance(&self) -> f32 {
+        self.chance
+    }
+
+    pub fn coeff(&self) -> f32 {
+        self.coeff
+    }
 }
 
 impl Mutation for GaussianMutation {
@@ -133,51 +237,266 @@ impl Mutation for GaussianMutation {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum GeneticError {
+    #[error("cannot evolve an empty population")]
     EmptyPopulation,
-    SelectionError,
-    CrossoverError,
-    ValidationError
+    #[error("individual {index} has fitness {fitness}, but selection needs finite non-negative values")]
+    InvalidFitness { index: usize, fitness: f32 },
+    #[error("cannot weight individuals by fitness: {0}")]
+    WeightError(#[from] WeightedError),
+    #[error("selected individual {index}, but the population only has {size}")]
+    SelectedOutOfRange { index: usize, size: usize },
+    #[error("cannot select the {parent} parent: {source}")]
+    SelectionError { parent: Parent, source: Box<GeneticError> },
+    #[error("cannot mix chromosomes of different lengths ({len_a} and {len_b} genes)")]
+    CrossoverError { len_a: usize, len_b: usize },
+    #[error("mutation chance must be between 0 and 1, got {chance}")]
+    ValidationError { chance: f32 },
+    #[error("{name} must be {expected}, got {value}")]
+    InvalidParameter { name: &'static str, value: f32, expected: &'static str },
+    #[error("population of {size} is too small, at least {required} individuals are needed")]
+    PopulationTooSmall { size: usize, required: usize },
+    #[error("expected chromosomes of {expected} genes, got one of {actual}")]
+    ChromosomeLength { expected: usize, actual: usize }
+}
+
+/// Which of the two parents of a child an error refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parent {
+    First,
+    Second
+}
+
+impl fmt::Display for Parent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Parent::First => write!(f, "first"),
+            Parent::Second => write!(f, "second"),
+        }
+    }
 }
 
 pub struct RouletteWheelSelection {}
 
-impl Selection for RouletteWheelSelection {
-    fn select<'a, T>(
-        &self,
-        rng: &mut dyn RngCore,
-        population: &'a [T],
-    ) -> Result<&'a T, GeneticError>
-    where
-        T: Individual,
-    {
-        let res = population
-            .choose_weighted(rng, |i| i.fitness());
-        match res {
-            Ok(r) => Ok(r),
-            Err(WeightedError::AllWeightsZero) => {
-                population.choose(rng).ok_or(GeneticError::SelectionError)
-            },
-            Err(e) => {
-                eprintln!("Cannot select individual: {:?}", e);
-                Err(GeneticError::SelectionError)
+impl FitnessSelection for RouletteWheelSelection {
+    fn select_index(&self, rng: &mut dyn RngCore, fitness: &[f32]) -> Result<usize, GeneticError> {
+        // `choose_weighted` rejects these too, but without telling who is to blame
+        let invalid = fitness
+            .iter()
+            .enumerate()
+            .find(|(_, f)| !f.is_finite() || **f < 0.0);
+        if let Some((index, &fitness)) = invalid {
+            return Err(GeneticError::InvalidFitness { index, fitness });
+        }
+        // Picks the same individuals as `SliceRandom::choose_weighted` would,
+        // without collecting the weights it sums up
+        let total: f32 = fitness.iter().sum();
+        match fitness.len() {
+            0 => Err(WeightedError::NoItem.into()),
+            // Nobody is fitter than anybody else: pick anyone
+            len if total == 0.0 => Ok(rng.gen_range(0..len as u32) as usize),
+            len => {
+                let point = Uniform::new(0.0, total).sample(rng);
+                let mut sum = 0.0;
+                Ok(fitness[..len - 1].iter().position(|&f| {
+                    sum += f;
+                    sum > point
+                }).unwrap_or(len - 1))
             }
         }
     }
 }
 
+/// Picks the fittest of `size` individuals drawn at random, with replacement
+pub struct TournamentSelection {
+    size: usize
+}
+
+impl TournamentSelection {
+    pub fn new(size: usize) -> Result<Self, GeneticError> {
+        if size == 0 {
+            return Err(GeneticError::InvalidParameter { name: "tournament size", value: 0.0, expected: "at least 1" });
+        }
+        Ok(Self { size })
+    }
+}
+
+impl FitnessSelection for TournamentSelection {
+    fn select_index(&self, rng: &mut dyn RngCore, fitness: &[f32]) -> Result<usize, GeneticError> {
+        if fitness.is_empty() {
+            return Err(GeneticError::EmptyPopulation);
+        }
+        (0..self.size)
+            .map(|_| rng.gen_range(0..fitness.len()))
+            .max_by(|&a, &b| fitness[a].total_cmp(&fitness[b]))
+            .ok_or(GeneticError::EmptyPopulation)
+    }
+}
+
+/// Like `RouletteWheelSelection`, but weighting individuals by their rank
+/// (1 for the least fit) instead of their fitness, so that any fitness,
+/// negative ones included, can be selected from
+pub struct RankSelection;
+
+impl FitnessSelection for RankSelection {
+    fn select_index(&self, rng: &mut dyn RngCore, fitness: &[f32]) -> Result<usize, GeneticError> {
+        let mut ranked: Vec<usize> = (0..fitness.len()).collect();
+        ranked.sort_by(|&a, &b| fitness[a].total_cmp(&fitness[b]));
+        let rank = rand::distributions::WeightedIndex::new(1..=fitness.len())
+            .map_err(|_| GeneticError::EmptyPopulation)?
+            .sample(rng);
+        Ok(ranked[rank])
+    }
+}
+
 pub struct GeneticAlgorithm<S: Selection, C: Crossover, M: Mutation>  {
     selection_method: S,
     crossover_algorithm: C,
-    mutation_algorithm: M
+    mutation_algorithm: M,
+    observers: Vec<Box<dyn EvolutionObserver + Send + Sync>>,
+    generation: AtomicUsize
 }
 
 impl<S: Selection, C: Crossover, M: Mutation> GeneticAlgorithm<S, C, M> {
     pub fn new(selection_method: S, crossover_algorithm: C, mutation_algorithm: M) -> Self {
-        Self{selection_method, crossover_algorithm, mutation_algorithm}
+        Self{selection_method, crossover_algorithm, mutation_algorithm, observers: vec![], generation: AtomicUsize::new(0)}
     }
-    
+
+    pub fn with_observer(mut self, observer: impl EvolutionObserver + Send + Sync + 'static) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    pub fn selection_method(&self) -> &S {
+        &self.selection_method
+    }
+
+    pub fn crossover_algorithm(&self) -> &C {
+        &self.crossover_algorithm
+    }
+
+    pub fn mutation_algorithm(&self) -> &M {
+        &self.mutation_algorithm
+    }
+
+    /// Number of generations evolved so far
+    pub fn generation(&self) -> usize {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    fn notify(&self, event: impl Fn(&dyn EvolutionObserver)) {
+        for observer in &self.observers {
+            event(observer.as_ref());
+        }
+    }
+
+    // Every child gets its own RNG stream seeded from the caller's RNG, so the
+    // offspring only depend on the seed and not on which thread breeds them
+    fn offspring_seeds(population_size: usize, rng: &mut dyn RngCore) -> Vec<u64> {
+        (0..population_size).map(|_| rng.next_u64()).collect()
+    }
+
+    fn select<'a, T>(&self, population: &'a [T], fitness: &[f32], rng: &mut dyn RngCore, child: usize, parent: Parent) -> Result<&'a T, GeneticError>
+    where
+        T: Individual,
+    {
+        let failed = |e| GeneticError::SelectionError { parent, source: Box::new(e) };
+        let index = self.selection_method.select_with_fitness(rng, population, fitness).map_err(failed)?;
+        // `Selection` is implemented outside this crate too
+        let selected = population.get(index).ok_or(GeneticError::SelectedOutOfRange { index, size: population.len() }).map_err(failed)?;
+        self.notify(|o| o.on_selection(child, parent, index, selected.fitness()));
+        Ok(selected)
+    }
+
+    // `fitness` is that of every individual of `population`, collected once
+    // for all the children bred from it
+    fn breed<T>(&self, population: &[T], fitness: &[f32], child: usize, seed: u64) -> Result<T, GeneticError>
+    where
+        T: Individual,
+    {
+        let rng = &mut ChaCha8Rng::seed_from_u64(seed);
+        let parent_a = self.select(population, fitness, rng, child, Parent::First)?;
+        let parent_b = self.select(population, fitness, rng, child, Parent::Second)?;
+        let mut new_chromosome = self.crossover_algorithm.mix_parents(rng, parent_a.chromosome(), parent_b.chromosome())?;
+        if self.observers.is_empty() {
+            self.mutation_algorithm.mutate(rng, &mut new_chromosome);
+        } else {
+            self.notify(|o| o.on_crossover(child, parent_a.chromosome(), parent_b.chromosome(), &new_chromosome));
+            let before = new_chromosome.clone();
+            self.mutation_algorithm.mutate(rng, &mut new_chromosome);
+            self.notify(|o| o.on_mutation(child, &before, &new_chromosome));
+        }
+        Ok(T::create(new_chromosome))
+    }
+
+    fn end_generation<T>(&self, population: &[T])
+    where
+        T: Individual,
+    {
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed);
+        if !self.observers.is_empty() {
+            let summary = GenerationSummary::from_population(generation, population);
+            self.notify(|o| o.on_generation_end(&summary));
+        }
+    }
+
+    /// Breeds `count` children from the whole population to take the place of
+    /// its `count` least fit individuals, each returned with the index it
+    /// replaces. Observers see those indices as the children's.
+    pub fn replace_worst<T>(&self, population: &[T], count: usize, rng: &mut dyn RngCore) -> Result<Vec<(usize, T)>, GeneticError>
+    where
+        T: Individual,
+    {
+        self.replace_at(population, &replacement::least_fit(population, count), rng)
+    }
+
+    /// Breeds a child from the whole population to take the place of each of
+    /// its individuals at `indices`, returned with the index it replaces
+    pub fn replace_at<T>(&self, population: &[T], indices: &[usize], rng: &mut dyn RngCore) -> Result<Vec<(usize, T)>, GeneticError>
+    where
+        T: Individual,
+    {
+        if population.is_empty() {
+            return Err(GeneticError::EmptyPopulation);
+        };
+        let fitness: Vec<f32> = population.iter().map(Individual::fitness).collect();
+        let children = Self::offspring_seeds(indices.len(), rng)
+            .into_iter()
+            .zip(indices)
+            .map(|(seed, &index)| self.breed(population, &fitness, index, seed).map(|child| (index, child)))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.end_generation(population);
+        Ok(children)
+    }
+
+    /// Breeds one child of `parent_a` and `parent_b` with this algorithm's
+    /// crossover and mutation, the parents being picked by the caller
+    pub fn reproduce<T>(&self, parent_a: &T, parent_b: &T, rng: &mut dyn RngCore) -> Result<T, GeneticError>
+    where
+        T: Individual,
+    {
+        let mut chromosome = self.crossover_algorithm.mix_parents(rng, parent_a.chromosome(), parent_b.chromosome())?;
+        self.mutation_algorithm.mutate(rng, &mut chromosome);
+        Ok(T::create(chromosome))
+    }
+
+    /// Like `evolve`, but only the part of `population` picked by
+    /// `replacement` makes room for children; the others survive as they are
+    pub fn evolve_with_replacement<T>(&self, mut population: Vec<T>, replacement: Replacement, rng: &mut dyn RngCore) -> Result<Vec<T>, GeneticError>
+    where
+        T: Individual,
+    {
+        let count = replacement.replaced(population.len());
+        for (index, child) in self.replace_worst(&population, count, rng)? {
+            population[index] = child;
+        }
+        Ok(population)
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+impl<S: Selection, C: Crossover, M: Mutation> GeneticAlgorithm<S, C, M> {
     pub fn evolve<T>(&self, population: &[T], rng: &mut dyn RngCore) -> Result<Vec<T>, GeneticError>
     where
         T: Individual,
@@ -185,22 +504,39 @@ impl<S: Selection, C: Crossover, M: Mutation> GeneticAlgorithm<S, C, M> {
         if population.is_empty() {
             return Err(GeneticError::EmptyPopulation);
         };
-        let result = (0..population.len())
-            .map(|_| {
-                let parent_a = self.selection_method
-                    .select(rng, population)
-                    .map_err(|e| eprintln!("Cannot select first parent: {e:?}"))
-                    .ok();
-                let parent_b = self.selection_method
-                    .select(rng, population)
-                    .map_err(|e| eprintln!("Cannot select second parent: {e:?}"))
-                    .ok();
-                let mut new_chromosome = self.crossover_algorithm.mix_parents(rng, parent_a.unwrap().chromosome(), parent_b.unwrap().chromosome()).expect("Cannot mix individuals!");    
-                self.mutation_algorithm.mutate(rng, &mut new_chromosome);
-                T::create(new_chromosome)
-            })
-            .collect();
-        Ok(result)
+        let fitness: Vec<f32> = population.iter().map(Individual::fitness).collect();
+        let offspring = Self::offspring_seeds(population.len(), rng)
+            .into_iter()
+            .enumerate()
+            .map(|(child, seed)| self.breed(population, &fitness, child, seed))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.end_generation(population);
+        Ok(offspring)
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<S, C, M> GeneticAlgorithm<S, C, M>
+where
+    S: Selection + Sync,
+    C: Crossover + Sync,
+    M: Mutation + Sync,
+{
+    pub fn evolve<T>(&self, population: &[T], rng: &mut dyn RngCore) -> Result<Vec<T>, GeneticError>
+    where
+        T: Individual + Send + Sync,
+    {
+        if population.is_empty() {
+            return Err(GeneticError::EmptyPopulation);
+        };
+        let fitness: Vec<f32> = population.iter().map(Individual::fitness).collect();
+        let offspring = Self::offspring_seeds(population.len(), rng)
+            .into_par_iter()
+            .enumerate()
+            .map(|(child, seed)| self.breed(population, &fitness, child, seed))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.end_generation(population);
+        Ok(offspring)
     }
 }
 
@@ -213,7 +549,7 @@ mod tests {
     #[derive(Debug)]
     struct TestIndividual {
         fitness: f32,
-        genes: Vec<f32>
+        chromosome: Chromosome
     }
 
     impl Individual for TestIndividual {
@@ -222,13 +558,13 @@ mod tests {
             self.fitness
         }
         fn chromosome(&self) -> &Chromosome {
-            todo!()
+            &self.chromosome
         }
         
         fn create(chromosome: Chromosome) -> Self {
             Self {
                 fitness: 0.0,
-                genes: chromosome.genes
+                chromosome
             }
         }
     }
@@ -242,16 +578,16 @@ mod tests {
         fn roulette_wheel_selection() {
             let mut rng = ChaCha8Rng::from_seed(Default::default());
             let population = vec![
-                TestIndividual { fitness: 4.0 , genes: vec![] },
-                TestIndividual { fitness: 1.0 , genes: vec![] },
-                TestIndividual { fitness: 5.0 , genes: vec![] },
-                TestIndividual { fitness: 10.0, genes: vec![] },
+                TestIndividual { fitness: 4.0 , chromosome: Chromosome { genes: vec![] } },
+                TestIndividual { fitness: 1.0 , chromosome: Chromosome { genes: vec![] } },
+                TestIndividual { fitness: 5.0 , chromosome: Chromosome { genes: vec![] } },
+                TestIndividual { fitness: 10.0, chromosome: Chromosome { genes: vec![] } },
             ];
             let mut actual_histogram: BTreeMap<i32, i32> = BTreeMap::new();
 
             let selection_method = RouletteWheelSelection {};
             _ = (0..100).map(|_| {
-                let fitness = selection_method.select(&mut rng, &population).expect("Failed to apply selection in tests").fitness();
+                let fitness = population[selection_method.select(&mut rng, &population).expect("Failed to apply selection in tests")].fitness();
                 *actual_histogram.entry(fitness as i32).or_insert(0) += 1;
                 fitness
             }).collect::<Vec<_>>();
@@ -264,6 +600,274 @@ mod tests {
             ]);
             assert_eq!(actual_histogram, expected_histogram);
         }
+
+        fn histogram(selection_method: &dyn FitnessSelection, fitness: &[f32]) -> Vec<usize> {
+            let mut rng = ChaCha8Rng::from_seed(Default::default());
+            let mut histogram = vec![0; fitness.len()];
+            for _ in 0..1000 {
+                histogram[selection_method.select_index(&mut rng, fitness).unwrap()] += 1;
+            }
+            histogram
+        }
+
+        #[test]
+        fn roulette_wheel_picks_anyone_without_fitness() {
+            assert!(histogram(&RouletteWheelSelection {}, &[0.0; 4]).iter().all(|&n| n > 200));
+            let histogram = histogram(&RouletteWheelSelection {}, &[0.0, 3.0, 0.0, 1.0]);
+            assert_eq!((histogram[0], histogram[2]), (0, 0));
+            assert!(histogram[1] > 2 * histogram[3]);
+        }
+
+        #[test]
+        fn tournament_selection() {
+            let fitness = [4.0, 1.0, 5.0, 10.0];
+            assert_eq!(histogram(&TournamentSelection::new(1).unwrap(), &fitness).iter().filter(|&&n| n > 0).count(), 4);
+            let histogram = histogram(&TournamentSelection::new(3).unwrap(), &fitness);
+            assert!(histogram[3] > histogram[2] && histogram[2] > histogram[0] && histogram[0] > histogram[1]);
+            assert!(TournamentSelection::new(0).is_err());
+        }
+
+        #[test]
+        fn rank_selection_accepts_negative_fitness() {
+            let histogram = histogram(&RankSelection, &[-4.0, -10.0, 3.0]);
+            assert!(histogram[2] > histogram[0] && histogram[0] > histogram[1]);
+        }
+
+        #[test]
+        fn boxed_selection() {
+            let mut rng = ChaCha8Rng::seed_from_u64(42);
+            let selection_method: BoxedSelection = Box::new(TournamentSelection::new(4).unwrap());
+            let population: Vec<TestIndividual> = (0..3).map(|i| TestIndividual {
+                fitness: i as f32,
+                chromosome: Chromosome { genes: vec![] }
+            }).collect();
+            let selected = (0..50).map(|_| population[selection_method.select(&mut rng, &population).unwrap()].fitness());
+            assert!(selected.into_iter().any(|f| f == 2.0));
+        }
+
+        // Picks an individual past the end of the population
+        struct OutOfRangeSelection;
+
+        impl Selection for OutOfRangeSelection {
+            fn select<T: Individual>(&self, _rng: &mut dyn RngCore, population: &[T]) -> Result<usize, GeneticError> {
+                Ok(population.len())
+            }
+        }
+
+        #[test]
+        fn rejects_selections_out_of_the_population() {
+            let mut rng = ChaCha8Rng::seed_from_u64(42);
+            let algorithm = GeneticAlgorithm::new(OutOfRangeSelection, UniformCrossover, GaussianMutation::new(0.0, 0.0).unwrap());
+            let population: Vec<TestIndividual> = (0..3).map(|i| TestIndividual {
+                fitness: i as f32,
+                chromosome: Chromosome { genes: vec![] }
+            }).collect();
+            let err = algorithm.evolve(&population, &mut rng).unwrap_err();
+            assert_eq!(err.to_string(), "cannot select the first parent: selected individual 3, but the population only has 3");
+        }
+    }
+
+    mod evolve {
+        use super::*;
+
+        fn population() -> Vec<TestIndividual> {
+            (0..20).map(|i| TestIndividual {
+                fitness: i as f32,
+                chromosome: (0..8).map(|g| (i * g) as f32).collect()
+            }).collect()
+        }
+
+        fn run(seed: u64) -> Vec<Vec<f32>> {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let algorithm = GeneticAlgorithm::new(
+                RouletteWheelSelection {},
+                UniformCrossover,
+                GaussianMutation::new(0.5, 0.5).unwrap()
+            );
+            algorithm.evolve(&population(), &mut rng)
+                .expect("Failed to evolve population in tests")
+                .into_iter()
+                .map(|i| i.chromosome.genes)
+                .collect()
+        }
+
+        #[test]
+        fn same_seed_gives_same_offspring() {
+            assert_eq!(run(42), run(42));
+            assert_ne!(run(42), run(43));
+        }
+
+        // Pinned so that `cargo test` and `cargo test --features parallel`
+        // both check that a seed breeds the same children either way
+        #[test]
+        fn seed_gives_the_same_offspring_with_or_without_parallel() {
+            let offspring = run(42);
+            assert_eq!(offspring.len(), 20);
+            assert_eq!(offspring[0], [-0.10195893, 4.4367356, 8.0, 53.67047, 16.26291, 90.0, 24.0, 126.1591]);
+            assert_eq!(offspring[10], [0.26833993, 19.163483, 38.48685, 56.64069, 72.05686, 90.0, 108.064804, 132.99272]);
+            assert_eq!(offspring[19], [0.3319467, 10.194616, 20.0, 30.404198, 51.760677, 50.45293, 60.0, 91.0]);
+        }
+
+        #[test]
+        fn empty_population_cannot_evolve() {
+            let mut rng = ChaCha8Rng::seed_from_u64(42);
+            let algorithm = GeneticAlgorithm::new(
+                RouletteWheelSelection {},
+                UniformCrossover,
+                GaussianMutation::new(0.5, 0.5).unwrap()
+            );
+            let population: Vec<TestIndividual> = vec![];
+            assert!(matches!(algorithm.evolve(&population, &mut rng), Err(GeneticError::EmptyPopulation)));
+        }
+
+        #[test]
+        fn negative_fitness_is_an_error() {
+            let mut rng = ChaCha8Rng::seed_from_u64(42);
+            let algorithm = GeneticAlgorithm::new(
+                RouletteWheelSelection {},
+                UniformCrossover,
+                GaussianMutation::new(0.5, 0.5).unwrap()
+            );
+            let mut population = population();
+            population[3].fitness = -2.0;
+            let err = algorithm.evolve(&population, &mut rng).expect_err("Negative fitness should not evolve");
+            assert!(matches!(
+                &err,
+                GeneticError::SelectionError { parent: Parent::First, source }
+                    if matches!(**source, GeneticError::InvalidFitness { index: 3, .. })
+            ));
+            assert_eq!(
+                err.to_string(),
+                "cannot select the first parent: individual 3 has fitness -2, but selection needs finite non-negative values"
+            );
+        }
+    }
+
+    mod observer {
+        use super::*;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Default)]
+        struct Recorder {
+            selections: Mutex<Vec<(usize, Parent, usize)>>,
+            crossovers: AtomicUsize,
+            mutations: AtomicUsize,
+            generations: Mutex<Vec<GenerationSummary>>
+        }
+
+        impl EvolutionObserver for Recorder {
+            fn on_selection(&self, child: usize, parent: Parent, index: usize, fitness: f32) {
+                assert_eq!(fitness, index as f32);
+                self.selections.lock().unwrap().push((child, parent, index));
+            }
+
+            fn on_crossover(&self, _child: usize, _parent_a: &Chromosome, _parent_b: &Chromosome, _offspring: &Chromosome) {
+                self.crossovers.fetch_add(1, Ordering::Relaxed);
+            }
+
+            fn on_mutation(&self, _child: usize, _before: &Chromosome, _after: &Chromosome) {
+                self.mutations.fetch_add(1, Ordering::Relaxed);
+            }
+
+            fn on_generation_end(&self, summary: &GenerationSummary) {
+                self.generations.lock().unwrap().push(*summary);
+            }
+        }
+
+        #[test]
+        fn observers_see_every_step() {
+            let mut rng = ChaCha8Rng::seed_from_u64(42);
+            let recorder = Arc::new(Recorder::default());
+            let algorithm = GeneticAlgorithm::new(
+                RouletteWheelSelection {},
+                UniformCrossover,
+                GaussianMutation::new(0.5, 0.5).unwrap()
+            ).with_observer(recorder.clone());
+            let population: Vec<TestIndividual> = (0..6).map(|i| TestIndividual {
+                fitness: i as f32,
+                chromosome: (0..3).map(|g| g as f32).collect()
+            }).collect();
+            algorithm.evolve(&population, &mut rng).unwrap();
+            algorithm.evolve(&population, &mut rng).unwrap();
+
+            let selections = recorder.selections.lock().unwrap();
+            assert_eq!(selections.len(), 2 * 2 * 6);
+            assert!(selections.iter().all(|&(child, _, index)| child < 6 && index < 6));
+            assert_eq!(selections.iter().filter(|(_, parent, _)| *parent == Parent::First).count(), 2 * 6);
+            assert_eq!(recorder.crossovers.load(Ordering::Relaxed), 2 * 6);
+            assert_eq!(recorder.mutations.load(Ordering::Relaxed), 2 * 6);
+            let generations = recorder.generations.lock().unwrap();
+            assert_eq!(generations.iter().map(|g| g.generation).collect::<Vec<_>>(), vec![0, 1]);
+            assert_eq!(generations[0].max_fitness, 5.0);
+            assert_eq!(algorithm.generation(), 2);
+        }
+    }
+
+    mod replacement {
+        use super::*;
+
+        fn population() -> Vec<TestIndividual> {
+            [3.0, 1.0, 4.0, 1.0, 5.0, 9.0].into_iter().map(|fitness| TestIndividual {
+                fitness,
+                chromosome: [fitness; 4].into_iter().collect()
+            }).collect()
+        }
+
+        #[test]
+        fn counts_replaced_individuals() {
+            assert_eq!(Replacement::Generational.replaced(10), 10);
+            assert_eq!(Replacement::SteadyState(2).replaced(10), 2);
+            assert_eq!(Replacement::SteadyState(20).replaced(10), 10);
+            assert_eq!(Replacement::GenerationalGap(0.25).replaced(10), 3);
+            assert_eq!(Replacement::GenerationalGap(0.0).replaced(10), 1);
+            assert_eq!(Replacement::GenerationalGap(0.5).replaced(0), 0);
+        }
+
+        #[test]
+        fn replaces_the_least_fit() {
+            let mut rng = ChaCha8Rng::seed_from_u64(42);
+            let algorithm = GeneticAlgorithm::new(
+                RouletteWheelSelection {},
+                UniformCrossover,
+                GaussianMutation::new(0.0, 0.0).unwrap()
+            );
+            let children = algorithm.replace_worst(&population(), 3, &mut rng).unwrap();
+            assert_eq!(children.iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![1, 3, 0]);
+
+            let evolved = algorithm.evolve_with_replacement(population(), Replacement::GenerationalGap(0.5), &mut rng).unwrap();
+            let survivors: Vec<f32> = evolved.iter().map(|i| i.fitness).collect();
+            assert_eq!(survivors, vec![0.0, 0.0, 4.0, 0.0, 5.0, 9.0]);
+            assert_eq!(algorithm.generation(), 2);
+
+            // Or any individuals the caller picks, the fittest included
+            let children = algorithm.replace_at(&population(), &[5, 2], &mut rng).unwrap();
+            assert_eq!(children.iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![5, 2]);
+        }
+    }
+
+    mod crossover {
+        use super::*;
+
+        #[test]
+        fn uniform_crossover_needs_same_length() {
+            let mut rng = ChaCha8Rng::seed_from_u64(42);
+            let parent_a: Chromosome = [1.0, 2.0, 3.0].into_iter().collect();
+            let parent_b: Chromosome = [1.0, 2.0].into_iter().collect();
+            let err = UniformCrossover.mix_parents(&mut rng, &parent_a, &parent_b).unwrap_err();
+            assert!(matches!(err, GeneticError::CrossoverError { len_a: 3, len_b: 2 }));
+            assert_eq!(err.to_string(), "cannot mix chromosomes of different lengths (3 and 2 genes)");
+        }
+
+        #[test]
+        fn single_point_crossover() {
+            let mut rng = ChaCha8Rng::seed_from_u64(42);
+            let parent_a: Chromosome = (0..10).map(|_| 1.0).collect();
+            let parent_b: Chromosome = (0..10).map(|_| 2.0).collect();
+            let crossover: BoxedCrossover = Box::new(SinglePointCrossover);
+            let child = crossover.mix_parents(&mut rng, &parent_a, &parent_b).unwrap();
+            let cut = child.iter().take_while(|&&g| g == 1.0).count();
+            assert!(child.iter().skip(cut).all(|&g| g == 2.0));
+        }
     }
     // fn it_works() {
     //     todo!()
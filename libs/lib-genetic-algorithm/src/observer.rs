@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use crate::{Chromosome, GenerationSummary, Parent};
+
+/// Hooks called by `GeneticAlgorithm::evolve` while it breeds a new
+/// generation.
+///
+/// Every method has an empty default, so observers only implement what they
+/// care about. Children are identified by their index in the new population;
+/// with the `parallel` feature the calls for different children can come from
+/// different threads and in any order.
+pub trait EvolutionObserver {
+    /// `index` is the position of the selected parent in the old population
+    fn on_selection(&self, _child: usize, _parent: Parent, _index: usize, _fitness: f32) {}
+
+    fn on_crossover(&self, _child: usize, _parent_a: &Chromosome, _parent_b: &Chromosome, _offspring: &Chromosome) {}
+
+    fn on_mutation(&self, _child: usize, _before: &Chromosome, _after: &Chromosome) {}
+
+    /// Called once the whole new generation has been bred, with the summary
+    /// of the generation it was bred from
+    fn on_generation_end(&self, _summary: &GenerationSummary) {}
+}
+
+impl<O: EvolutionObserver + ?Sized> EvolutionObserver for Arc<O> {
+    fn on_selection(&self, child: usize, parent: Parent, index: usize, fitness: f32) {
+        (**self).on_selection(child, parent, index, fitness)
+    }
+
+    fn on_crossover(&self, child: usize, parent_a: &Chromosome, parent_b: &Chromosome, offspring: &Chromosome) {
+        (**self).on_crossover(child, parent_a, parent_b, offspring)
+    }
+
+    fn on_mutation(&self, child: usize, before: &Chromosome, after: &Chromosome) {
+        (**self).on_mutation(child, before, after)
+    }
+
+    fn on_generation_end(&self, summary: &GenerationSummary) {
+        (**self).on_generation_end(summary)
+    }
+}
@@ -0,0 +1,275 @@
+use rand::RngCore;
+
+use crate::{replacement, Crossover, EvolutionObserver, GeneticAlgorithm, GeneticError, Individual, Mutation, Selection, UniformCrossover};
+
+/// Anything that turns a scored population into the next one to score.
+///
+/// Unlike `GeneticAlgorithm::evolve` this takes `&mut self`, since optimisers
+/// such as `DifferentialEvolution` and `CmaEs` carry state from one generation
+/// to the next. The trait is object safe, so callers can pick an optimiser at
+/// runtime with a `Box<dyn Optimizer<T>>`.
+pub trait Optimizer<T: Individual> {
+    fn evolve(&mut self, population: &[T], rng: &mut dyn RngCore) -> Result<Vec<T>, GeneticError>;
+
+    /// Breeds `count` children to take the place of the `count` least fit
+    /// individuals of `population`, each returned with the index it replaces
+    fn replace_worst(&mut self, population: &[T], count: usize, rng: &mut dyn RngCore) -> Result<Vec<(usize, T)>, GeneticError> {
+        self.replace_at(population, &replacement::least_fit(population, count), rng)
+    }
+
+    /// Breeds a child to take the place of each individual of `population`
+    /// at `indices`, returned with the index it replaces.
+    ///
+    /// By default the whole population is evolved, and only the children
+    /// born at those indices are kept.
+    fn replace_at(&mut self, population: &[T], indices: &[usize], rng: &mut dyn RngCore) -> Result<Vec<(usize, T)>, GeneticError> {
+        let mut children: Vec<Option<T>> = self.evolve(population, rng)?.into_iter().map(Some).collect();
+        Ok(indices.iter().filter_map(|&index| children.get_mut(index).and_then(Option::take).map(|child| (index, child))).collect())
+    }
+
+    /// Breeds one child of `parent_a` and `parent_b` (the same individual
+    /// twice for a child of a single parent), for populations where
+    /// individuals are born one at a time rather than a generation at once.
+    ///
+    /// By default the child takes each gene from either parent at random,
+    /// as with `UniformCrossover`; optimisers with operators of their own use
+    /// them instead.
+    fn reproduce(&self, parent_a: &T, parent_b: &T, rng: &mut dyn RngCore) -> Result<T, GeneticError> {
+        Ok(T::create(UniformCrossover.mix_parents(rng, parent_a.chromosome(), parent_b.chromosome())?))
+    }
+
+    /// Fewest individuals `evolve` can breed a population from
+    fn minimum_population(&self) -> usize {
+        1
+    }
+
+    /// Number of generations evolved so far
+    fn generation(&self) -> usize;
+
+    fn add_observer(&mut self, observer: Box<dyn EvolutionObserver + Send + Sync>);
+
+    /// Human readable name, e.g. for the header of an export
+    fn name(&self) -> &'static str;
+}
+
+impl<T: Individual, O: Optimizer<T> + ?Sized> Optimizer<T> for Box<O> {
+    fn evolve(&mut self, population: &[T], rng: &mut dyn RngCore) -> Result<Vec<T>, GeneticError> {
+        (**self).evolve(population, rng)
+    }
+
+    fn replace_worst(&mut self, population: &[T], count: usize, rng: &mut dyn RngCore) -> Result<Vec<(usize, T)>, GeneticError> {
+        (**self).replace_worst(population, count, rng)
+    }
+
+    fn replace_at(&mut self, population: &[T], indices: &[usize], rng: &mut dyn RngCore) -> Result<Vec<(usize, T)>, GeneticError> {
+        (**self).replace_at(population, indices, rng)
+    }
+
+    fn reproduce(&self, parent_a: &T, parent_b: &T, rng: &mut dyn RngCore) -> Result<T, GeneticError> {
+        (**self).reproduce(parent_a, parent_b, rng)
+    }
+
+    fn minimum_population(&self) -> usize {
+        (**self).minimum_population()
+    }
+
+    fn generation(&self) -> usize {
+        (**self).generation()
+    }
+
+    fn add_observer(&mut self, observer: Box<dyn EvolutionObserver + Send + Sync>) {
+        (**self).add_observer(observer)
+    }
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+impl<T, S, C, M> Optimizer<T> for GeneticAlgorithm<S, C, M>
+where
+    T: Individual,
+    S: Selection,
+    C: Crossover,
+    M: Mutation,
+{
+    fn evolve(&mut self, population: &[T], rng: &mut dyn RngCore) -> Result<Vec<T>, GeneticError> {
+        GeneticAlgorithm::evolve(self, population, rng)
+    }
+
+    fn replace_worst(&mut self, population: &[T], count: usize, rng: &mut dyn RngCore) -> Result<Vec<(usize, T)>, GeneticError> {
+        GeneticAlgorithm::replace_worst(self, population, count, rng)
+    }
+
+    fn replace_at(&mut self, population: &[T], indices: &[usize], rng: &mut dyn RngCore) -> Result<Vec<(usize, T)>, GeneticError> {
+        GeneticAlgorithm::replace_at(self, population, indices, rng)
+    }
+
+    fn reproduce(&self, parent_a: &T, parent_b: &T, rng: &mut dyn RngCore) -> Result<T, GeneticError> {
+        GeneticAlgorithm::reproduce(self, parent_a, parent_b, rng)
+    }
+
+    fn generation(&self) -> usize {
+        GeneticAlgorithm::generation(self)
+    }
+
+    fn add_observer(&mut self, observer: Box<dyn EvolutionObserver + Send + Sync>) {
+        self.observers.push(observer);
+    }
+
+    fn name(&self) -> &'static str {
+        "genetic algorithm"
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<T, S, C, M> Optimizer<T> for GeneticAlgorithm<S, C, M>
+where
+    T: Individual + Send + Sync,
+    S: Selection + Sync,
+    C: Crossover + Sync,
+    M: Mutation + Sync,
+{
+    fn evolve(&mut self, population: &[T], rng: &mut dyn RngCore) -> Result<Vec<T>, GeneticError> {
+        GeneticAlgorithm::evolve(self, population, rng)
+    }
+
+    fn replace_worst(&mut self, population: &[T], count: usize, rng: &mut dyn RngCore) -> Result<Vec<(usize, T)>, GeneticError> {
+        GeneticAlgorithm::replace_worst(self, population, count, rng)
+    }
+
+    fn replace_at(&mut self, population: &[T], indices: &[usize], rng: &mut dyn RngCore) -> Result<Vec<(usize, T)>, GeneticError> {
+        GeneticAlgorithm::replace_at(self, population, indices, rng)
+    }
+
+    fn reproduce(&self, parent_a: &T, parent_b: &T, rng: &mut dyn RngCore) -> Result<T, GeneticError> {
+        GeneticAlgorithm::reproduce(self, parent_a, parent_b, rng)
+    }
+
+    fn generation(&self) -> usize {
+        GeneticAlgorithm::generation(self)
+    }
+
+    fn add_observer(&mut self, observer: Box<dyn EvolutionObserver + Send + Sync>) {
+        self.observers.push(observer);
+    }
+
+    fn name(&self) -> &'static str {
+        "genetic algorithm"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    struct Point {
+        fitness: f32,
+        chromosome: Chromosome,
+    }
+
+    impl Individual for Point {
+        fn fitness(&self) -> f32 {
+            self.fitness
+        }
+
+        fn chromosome(&self) -> &Chromosome {
+            &self.chromosome
+        }
+
+        fn create(chromosome: Chromosome) -> Self {
+            let fitness = -chromosome.iter().map(|g| g * g).sum::<f32>();
+            Self { fitness, chromosome }
+        }
+    }
+
+    // Best fitness reached on the (negated) sphere function
+    fn minimise_sphere(optimizer: &mut dyn Optimizer<Point>, generations: usize) -> f32 {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let mut population: Vec<Point> = (0..12)
+            .map(|_| Point::create((0..5).map(|_| rng.gen_range(-5.0..5.0)).collect()))
+            .collect();
+        let mut best = f32::NEG_INFINITY;
+        for _ in 0..generations {
+            population = optimizer.evolve(&population, &mut rng).unwrap();
+            best = population.iter().map(|p| p.fitness).fold(best, f32::max);
+        }
+        best
+    }
+
+    #[test]
+    fn differential_evolution_minimises_sphere() {
+        for strategy in [DifferentialStrategy::Rand1Bin, DifferentialStrategy::Best1Bin] {
+            let mut optimizer = DifferentialEvolution::new(strategy, 0.8, 0.9).unwrap();
+            assert!(minimise_sphere(&mut optimizer, 300) > -1e-6, "{:?} did not converge", strategy);
+            assert_eq!(Optimizer::<Point>::generation(&optimizer), 300);
+        }
+    }
+
+    #[test]
+    fn best_1_bin_donors_are_neither_the_best_nor_the_target() {
+        // Every child takes its only gene from best + (a - b), best being 0
+        let population: Vec<Point> = [(0.0, 3.0), (1.0, 2.0), (10.0, 1.0), (100.0, 0.0)]
+            .into_iter()
+            .map(|(gene, fitness)| Point { fitness, chromosome: [gene].into_iter().collect() })
+            .collect();
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        for _ in 0..20 {
+            let mut optimizer = DifferentialEvolution::new(DifferentialStrategy::Best1Bin, 1.0, 0.0).unwrap();
+            let trials = optimizer.evolve(&population, &mut rng).unwrap();
+            let genes: Vec<f32> = trials.iter().map(|t| t.chromosome[0].abs()).collect();
+            assert_eq!(genes[1..], [90.0, 99.0, 9.0]);
+        }
+
+        let population = &population[..3];
+        assert!(matches!(
+            DifferentialEvolution::new(DifferentialStrategy::Best1Bin, 1.0, 0.0).unwrap().evolve(population, &mut rng),
+            Err(GeneticError::PopulationTooSmall { size: 3, required: 4 })
+        ));
+    }
+
+    #[test]
+    fn cma_es_minimises_sphere() {
+        let mut optimizer = CmaEs::new(2.0).unwrap();
+        assert!(minimise_sphere(&mut optimizer, 150) > -1e-6);
+        assert!(optimizer.sigma().unwrap() < 0.1);
+    }
+
+    #[test]
+    fn genetic_algorithm_is_an_optimizer() {
+        let mut optimizer = GeneticAlgorithm::new(
+            RouletteWheelSelection {},
+            UniformCrossover,
+            GaussianMutation::new(0.5, 0.5).unwrap()
+        );
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let population: Vec<Point> = (0..4).map(|i| Point { fitness: i as f32, chromosome: [0.0].into_iter().collect() }).collect();
+        let optimizer: &mut dyn Optimizer<Point> = &mut optimizer;
+        assert_eq!(optimizer.evolve(&population, &mut rng).unwrap().len(), 4);
+        assert_eq!(optimizer.generation(), 1);
+        assert_eq!(optimizer.name(), "genetic algorithm");
+    }
+
+    #[test]
+    fn rejects_bad_parameters() {
+        assert!(matches!(
+            DifferentialEvolution::new(DifferentialStrategy::Rand1Bin, 0.0, 0.5),
+            Err(GeneticError::InvalidParameter { name: "differential weight", .. })
+        ));
+        assert!(DifferentialEvolution::new(DifferentialStrategy::Rand1Bin, 0.5, 1.5).is_err());
+        let err = CmaEs::new(-1.0).err().unwrap();
+        assert_eq!(err.to_string(), "initial sigma must be positive, got -1");
+
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let mut optimizer = DifferentialEvolution::new(DifferentialStrategy::Rand1Bin, 0.5, 0.5).unwrap();
+        let population: Vec<Point> = (0..3).map(|_| Point::create([1.0].into_iter().collect())).collect();
+        assert!(matches!(
+            optimizer.evolve(&population, &mut rng),
+            Err(GeneticError::PopulationTooSmall { size: 3, required: 4 })
+        ));
+    }
+}
@@ -0,0 +1,37 @@
+use crate::Individual;
+
+/// How much of the population is replaced each time it is evolved
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Replacement {
+    /// Every individual is replaced by a child
+    #[default]
+    Generational,
+    /// Only the `n` least fit individuals are replaced, a few at a time
+    SteadyState(usize),
+    /// This fraction of the population, the least fit part, is replaced;
+    /// `1.0` is the same as `Generational`
+    GenerationalGap(f32),
+}
+
+impl Replacement {
+    /// How many individuals of a population of `size` get replaced; always
+    /// at least one, unless the population is empty
+    pub fn replaced(&self, size: usize) -> usize {
+        let count = match *self {
+            Self::Generational => size,
+            Self::SteadyState(n) => n,
+            Self::GenerationalGap(gap) => (gap * size as f32).round() as usize,
+        };
+        count.max(1).min(size)
+    }
+}
+
+/// Indices of the `count` least fit individuals, the worst first; ties go to
+/// the lowest index
+pub(crate) fn least_fit<T: Individual>(population: &[T], count: usize) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..population.len()).collect();
+    indices.sort_by(|&a, &b| population[a].fitness().total_cmp(&population[b].fitness()));
+    indices.truncate(count);
+    indices
+}
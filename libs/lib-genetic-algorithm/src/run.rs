@@ -0,0 +1,369 @@
+use std::time::{Duration, Instant};
+
+use crate::{GeneticError, Individual};
+
+/// When a run of repeated `evolve` calls should stop.
+///
+/// Conditions can be nested with `Any` and `All`, e.g. "stop after 500
+/// generations, or earlier if the best score did not move for 50 of them":
+///
+/// ```
+/// # use genetic_algorithm::StopCondition;
+/// let condition = StopCondition::Any(vec![
+///     StopCondition::Generations(500),
+///     StopCondition::Stagnation(50),
+/// ]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopCondition {
+    /// Stop once this many generations have been evaluated
+    Generations(usize),
+    /// Stop once an individual reaches at least this fitness
+    TargetFitness(f32),
+    /// Stop when the best fitness has not improved for this many generations
+    Stagnation(usize),
+    /// Stop once the run has taken longer than this
+    TimeBudget(Duration),
+    /// Stop when the mean per-gene standard deviation of the population
+    /// falls below this value, i.e. everybody carries (almost) the same genes
+    DiversityCollapse(f32),
+    /// Stop as soon as one of the conditions holds; never stops if empty
+    Any(Vec<StopCondition>),
+    /// Stop only when all the conditions hold at the same time; never stops
+    /// if empty either
+    All(Vec<StopCondition>),
+}
+
+/// Why a run stopped, i.e. which leaf of the `StopCondition` triggered
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopReason {
+    Generations(usize),
+    TargetFitness(f32),
+    Stagnation(usize),
+    TimeBudget(Duration),
+    DiversityCollapse(f32),
+    All(Vec<StopReason>),
+}
+
+impl StopCondition {
+    pub fn check(&self, progress: &RunProgress) -> Option<StopReason> {
+        match self {
+            Self::Generations(n) => (progress.generation >= *n).then_some(StopReason::Generations(*n)),
+            Self::TargetFitness(target) => (progress.best_fitness >= *target).then_some(StopReason::TargetFitness(*target)),
+            Self::Stagnation(k) => (progress.generations_without_improvement >= *k).then_some(StopReason::Stagnation(*k)),
+            Self::TimeBudget(budget) => (progress.elapsed() >= *budget).then_some(StopReason::TimeBudget(*budget)),
+            Self::DiversityCollapse(threshold) => (progress.last.diversity < *threshold).then_some(StopReason::DiversityCollapse(*threshold)),
+            Self::Any(conditions) => conditions.iter().find_map(|c| c.check(progress)),
+            Self::All(conditions) if conditions.is_empty() => None,
+            Self::All(conditions) => conditions
+                .iter()
+                .map(|c| c.check(progress))
+                .collect::<Option<Vec<_>>>()
+                .map(StopReason::All),
+        }
+    }
+}
+
+/// Fitness summary of one evaluated generation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GenerationSummary {
+    pub generation: usize,
+    pub min_fitness: f32,
+    pub avg_fitness: f32,
+    pub max_fitness: f32,
+    /// Mean over all genes of their standard deviation across the population
+    pub diversity: f32,
+}
+
+impl GenerationSummary {
+    pub fn from_population<T>(generation: usize, population: &[T]) -> Self
+    where
+        T: Individual,
+    {
+        let fitness = population.iter().map(|i| i.fitness());
+        let min_fitness = fitness.clone().fold(f32::INFINITY, f32::min);
+        let max_fitness = fitness.clone().fold(f32::NEG_INFINITY, f32::max);
+        let avg_fitness = fitness.sum::<f32>() / population.len().max(1) as f32;
+        Self {
+            generation,
+            min_fitness,
+            avg_fitness,
+            max_fitness,
+            diversity: gene_diversity(population),
+        }
+    }
+}
+
+fn gene_diversity<T>(population: &[T]) -> f32
+where
+    T: Individual,
+{
+    let Some(first) = population.first() else {
+        return 0.0;
+    };
+    let genes = first.chromosome().genes().len();
+    if genes == 0 {
+        return 0.0;
+    }
+    let size = population.len() as f32;
+    let total_std_dev: f32 = (0..genes)
+        .map(|g| {
+            let mean = population.iter().map(|i| i.chromosome()[g]).sum::<f32>() / size;
+            let variance = population.iter().map(|i| (i.chromosome()[g] - mean).powi(2)).sum::<f32>() / size;
+            variance.sqrt()
+        })
+        .sum();
+    total_std_dev / genes as f32
+}
+
+/// State of a run in progress, as seen by the `StopCondition`s
+#[derive(Debug, Clone)]
+pub struct RunProgress {
+    /// Number of generations evaluated so far
+    pub generation: usize,
+    /// Best fitness seen during the whole run
+    pub best_fitness: f32,
+    /// Generation where `best_fitness` was reached
+    pub best_generation: usize,
+    pub generations_without_improvement: usize,
+    /// Summary of the last evaluated generation
+    pub last: GenerationSummary,
+    started: Instant,
+}
+
+impl RunProgress {
+    fn new() -> Self {
+        Self {
+            generation: 0,
+            best_fitness: f32::NEG_INFINITY,
+            best_generation: 0,
+            generations_without_improvement: 0,
+            last: GenerationSummary {
+                generation: 0,
+                min_fitness: 0.0,
+                avg_fitness: 0.0,
+                max_fitness: 0.0,
+                diversity: f32::INFINITY,
+            },
+            started: Instant::now(),
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    fn record(&mut self, summary: GenerationSummary) {
+        self.generation += 1;
+        if summary.max_fitness > self.best_fitness {
+            self.best_fitness = summary.max_fitness;
+            self.best_generation = summary.generation;
+            self.generations_without_improvement = 0;
+        } else {
+            self.generations_without_improvement += 1;
+        }
+        self.last = summary;
+    }
+}
+
+/// What happened during a run driven by a `RunController`
+#[derive(Debug, Clone)]
+pub struct RunSummary {
+    pub generations: usize,
+    pub reason: StopReason,
+    pub best_fitness: f32,
+    pub best_generation: usize,
+    pub elapsed: Duration,
+    pub history: Vec<GenerationSummary>,
+}
+
+/// Drives repeated evolution until its `StopCondition` holds
+#[derive(Debug, Clone)]
+pub struct RunController {
+    condition: StopCondition,
+}
+
+impl RunController {
+    pub fn new(condition: StopCondition) -> Self {
+        Self { condition }
+    }
+
+    pub fn condition(&self) -> &StopCondition {
+        &self.condition
+    }
+
+    /// Calls `next_generation` with the index of the generation to produce
+    /// until the stop condition holds.
+    ///
+    /// `next_generation` must return the population of that generation once
+    /// its fitness is known; how it gets there (evaluating a function,
+    /// running a whole simulated world, ...) is up to the caller.
+    pub fn run<T, F>(&self, mut next_generation: F) -> Result<RunSummary, GeneticError>
+    where
+        T: Individual,
+        F: FnMut(usize) -> Result<Vec<T>, GeneticError>,
+    {
+        let mut progress = RunProgress::new();
+        let mut history = vec![];
+        let reason = loop {
+            let population = next_generation(progress.generation)?;
+            if population.is_empty() {
+                return Err(GeneticError::EmptyPopulation);
+            }
+            let summary = GenerationSummary::from_population(progress.generation, &population);
+            progress.record(summary);
+            history.push(summary);
+            if let Some(reason) = self.condition.check(&progress) {
+                break reason;
+            }
+        };
+        Ok(RunSummary {
+            generations: progress.generation,
+            reason,
+            best_fitness: progress.best_fitness,
+            best_generation: progress.best_generation,
+            elapsed: progress.elapsed(),
+            history,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[derive(Clone)]
+    struct Genome {
+        fitness: f32,
+        chromosome: Chromosome,
+    }
+
+    impl Individual for Genome {
+        fn fitness(&self) -> f32 {
+            self.fitness
+        }
+
+        fn chromosome(&self) -> &Chromosome {
+            &self.chromosome
+        }
+
+        fn create(chromosome: Chromosome) -> Self {
+            Self { fitness: 0.0, chromosome }
+        }
+    }
+
+    fn constant_population(fitness: f32) -> Vec<Genome> {
+        (0..4).map(|_| Genome { fitness, chromosome: [1.0, 2.0].into_iter().collect() }).collect()
+    }
+
+    #[test]
+    fn stops_after_generations() {
+        let summary = RunController::new(StopCondition::Generations(5))
+            .run(|_| Ok(constant_population(1.0)))
+            .unwrap();
+        assert_eq!(summary.generations, 5);
+        assert_eq!(summary.history.len(), 5);
+        assert_eq!(summary.reason, StopReason::Generations(5));
+    }
+
+    #[test]
+    fn stops_when_target_is_reached() {
+        let summary = RunController::new(StopCondition::Any(vec![
+            StopCondition::Generations(100),
+            StopCondition::TargetFitness(3.0),
+        ]))
+        .run(|generation| Ok(constant_population(generation as f32)))
+        .unwrap();
+        assert_eq!(summary.generations, 4);
+        assert_eq!(summary.reason, StopReason::TargetFitness(3.0));
+        assert_eq!(summary.best_generation, 3);
+    }
+
+    #[test]
+    fn stops_on_stagnation() {
+        // The first generation always counts as an improvement
+        let summary = RunController::new(StopCondition::Stagnation(3))
+            .run(|_| Ok(constant_population(2.0)))
+            .unwrap();
+        assert_eq!(summary.generations, 4);
+        assert_eq!(summary.reason, StopReason::Stagnation(3));
+    }
+
+    #[test]
+    fn all_needs_every_condition() {
+        let summary = RunController::new(StopCondition::All(vec![
+            StopCondition::Generations(2),
+            StopCondition::TargetFitness(5.0),
+        ]))
+        .run(|generation| Ok(constant_population(generation as f32)))
+        .unwrap();
+        assert_eq!(summary.generations, 6);
+        assert_eq!(
+            summary.reason,
+            StopReason::All(vec![StopReason::Generations(2), StopReason::TargetFitness(5.0)])
+        );
+    }
+
+    #[test]
+    fn empty_conditions_never_hold() {
+        let summary = RunController::new(StopCondition::Any(vec![
+            StopCondition::All(vec![]),
+            StopCondition::Any(vec![]),
+            StopCondition::Generations(3),
+        ]))
+        .run(|_| Ok(constant_population(1.0)))
+        .unwrap();
+        assert_eq!(summary.generations, 3);
+        assert_eq!(summary.reason, StopReason::Generations(3));
+    }
+
+    #[test]
+    fn stops_when_diversity_collapses() {
+        let summary = RunController::new(StopCondition::DiversityCollapse(0.01))
+            .run(|_| Ok(constant_population(1.0)))
+            .unwrap();
+        assert_eq!(summary.generations, 1);
+        assert_eq!(summary.history[0].diversity, 0.0);
+    }
+
+    #[test]
+    fn empty_population_stops_the_run() {
+        let result = RunController::new(StopCondition::Generations(5)).run(|_| Ok(Vec::<Genome>::new()));
+        assert!(matches!(result, Err(GeneticError::EmptyPopulation)));
+    }
+
+    #[test]
+    fn drives_a_genetic_algorithm() {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let algorithm = GeneticAlgorithm::new(
+            RouletteWheelSelection {},
+            UniformCrossover,
+            GaussianMutation::new(0.2, 0.5).unwrap(),
+        );
+        let evaluate = |chromosome: Chromosome| Genome {
+            fitness: chromosome.iter().map(|g| g.max(0.0)).sum(),
+            chromosome,
+        };
+        let mut population: Vec<Genome> = (0..20)
+            .map(|_| evaluate((0..5).map(|_| rng.gen::<f32>()).collect()))
+            .collect();
+        let summary = RunController::new(StopCondition::Generations(30))
+            .run(|generation| {
+                if generation > 0 {
+                    population = algorithm
+                        .evolve(&population, &mut rng)?
+                        .into_iter()
+                        .map(|i| evaluate(i.chromosome))
+                        .collect();
+                }
+                Ok(population.clone())
+            })
+            .unwrap();
+        let first = summary.history.first().unwrap();
+        let last = summary.history.last().unwrap();
+        assert!(last.avg_fitness > first.avg_fitness);
+    }
+}
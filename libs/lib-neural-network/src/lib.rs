@@ -1,4 +1,4 @@
-use std::{fmt::format, iter::once};
+use std::iter::once;
 
 use rand::{Rng, RngCore};
 
@@ -18,7 +18,7 @@ impl NeuralNetwork {
 
     pub fn random(layers: Vec<LayerTopology>, rng: &mut dyn RngCore) -> Result<Self, String> {
         if layers.len() <= 1 {
-            return Err(format!("Network must have at least 2 or more layers"))
+            return Err("Network must have at least 2 or more layers".to_string())
         }
         let built_layers = layers.windows(2).map(|layer_flow| {
             Layer::random(layer_flow[0].neurons, layer_flow[1].neurons, rng)
@@ -34,7 +34,7 @@ impl NeuralNetwork {
     }
     
     pub fn from_weights(layers: &[LayerTopology], weights: &[f32]) -> Result<Self, String> {
-        if layers.len() < 1 {
+        if layers.is_empty() {
             return Err("At least one layer needed".into());
         }
         let mut read_weigths = 0;
@@ -42,7 +42,7 @@ impl NeuralNetwork {
         let mut built_layers = vec![];
         for layer in layers.iter().skip(1) {
             let mut layer_neurons = vec![];
-            for i in (0..layer.neurons) {
+            for _ in 0..layer.neurons {
                 let bias = weights[read_weigths];
                 let neuron_weigths = &weights[read_weigths+1..read_weigths+1+current_layer_inputs];
                 layer_neurons.push(Neuron{
@@ -72,7 +72,7 @@ pub struct Layer {
 impl Layer {
     fn propagate(&self, inputs: &[f32]) -> Vec<f32> {
         self.neurons.iter().map(|n| {
-            n.propagate(inputs.clone()).map_err(|e| eprintln!("[ERR] {}", e)).unwrap()
+            n.propagate(inputs).map_err(|e| eprintln!("[ERR] {}", e)).unwrap()
         }).collect()
     }
 
@@ -100,7 +100,7 @@ impl Neuron {
         Ok((inputs.iter().zip(self.weights.iter()).map(|(&i, &w)| i * w).sum::<f32>() + self.bias).max(0.0))
     }
 
-    fn get_weights(&self) -> &[f32] {
+    pub fn get_weights(&self) -> &[f32] {
         self.weights.as_slice()
     }
 
@@ -127,14 +127,14 @@ mod test {
 
         #[test]
         fn can_create_random_neurons() {
-            let mut rng = ChaCha8Rng::from_seed(ChaCha8Rng::seed_from_u64(10495 as u64).get_seed());
+            let mut rng = ChaCha8Rng::from_seed(ChaCha8Rng::seed_from_u64(10495).get_seed());
             let first_neuron = Neuron::random(2, &mut rng);
             let second_neuron = Neuron::random(3, &mut rng);
 
             approx::assert_relative_eq!(first_neuron.bias, 0.036300004);
             approx::assert_relative_eq!(first_neuron.get_weights(), [0.81902564, 0.21760976].as_slice());
 
-            approx::assert_relative_eq!(second_neuron.bias, 0.0117205980);
+            approx::assert_relative_eq!(second_neuron.bias, 0.011720598);
             approx::assert_relative_eq!(second_neuron.get_weights(), [0.97990996, 0.6928334, 0.29998326].as_slice());
         }
         
@@ -145,7 +145,7 @@ mod test {
 
         #[test]
         fn can_create_layers_from_topology() {
-            let mut rng = ChaCha8Rng::from_seed(ChaCha8Rng::seed_from_u64(140516 as u64).get_seed());
+            let mut rng = ChaCha8Rng::from_seed(ChaCha8Rng::seed_from_u64(140516).get_seed());
             let first_layer = Layer::random(2, 3, &mut rng);
             let second_layer = Layer::random(5, 2, &mut rng);
 
@@ -169,7 +169,7 @@ mod test {
 
         #[test]
         fn can_not_create_network_without_layers_topology() {
-            let mut rng = ChaCha8Rng::from_seed(ChaCha8Rng::seed_from_u64(91295 as u64).get_seed());
+            let mut rng = ChaCha8Rng::from_seed(ChaCha8Rng::seed_from_u64(91295).get_seed());
             let invalid_network = NeuralNetwork::random(vec![], &mut rng);
 
             assert!(invalid_network.is_err());
@@ -178,7 +178,7 @@ mod test {
 
         #[test]
         fn can_not_create_network_with_one_layer_topology() {
-            let mut rng = ChaCha8Rng::from_seed(ChaCha8Rng::seed_from_u64(91295 as u64).get_seed());
+            let mut rng = ChaCha8Rng::from_seed(ChaCha8Rng::seed_from_u64(91295).get_seed());
             let invalid_network = NeuralNetwork::random(vec![LayerTopology{neurons: 42}], &mut rng);
 
             assert!(invalid_network.is_err());
@@ -187,7 +187,7 @@ mod test {
 
         #[test]
         fn can_create_network_with_enough_layers() {
-            let mut rng = ChaCha8Rng::from_seed(ChaCha8Rng::seed_from_u64(91295 as u64).get_seed());
+            let mut rng = ChaCha8Rng::from_seed(ChaCha8Rng::seed_from_u64(91295).get_seed());
             let network = NeuralNetwork::random(vec![LayerTopology{neurons: 1}, LayerTopology{ neurons: 4}, LayerTopology{ neurons: 2}], &mut rng);
             /*
              * 
@@ -213,17 +213,17 @@ mod test {
             assert!(network.is_ok());
             let network = network.unwrap();
             assert_eq!(network.get_layers().len(), 2);
-            let mut layers = network.get_layers().into_iter();
+            let mut layers = network.get_layers().iter();
             let layer = layers.next().unwrap();
             assert_eq!(layer.get_neurons().len(), 4);
-            let mut layers_neurons = layer.get_neurons().into_iter();
+            let mut layers_neurons = layer.get_neurons().iter();
             let first_neuron = layers_neurons.next().unwrap();
             approx::assert_relative_eq!(first_neuron.bias, 0.024323463);
             assert_eq!(first_neuron.get_weights().len(), 1);
             approx::assert_relative_eq!(first_neuron.get_weights(), &[0.55383307].as_slice());
             let layer = layers.next().unwrap();
             assert_eq!(layer.get_neurons().len(), 2);
-            let mut layers_neurons = layer.get_neurons().into_iter();
+            let mut layers_neurons = layer.get_neurons().iter();
             let neuron = layers_neurons.next().unwrap();
             assert_eq!(neuron.get_weights().len(), 4);
             approx::assert_relative_eq!(neuron.bias, 0.65961576);
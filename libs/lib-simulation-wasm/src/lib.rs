@@ -1,18 +1,33 @@
-use lib_simulation;
 use wasm_bindgen::prelude::*;
-use rand::prelude::*;
 
 #[wasm_bindgen]
 pub fn whos_that_dog() -> String {
     "Mister Peanutbutter".into()
 }
 
+fn random_seed() -> u64 {
+    rand::random()
+}
+
 #[wasm_bindgen]
 #[derive(Debug,Clone)]
 pub struct Statistics {
-    pub min_score: u32,
+    pub min_score: f32,
     pub avg_score: f32,
-    pub max_score: u32
+    pub max_score: f32,
+    pub median_score: f32,
+    pub std_dev_score: f32,
+    pub mean_pairwise_distance: f32,
+    pub distinct_brains: usize,
+    pub food_eaten: u32,
+    pub collisions: u32,
+    pub mean_speed: f32,
+    pub starved: u32,
+    pub population: usize,
+    pub births: u32,
+    pub food_regrown: u32,
+    pub food_rotten: u32,
+    pub mean_food_available: f32
 }
 
 impl From<&lib_simulation::Statistics> for Statistics {
@@ -20,15 +35,27 @@ impl From<&lib_simulation::Statistics> for Statistics {
         Self {
             min_score: value.min_score,
             avg_score: value.avg_score,
-            max_score: value.max_score
+            max_score: value.max_score,
+            median_score: value.median_score,
+            std_dev_score: value.std_dev_score,
+            mean_pairwise_distance: value.mean_pairwise_distance,
+            distinct_brains: value.distinct_brains,
+            food_eaten: value.food_eaten,
+            collisions: value.collisions,
+            mean_speed: value.mean_speed,
+            starved: value.starved,
+            population: value.population,
+            births: value.births,
+            food_regrown: value.food_regrown,
+            food_rotten: value.food_rotten,
+            mean_food_available: value.mean_food_available
         }
     }
 }
 
 
 #[wasm_bindgen]
-struct Simulation {
-    rng: ThreadRng,
+pub struct Simulation {
     sim: lib_simulation::Simulation
 }
 
@@ -36,10 +63,77 @@ struct Simulation {
 impl Simulation {
     #[wasm_bindgen(constructor)]
     pub fn new(animals: usize, food: usize, mut_chance: f32, mut_coeff: f32, age: u32) -> Self {
-        let mut rng = thread_rng();
-        let sim = lib_simulation::Simulation::random(&mut rng, animals, food, mut_chance, mut_coeff, age);
+        let sim = lib_simulation::Simulation::random(random_seed(), animals, food, mut_chance, mut_coeff, age);
+
+        Self { sim }
+    }
+
+    /// Like `new`, with the selection (`"roulette"`, `"tournament"` or
+    /// `"rank"`) and crossover (`"uniform"` or `"single-point"`) picked by name
+    #[wasm_bindgen(js_name = withOperators)]
+    pub fn with_operators(animals: usize, food: usize, mut_chance: f32, mut_coeff: f32, age: u32, selection: &str, crossover: &str) -> Result<Simulation, JsError> {
+        let selection: lib_simulation::BoxedSelection = match selection {
+            "roulette" => Box::new(lib_simulation::RouletteWheelSelection {}),
+            "tournament" => Box::new(lib_simulation::TournamentSelection::new(3)?),
+            "rank" => Box::new(lib_simulation::RankSelection),
+            other => return Err(JsError::new(&format!("unknown selection method {other:?}"))),
+        };
+        let crossover: lib_simulation::BoxedCrossover = match crossover {
+            "uniform" => Box::new(lib_simulation::UniformCrossover),
+            "single-point" => Box::new(lib_simulation::SinglePointCrossover),
+            other => return Err(JsError::new(&format!("unknown crossover {other:?}"))),
+        };
+        let sim = lib_simulation::SimulationBuilder::new()
+            .animals(animals)
+            .food(food)
+            .generation_length(age)
+            .selection(selection)
+            .crossover(crossover)
+            .mutation(lib_simulation::GaussianMutation::new(mut_chance, mut_coeff)?)
+            .build()?;
+
+        Ok(Self { sim })
+    }
+
+    /// Builds a simulation from a JSON `SimulationConfig`, where missing
+    /// fields take their default value. Passing the `seed` of an earlier
+    /// simulation with the same config replays it exactly.
+    #[wasm_bindgen(js_name = fromConfig)]
+    pub fn from_config(config: &str, seed: Option<u64>) -> Result<Simulation, JsError> {
+        let config = lib_simulation::SimulationConfig::from_json(config)?;
+        let sim = lib_simulation::Simulation::from_config(seed.unwrap_or_else(random_seed), config)?;
+
+        Ok(Self { sim })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.sim.seed()
+    }
 
-        Self { rng, sim }
+    /// Snapshot of the whole simulation as JSON, to be handed back to
+    /// `fromSnapshot` later, e.g. through local storage
+    pub fn snapshot(&self) -> Result<String, JsError> {
+        let mut out = vec![];
+        self.sim.save(&mut out)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    /// Resumes a simulation from a JSON snapshot taken by `snapshot`
+    #[wasm_bindgen(js_name = fromSnapshot)]
+    pub fn from_snapshot(snapshot: &str) -> Result<Simulation, JsError> {
+        let sim = lib_simulation::Simulation::load(snapshot.as_bytes())?;
+
+        Ok(Self { sim })
+    }
+
+    /// The default `SimulationConfig` as JSON, listing every knob
+    #[wasm_bindgen(js_name = defaultConfig)]
+    pub fn default_config() -> String {
+        lib_simulation::SimulationConfig::default().to_json()
+    }
+
+    pub fn config(&self) -> String {
+        self.sim.config().to_json()
     }
 
     pub fn world(&self) -> World {
@@ -47,11 +141,12 @@ impl Simulation {
     }
 
 
-    pub fn step(&mut self) -> Option<Statistics> {
-        if let Some(stats) = self.sim.step(&mut self.rng) {
-            return Some(Statistics::from(&stats));
-        }
-        None
+    pub fn generation(&self) -> usize {
+        self.sim.generation()
+    }
+
+    pub fn step(&mut self) -> Result<Option<Statistics>, JsError> {
+        Ok(self.sim.step()?.map(|stats| Statistics::from(&stats)))
     }
 }
 
@@ -59,14 +154,16 @@ impl Simulation {
 #[derive(Clone,  Debug)]
 pub struct Food {
     pub x: f32,
-    pub y: f32
+    pub y: f32,
+    pub kind: usize
 }
 
 #[wasm_bindgen]
 #[derive(Clone, Debug)]
 pub struct Animal {
     pub x: f32,
-    pub y: f32
+    pub y: f32,
+    pub exhausted: bool
 }
 
 #[wasm_bindgen]
@@ -75,7 +172,9 @@ pub struct World {
     #[wasm_bindgen(getter_with_clone)]
     pub animals: Vec<Animal>,
     #[wasm_bindgen(getter_with_clone)]
-    pub food: Vec<Food>
+    pub food: Vec<Food>,
+    #[wasm_bindgen(getter_with_clone)]
+    pub predators: Vec<Animal>
 }
 
 impl From<&lib_simulation::Food> for Food {
@@ -83,7 +182,8 @@ impl From<&lib_simulation::Food> for Food {
         let orig_pos = value.position();
         Self {
             x: orig_pos.x,
-            y: orig_pos.y
+            y: orig_pos.y,
+            kind: value.kind()
         }
     }
 }
@@ -93,7 +193,8 @@ impl From<&lib_simulation::Animal> for Animal {
         let orig_pos = value.position();
         Self {
             x: orig_pos.x,
-            y: orig_pos.y
+            y: orig_pos.y,
+            exhausted: value.is_exhausted()
         }
     }
 }
@@ -103,7 +204,8 @@ impl From<&lib_simulation::World> for World {
     fn from(orig: &lib_simulation::World) -> Self {
         Self {
             animals: orig.animals().iter().map(Animal::from).collect(),
-            food: orig.food().iter().map(Food::from).collect()
+            food: orig.food().iter().filter(|f| f.is_available()).map(Food::from).collect(),
+            predators: orig.predators().iter().map(Animal::from).collect()
         }
     }
 }
@@ -0,0 +1,47 @@
+//! `cargo bench -p lib-simulation`, with `--features parallel` to think on
+//! every core
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use lib_simulation::{Simulation, SpatialGrid, Topology};
+use nalgebra::Point2;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+const BIRDS: [usize; 2] = [1_000, 10_000];
+
+fn step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+    group.sample_size(10);
+    for birds in BIRDS {
+        // As much food as birds, and generations long enough not to evolve
+        // while measuring
+        let mut simulation = Simulation::random(42, birds, birds, 0.01, 0.3, u32::MAX);
+        group.bench_function(BenchmarkId::from_parameter(birds), |b| b.iter(|| simulation.step().unwrap()));
+    }
+    group.finish();
+}
+
+fn queries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("within");
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+    for birds in BIRDS {
+        let points: Vec<Point2<f32>> = (0..birds).map(|_| rng.gen()).collect();
+        let centers: Vec<Point2<f32>> = (0..100).map(|_| rng.gen()).collect();
+        let grid = SpatialGrid::new(Topology::Torus, &points);
+        group.bench_function(BenchmarkId::new("grid", birds), |b| {
+            b.iter(|| centers.iter().map(|&c| grid.within(c, 0.05).count()).sum::<usize>())
+        });
+        group.bench_function(BenchmarkId::new("full scan", birds), |b| {
+            b.iter(|| {
+                centers
+                    .iter()
+                    .map(|&c| points.iter().filter(|&&p| Topology::Torus.distance(c, p) <= 0.05).count())
+                    .sum::<usize>()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, step, queries);
+criterion_main!(benches);
@@ -1,73 +1,103 @@
 use neural_network as nn;
 use nalgebra::geometry::Point2;
-use nalgebra::{Rotation2, wrap, distance};
+use nalgebra::Rotation2;
 use genetic_algorithm::{Individual, Chromosome};
 use nn::{LayerTopology, NeuralNetwork};
-use rand::{rngs, Rng, RngCore};
-
-use crate::eye::{Eye, CELLS};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::eye::Eye;
+use crate::obstacle;
+use crate::sensor;
+use crate::genealogy::AnimalId;
+use crate::{BrainConfig, EnergyConfig, SimulationConfig};
+
+/// Kind of bird, each evolved on its own and scored its own way: prey by
+/// the food they eat, less the times they are caught, predators by the prey
+/// they catch
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Species {
+    #[default]
+    Prey,
+    Predator,
+}
 
 #[derive(Debug)]
 pub struct Animal {
+    pub(crate) id: AnimalId,
+    pub(crate) species: Species,
     pub(crate) eye: Eye,
     pub(crate) brain: nn::NeuralNetwork,
     pub(crate) position: Point2<f32>,
     pub(crate) rotation: Rotation2<f32>,
     pub(crate) speed: f32,
     pub(crate) score: i32,
+    /// Number of steps the bird has been flying for
+    pub(crate) age: u32,
+    /// Energy left, only spent when the config says birds run on energy
+    pub(crate) energy: f32,
+    /// Whether the bird ran out of energy, and so no longer flies
+    pub(crate) exhausted: bool,
 }
 
 
-#[derive(Debug)]
-pub struct Brain {
-    nerual_network: NeuralNetwork
-}
+impl Animal {
+    pub fn brain_topology_from_eyes(eye: &Eye) -> Vec<LayerTopology>{
+        Self::brain_topology(eye, &BrainConfig::default())
+    }
 
-impl Brain {
-    pub fn topology(eye: &Eye) -> Vec<LayerTopology> {
-        vec![
-            nn::LayerTopology{ neurons: eye.cells() * 2 }, // Input is vision for food and vision for other animals
-            nn::LayerTopology{ neurons: 10 },
-            nn::LayerTopology{ neurons: 5 },
-            nn::LayerTopology{ neurons: 10 },
-                nn::LayerTopology{ neurons: 2 } // Output is rotation angle and speed
-            ]
+    /// One input per eye cell and channel (food, birds of each species,
+    /// obstacles) the eye sees, then the hidden layers of `brain`
+    pub fn brain_topology(eye: &Eye, brain: &BrainConfig) -> Vec<LayerTopology> {
+        std::iter::once(eye.cells() * eye.channels().len())
+            .chain(brain.hidden_layers.iter().copied())
+            .chain(std::iter::once(2)) // Output is rotation angle and speed
+            .map(|neurons| nn::LayerTopology { neurons })
+            .collect()
     }
-}
 
-impl Animal {
-    pub fn brain_topology_from_eyes(eye: &Eye) -> Vec<LayerTopology>{
-        vec![
-            nn::LayerTopology{ neurons: eye.cells() * 2 },
-            nn::LayerTopology{ neurons: 10 },
-            nn::LayerTopology{ neurons: 5 },
-            nn::LayerTopology{ neurons: 10 },
-            nn::LayerTopology{ neurons: 2 } // Output is rotation angle and speed
-            ]
+    /// Brain layers of the birds of `species` as `config` sets them up: those
+    /// of `brain_topology`, with the inputs of their other sensors added
+    pub fn brain_layers(config: &SimulationConfig, species: Species) -> Vec<LayerTopology> {
+        let mut layers = Self::brain_topology(&Eye::for_species(config, species), config.brain(species));
+        layers[0].neurons += sensor::for_species(config, species).iter().map(|s| s.inputs()).sum::<usize>();
+        layers
     }
 
 
     pub fn random(rng: &mut dyn RngCore) -> Self {
-        let eye = Eye::default();
+        Self::random_with_config(rng, &SimulationConfig::default(), Species::Prey)
+    }
+
+    pub(crate) fn random_with_config(rng: &mut dyn RngCore, config: &SimulationConfig, species: Species) -> Self {
         let brain = nn::NeuralNetwork::random(
-            Self::brain_topology_from_eyes(&eye),
+            Self::brain_layers(config, species),
             rng
         ).expect("Cannot build animal brain!");
-        for layer in brain.get_layers() {
-            for neuron in layer.get_neurons() {
-
-            }
-        }
         Self {
-            eye,
+            id: AnimalId::default(),
+            species,
+            eye: Eye::for_species(config, species),
             brain,
-            position: rng.gen(),
+            position: obstacle::free_position(&config.world.obstacles, config.world.topology, rng),
             rotation: rng.gen(),
             speed: 0.002,
-            score: 0
+            score: 0,
+            age: 0,
+            energy: config.energy.capacity,
+            exhausted: false,
         }
     }
 
+    pub fn id(&self) -> AnimalId {
+        self.id
+    }
+
+    pub fn species(&self) -> Species {
+        self.species
+    }
+
     pub fn position(&self) -> Point2<f32> {
         self.position
     }
@@ -75,11 +105,45 @@ impl Animal {
     pub fn rotation(&self) -> Rotation2<f32> {
         self.rotation
     }
+
+    pub fn energy(&self) -> f32 {
+        self.energy
+    }
+
+    /// Whether the bird ran out of energy before the end of its generation
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// Pays for flying at the current speed and for turning by `turn`
+    /// radians, telling whether that used up the last of the bird's energy
+    pub(crate) fn spend_energy(&mut self, config: &EnergyConfig, turn: f32) -> bool {
+        self.energy -= config.metabolism + config.speed_cost * self.speed + config.turn_cost * turn.abs();
+        if self.energy > 0.0 {
+            return false;
+        }
+        self.energy = 0.0;
+        self.exhausted = true;
+        true
+    }
+
+    /// Gives back the energy of a meal worth `value`, up to what the bird
+    /// can hold; a poisonous one takes some away
+    pub(crate) fn refuel(&mut self, config: &EnergyConfig, value: i32) {
+        self.energy = (self.energy + config.food_energy * value as f32).min(config.capacity);
+    }
 }
 
 pub struct AnimalIndividual {
-    fitness: f32,
-    chromosome: Chromosome
+    pub(crate) id: AnimalId,
+    pub(crate) fitness: f32,
+    pub(crate) chromosome: Chromosome
+}
+
+impl AnimalIndividual {
+    pub fn id(&self) -> AnimalId {
+        self.id
+    }
 }
 
 
@@ -94,6 +158,7 @@ impl Individual for AnimalIndividual {
 
     fn create(chromosome: Chromosome) -> Self {
         Self {
+            id: AnimalId::default(),
             fitness: 0.0,
             chromosome
         }
@@ -104,6 +169,9 @@ impl From<&Animal> for AnimalIndividual {
     fn from(value: &Animal) -> Self {
         let animal_weigths = value.brain.weights().into_iter();
         Self {
+            id: value.id,
+            // Below zero for prey caught more often than they ate or that ate
+            // poison, until `Simulation` shifts the flock's fitness
             fitness: value.score as f32,
             chromosome: Chromosome::from_iter(animal_weigths)
 
@@ -113,17 +181,26 @@ impl From<&Animal> for AnimalIndividual {
 
 impl From<&AnimalIndividual> for Animal {
     fn from(value: &AnimalIndividual) -> Self {
-        let eye = Eye::default();
-        let brain_topology = Self::brain_topology_from_eyes(&eye);
+        Self::from_individual(value, &SimulationConfig::default(), Species::Prey)
+    }
+}
+
+impl Animal {
+    pub(crate) fn from_individual(value: &AnimalIndividual, config: &SimulationConfig, species: Species) -> Self {
+        let brain_topology = Self::brain_layers(config, species);
         let brain = NeuralNetwork::from_weights(&brain_topology,value.chromosome.genes()).expect("Cannot generate brain from weights");
         Self {
+            id: value.id,
+            species,
             score: value.fitness as i32,
-            eye,
+            eye: Eye::for_species(config, species),
             brain,
             position: Point2::new(0.0, 0.0),
             rotation: Rotation2::new(0.0),
             speed: 0.001,
-
+            age: 0,
+            energy: config.energy.capacity,
+            exhausted: false,
         }
     }
 }
@@ -0,0 +1,280 @@
+use std::any::Any;
+
+use genetic_algorithm::{Crossover, GaussianMutation, GeneticAlgorithm, Mutation, Replacement, RouletteWheelSelection, Selection, UniformCrossover};
+
+use crate::{ConfigError, Simulation, SimulationConfig, Snapshot, SnapshotError};
+
+/// Builds a `Simulation` evolved by a `GeneticAlgorithm` made of any
+/// selection, crossover and mutation.
+///
+/// Operators are either concrete types or boxed ones (`BoxedSelection`,
+/// `BoxedCrossover`, `BoxedMutation`) when they are only known at runtime:
+///
+/// ```
+/// # use lib_simulation::*;
+/// let selection: BoxedSelection = Box::new(TournamentSelection::new(3).unwrap());
+/// let simulation = SimulationBuilder::new()
+///     .animals(20)
+///     .selection(selection)
+///     .crossover(SinglePointCrossover)
+///     .build()
+///     .unwrap();
+/// ```
+pub struct SimulationBuilder<S = RouletteWheelSelection, C = UniformCrossover, M = GaussianMutation> {
+    config: SimulationConfig,
+    seed: Option<u64>,
+    replacement: Replacement,
+    selection: S,
+    crossover: C,
+    mutation: M,
+}
+
+impl SimulationBuilder {
+    pub fn new() -> Self {
+        Self::from_config(SimulationConfig::default()).expect("Default config is valid")
+    }
+
+    /// Starts from `config`, with a `GaussianMutation` using its mutation
+    /// parameters
+    pub fn from_config(config: SimulationConfig) -> Result<Self, ConfigError> {
+        config.validate()?;
+        let mutation = GaussianMutation::new(config.evolution.mutation_chance, config.evolution.mutation_coeff)
+            .expect("Mutation chance was validated with the config");
+        Ok(Self {
+            config,
+            seed: None,
+            replacement: Replacement::default(),
+            selection: RouletteWheelSelection {},
+            crossover: UniformCrossover,
+            mutation,
+        })
+    }
+}
+
+impl Default for SimulationBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, C, M> SimulationBuilder<S, C, M>
+where
+    S: Selection + Send + Sync + 'static,
+    C: Crossover + Send + Sync + 'static,
+    M: Mutation + Send + Sync + 'static,
+{
+    pub fn animals(mut self, animals: usize) -> Self {
+        self.config.world.animals = animals;
+        self
+    }
+
+    pub fn food(mut self, food: usize) -> Self {
+        self.config.world.food = food;
+        self
+    }
+
+    pub fn generation_length(mut self, generation_length: u32) -> Self {
+        self.config.evolution.generation_length = generation_length;
+        self
+    }
+
+    /// Seeds the simulation's RNG; without a seed a random one is picked,
+    /// which `Simulation::seed` still reports
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn replacement(mut self, replacement: Replacement) -> Self {
+        self.replacement = replacement;
+        self
+    }
+
+    pub fn selection<S2>(self, selection: S2) -> SimulationBuilder<S2, C, M> {
+        SimulationBuilder {
+            config: self.config,
+            seed: self.seed,
+            replacement: self.replacement,
+            selection,
+            crossover: self.crossover,
+            mutation: self.mutation,
+        }
+    }
+
+    pub fn crossover<C2>(self, crossover: C2) -> SimulationBuilder<S, C2, M> {
+        SimulationBuilder {
+            config: self.config,
+            seed: self.seed,
+            replacement: self.replacement,
+            selection: self.selection,
+            crossover,
+            mutation: self.mutation,
+        }
+    }
+
+    pub fn mutation<M2>(self, mutation: M2) -> SimulationBuilder<S, C, M2> {
+        SimulationBuilder {
+            config: self.config,
+            seed: self.seed,
+            replacement: self.replacement,
+            selection: self.selection,
+            crossover: self.crossover,
+            mutation,
+        }
+    }
+
+    pub fn build(mut self) -> Result<Simulation, ConfigError> {
+        // Keep the config telling the truth when the mutation was replaced
+        // by another `GaussianMutation`
+        if let Some(mutation) = (&self.mutation as &dyn Any).downcast_ref::<GaussianMutation>() {
+            self.config.evolution.mutation_chance = mutation.chance();
+            self.config.evolution.mutation_coeff = mutation.coeff();
+        }
+        let evolution_algorithm = GeneticAlgorithm::new(self.selection, self.crossover, self.mutation);
+        let seed = self.seed.unwrap_or_else(rand::random);
+        Ok(Simulation::with_optimizer(seed, self.config, evolution_algorithm)?.with_replacement(self.replacement))
+    }
+
+    /// Resumes the simulation of `snapshot` with this builder's operators;
+    /// everything else, including the config, comes from the snapshot
+    pub fn restore(self, snapshot: Snapshot) -> Result<Simulation, SnapshotError> {
+        let evolution_algorithm = GeneticAlgorithm::new(self.selection, self.crossover, self.mutation);
+        Simulation::restore_with_optimizer(snapshot, evolution_algorithm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn builds_with_boxed_operators() {
+        let operators: Vec<(BoxedSelection, BoxedCrossover)> = vec![
+            (Box::new(RouletteWheelSelection {}), Box::new(UniformCrossover)),
+            (Box::new(TournamentSelection::new(2).unwrap()), Box::new(SinglePointCrossover)),
+            (Box::new(RankSelection), Box::new(UniformCrossover)),
+        ];
+        for (selection, crossover) in operators {
+            let mut simulation = SimulationBuilder::new()
+                .animals(6)
+                .food(10)
+                .generation_length(10)
+                .selection(selection)
+                .crossover(crossover)
+                .seed(42)
+                .build()
+                .unwrap();
+            while simulation.step().unwrap().is_none() {}
+            assert_eq!(simulation.generation(), 1);
+            assert_eq!(simulation.world().animals().len(), 6);
+            assert_eq!(simulation.config().evolution.mutation_chance, 0.1);
+        }
+    }
+
+    // Never manages to pick a parent
+    struct FailingSelection;
+
+    impl FitnessSelection for FailingSelection {
+        fn select_index(&self, _rng: &mut dyn rand::RngCore, _fitness: &[f32]) -> Result<usize, GeneticError> {
+            Err(GeneticError::EmptyPopulation)
+        }
+    }
+
+    #[test]
+    fn step_tells_when_evolution_fails() {
+        let mut simulation = SimulationBuilder::new().animals(4).generation_length(10).selection(FailingSelection).seed(42).build().unwrap();
+        let founders: Vec<AnimalId> = simulation.world().animals().iter().map(|a| a.id()).collect();
+        let err = loop {
+            match simulation.step() {
+                Ok(statistics) => assert_eq!(statistics, None),
+                Err(err) => break err,
+            }
+        };
+        assert!(matches!(err, GeneticError::SelectionError { parent: Parent::First, .. }));
+        // The generation is over all the same, with the same birds
+        assert_eq!(simulation.generation(), 1);
+        assert_eq!(simulation.world().animals().iter().map(|a| a.id()).collect::<Vec<_>>(), founders);
+    }
+
+    #[test]
+    fn step_tells_when_replacing_birds_fails() {
+        let mut simulation = SimulationBuilder::new()
+            .animals(4)
+            .generation_length(10)
+            .selection(FailingSelection)
+            .replacement(Replacement::SteadyState(1))
+            .seed(42)
+            .build()
+            .unwrap();
+        // Birds can be replaced once they flew for 5 steps, every other step
+        for _ in 0..5 {
+            assert_eq!(simulation.step().unwrap(), None);
+        }
+        assert!(matches!(simulation.step(), Err(GeneticError::SelectionError { .. })));
+    }
+
+    // Turns every gene around
+    struct Negation;
+
+    impl Mutation for Negation {
+        fn mutate(&self, _rng: &mut dyn rand::RngCore, child: &mut genetic_algorithm::Chromosome) {
+            child.iter_mut().for_each(|gene| *gene = -*gene);
+        }
+    }
+
+    #[test]
+    fn birds_born_in_the_world_use_the_mutation() {
+        let mut config = SimulationConfig::default();
+        config.world.food = 60;
+        config.world.eat_radius = 0.03;
+        config.energy = EnergyConfig { enabled: true, ..Default::default() };
+        config.reproduction = ReproductionConfig { enabled: true, energy_threshold: 0.9, max_population: 30, ..Default::default() };
+        let mut simulation = SimulationBuilder::from_config(config).unwrap().mutation(Negation).seed(42).build().unwrap();
+        for _ in 0..200 {
+            simulation.step().unwrap();
+        }
+        let born: Vec<&LineageRecord> = simulation.genealogy().records().filter(|r| r.parents.is_some()).collect();
+        assert!(!born.is_empty());
+        assert!(born.iter().all(|r| r.genes_from_first_parent == 0 && r.mutated_genes > 0));
+    }
+
+    #[test]
+    fn random_is_the_default_builder() {
+        let mut built = SimulationBuilder::new()
+            .mutation(GaussianMutation::new(0.2, 0.3).unwrap())
+            .generation_length(20)
+            .seed(42)
+            .build()
+            .unwrap();
+        let mut random = Simulation::random(42, 10, 15, 0.2, 0.3, 20);
+
+        let a = (0..=20).find_map(|_| built.step().unwrap());
+        let b = (0..=20).find_map(|_| random.step().unwrap());
+        assert_eq!(a, b);
+        assert_eq!(built.run_header(), random.run_header());
+    }
+
+    #[test]
+    fn rejects_invalid_configs() {
+        let err = SimulationBuilder::new().generation_length(0).build().err().unwrap();
+        assert!(matches!(err, ConfigError::Invalid { field: "evolution.generation_length", .. }));
+    }
+
+    #[test]
+    fn uses_the_config() {
+        let config = SimulationConfig::from_toml("[world]\nanimals = 4\n[eye]\ncells = 5\n[brain]\nhidden_layers = [3]\n").unwrap();
+        let mut simulation = Simulation::from_config(42, config).unwrap();
+        assert_eq!(simulation.world().animals().len(), 4);
+        assert_eq!(simulation.world().animals()[0].brain.weights().len(), (10 + 1) * 3 + (3 + 1) * 2);
+        while simulation.step().unwrap().is_none() {}
+        assert_eq!(simulation.world().animals()[0].brain.weights().len(), (10 + 1) * 3 + (3 + 1) * 2);
+    }
+
+    #[test]
+    fn picks_a_seed_when_not_given_one() {
+        let simulation = SimulationBuilder::new().build().unwrap();
+        let replay = Simulation::from_config(simulation.seed(), simulation.config().clone()).unwrap();
+        assert_eq!(crate::tests::world_bits(simulation.world()), crate::tests::world_bits(replay.world()));
+    }
+}
@@ -0,0 +1,711 @@
+use std::borrow::Cow;
+use std::f32::consts::{FRAC_PI_4, TAU};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use genetic_algorithm::Optimizer;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{AnimalIndividual, Obstacle, Species, Topology};
+
+/// Everything about a `Simulation` that can be tuned without recompiling.
+///
+/// Missing fields take their default value, so a config file only needs to
+/// mention the knobs it turns, e.g. in TOML:
+///
+/// ```toml
+/// [world]
+/// animals = 40
+///
+/// [eye]
+/// cells = 9
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimulationConfig {
+    pub world: WorldConfig,
+    pub evolution: EvolutionConfig,
+    pub animal: AnimalConfig,
+    pub eye: EyeConfig,
+    pub brain: BrainConfig,
+    /// Senses of the birds besides their eyes
+    pub sensors: Vec<SensorConfig>,
+    pub predators: PredatorConfig,
+    pub energy: EnergyConfig,
+    pub reproduction: ReproductionConfig,
+    pub food: FoodConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorldConfig {
+    pub animals: usize,
+    pub food: usize,
+    /// How close a bird has to fly to some food to eat it
+    pub eat_radius: f32,
+    /// How close two birds have to get to bump into each other
+    pub collision_radius: f32,
+    /// What happens at the edges of the world
+    pub topology: Topology,
+    /// Static shapes birds fly around and cannot see through
+    pub obstacles: Vec<Obstacle>,
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        Self { animals: 10, food: 15, eat_radius: 0.01, collision_radius: 0.01, topology: Topology::Torus, obstacles: vec![] }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EvolutionConfig {
+    /// Number of steps a generation lives for or, with `reproduction`,
+    /// between two samples of the population statistics
+    pub generation_length: u32,
+    /// Parameters of the default `GaussianMutation`
+    pub mutation_chance: f32,
+    pub mutation_coeff: f32,
+}
+
+impl Default for EvolutionConfig {
+    fn default() -> Self {
+        Self { generation_length: 1000, mutation_chance: 0.1, mutation_coeff: 0.5 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnimalConfig {
+    /// Minimum speed of a bird.
+    ///
+    /// Keeping it above zero prevents birds from getting stuck in one place.
+    pub speed_min: f32,
+
+    /// Maximum speed of a bird.
+    ///
+    /// Keeping it "sane" prevents birds from accelerating up to infinity,
+    /// which makes the simulation... unrealistic :-)
+    pub speed_max: f32,
+
+    /// Speed acceleration; determines how much the brain can affect bird's
+    /// speed during one step.
+    ///
+    /// Assuming our bird is currently flying with speed=0.5, when the brain
+    /// yells "stop flying!", a `speed_accel` of:
+    ///
+    /// - 0.1 = makes it take 5 steps ("5 seconds") for the bird to actually
+    ///   slow down to `speed_min`,
+    ///
+    /// - 0.5 = makes it take 1 step for the bird to slow down to `speed_min`.
+    ///
+    /// This improves simulation faithfulness, because - as in real life -
+    /// it's not possible to increase speed from 1km/h to 50km/h in one
+    /// instant, even if your brain very much wants to.
+    pub speed_accel: f32,
+
+    /// Ditto, but for rotation:
+    ///
+    /// - 2 * PI = it takes one step for the bird to do a 360° rotation,
+    /// - PI = it takes two steps for the bird to do a 360° rotation,
+    ///
+    /// I've chosen PI/4, because - as our motto goes - this value seems
+    /// to play nice.
+    pub rotation_accel: f32,
+}
+
+impl Default for AnimalConfig {
+    fn default() -> Self {
+        Self { speed_min: 0.001, speed_max: 0.005, speed_accel: 0.2, rotation_accel: FRAC_PI_4 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EyeConfig {
+    /// How far a bird can see
+    pub fov_range: f32,
+    /// How wide a bird can see, in radians
+    pub fov_angle: f32,
+    /// Number of photoreceptors the field of view is split into
+    pub cells: usize,
+    pub vision: Vision,
+}
+
+impl Default for EyeConfig {
+    fn default() -> Self {
+        Self { fov_range: 0.75, fov_angle: FRAC_PI_4, cells: 13, vision: Vision::Cone }
+    }
+}
+
+/// How the cells of an eye see what is around, e.g. in TOML:
+///
+/// ```toml
+/// [eye.vision]
+/// kind = "raycast"
+/// hit_radius = 0.01
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Vision {
+    /// Each cell adds up everything within its slice of the field of view
+    /// that no obstacle hides
+    #[default]
+    Cone,
+    /// Each cell casts a ray through its middle and sees only the first
+    /// thing it meets, food and birds being `hit_radius` wide
+    Raycast { hit_radius: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BrainConfig {
+    /// Neurons of each layer between the eyes and the two outputs (speed
+    /// and rotation)
+    pub hidden_layers: Vec<usize>,
+}
+
+impl Default for BrainConfig {
+    fn default() -> Self {
+        Self { hidden_layers: vec![10, 5, 10] }
+    }
+}
+
+/// Birds of a second species, hunting the others and evolved apart from
+/// them. They fly like the others, as `animal` says, but have their own eyes
+/// and brains.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PredatorConfig {
+    /// None by default, leaving the world to the prey
+    pub count: usize,
+    /// How close a predator has to get to a prey bird to catch it
+    pub catch_radius: f32,
+    /// Score a prey bird loses each time it is caught
+    pub caught_penalty: i32,
+    pub eye: EyeConfig,
+    pub brain: BrainConfig,
+    pub sensors: Vec<SensorConfig>,
+}
+
+impl Default for PredatorConfig {
+    fn default() -> Self {
+        Self { count: 0, catch_radius: 0.02, caught_penalty: 1, eye: EyeConfig::default(), brain: BrainConfig::default(), sensors: vec![] }
+    }
+}
+
+/// A sense of the birds besides their eyes, feeding their brains after the
+/// eyes do, e.g. in TOML:
+///
+/// ```toml
+/// [[sensors]]
+/// kind = "smell"
+/// range = 0.3
+///
+/// [[sensors]]
+/// kind = "proprioception"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SensorConfig {
+    /// Which way the nearest food within `range` is
+    Smell { range: f32 },
+    /// The bird's own speed, heading and energy
+    Proprioception,
+    /// How close obstacles and edges of the world are along `rays` rays
+    /// around the bird, within `range`
+    Rays { rays: usize, range: f32 },
+    /// A signal cycling every `period` steps
+    Clock { period: u32 },
+}
+
+/// Energy birds spend flying and get back by eating. Off by default, leaving
+/// flying free and every bird alive until the end of its generation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnergyConfig {
+    pub enabled: bool,
+    /// Energy a bird is born with, and the most it can hold
+    pub capacity: f32,
+    /// Energy spent every step, flying or not
+    pub metabolism: f32,
+    /// Energy spent per unit of distance flown
+    pub speed_cost: f32,
+    /// Energy spent per radian turned
+    pub turn_cost: f32,
+    /// Energy given back by each food eaten, or each prey caught by a predator
+    pub food_energy: f32,
+    /// What becomes of a bird out of energy
+    pub exhaustion: Exhaustion,
+    /// Fitness of a bird that stayed alive through its whole generation, on
+    /// top of its score; birds that ran out of energy earlier get their share
+    pub survival_reward: f32,
+}
+
+impl Default for EnergyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            capacity: 1.0,
+            metabolism: 0.0005,
+            speed_cost: 0.5,
+            turn_cost: 0.001,
+            food_energy: 0.25,
+            exhaustion: Exhaustion::Die,
+            survival_reward: 5.0,
+        }
+    }
+}
+
+/// What becomes of a bird out of energy, until the end of its generation
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Exhaustion {
+    /// The bird leaves the world: nobody sees it, bumps into it or catches it
+    #[default]
+    Die,
+    /// The bird stops where it is, still seen, bumped into and caught, but
+    /// no longer flies, eats or thinks
+    Stop,
+}
+
+impl Exhaustion {
+    pub const ALL: [Exhaustion; 2] = [Exhaustion::Die, Exhaustion::Stop];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Exhaustion::Die => "die",
+            Exhaustion::Stop => "stop",
+        }
+    }
+}
+
+/// Open-ended evolution: instead of a whole new generation every
+/// `generation_length` steps, birds with enough energy give birth in the
+/// world and old ones die. Needs `energy`; birds out of it die right away,
+/// whatever `energy.exhaustion` says.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReproductionConfig {
+    pub enabled: bool,
+    /// Energy a bird needs to give birth
+    pub energy_threshold: f32,
+    /// Energy a newborn starts with, taken from its parents
+    pub offspring_energy: f32,
+    /// How close a bird has to be to another one of its species, also ready
+    /// to give birth, to mate with it; farther apart, a bird gives birth to
+    /// a mutated copy of itself
+    pub mate_radius: f32,
+    /// Number of steps a bird flies before dying of old age
+    pub max_age: u32,
+    /// No more births while a species counts that many birds
+    pub max_population: usize,
+    /// Random birds fly in while a species counts fewer
+    pub min_population: usize,
+}
+
+impl Default for ReproductionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            energy_threshold: 0.8,
+            offspring_energy: 0.4,
+            mate_radius: 0.05,
+            max_age: 3000,
+            max_population: 100,
+            min_population: 2,
+        }
+    }
+}
+
+/// How food grows back once eaten. By default it does so right away,
+/// anywhere in the world and all year round.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FoodConfig {
+    /// Steps eaten food takes to grow back
+    pub regrowth_delay: u32,
+    pub placement: FoodPlacement,
+    /// Steps uneaten food lasts before rotting and growing back elsewhere,
+    /// forever if 0
+    pub lifetime: u32,
+    /// Steps of a cycle of seasons, no seasons if 0
+    pub season_length: u32,
+    /// Share of the food gone at the low of the cycle of seasons
+    pub season_amplitude: f32,
+    /// Number of times food can grow back in a generation, as often as it is
+    /// eaten if not set
+    pub regrowth_limit: Option<u32>,
+    /// Kinds of food sharing the `world.food`, a single plain one if empty
+    pub kinds: Vec<FoodKind>,
+}
+
+impl FoodConfig {
+    /// The kinds of food of the world, in the order eye channels see them
+    pub fn kinds(&self) -> Cow<'_, [FoodKind]> {
+        if self.kinds.is_empty() {
+            Cow::Owned(vec![FoodKind::default()])
+        } else {
+            Cow::Borrowed(&self.kinds)
+        }
+    }
+}
+
+/// A kind of food, e.g. in TOML:
+///
+/// ```toml
+/// [[food.kinds]]
+/// name = "berry"
+/// share = 0.2
+/// value = -2
+/// color = [160, 0, 200]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FoodKind {
+    pub name: String,
+    /// Share of the `world.food` of this kind, relative to the other kinds
+    pub share: f32,
+    /// Score a bird gets eating it, negative for poison; with `energy`, it
+    /// also gives `value` times `energy.food_energy`
+    pub value: i32,
+    /// How close a bird has to fly to eat it, `world.eat_radius` if not set
+    pub radius: Option<f32>,
+    /// RGB colour it is drawn with
+    pub color: [u8; 3],
+    /// Whether birds see it, on a channel of eye cells of its own
+    pub visible: bool,
+}
+
+impl Default for FoodKind {
+    fn default() -> Self {
+        Self { name: "food".to_string(), share: 1.0, value: 1, radius: None, color: [0, 200, 0], visible: true }
+    }
+}
+
+/// Where food grows, e.g. in TOML:
+///
+/// ```toml
+/// [food.placement]
+/// kind = "patches"
+/// count = 3
+/// radius = 0.1
+/// drift = 0.001
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FoodPlacement {
+    /// Anywhere outside the obstacles
+    #[default]
+    Uniform,
+    /// Within `radius` of the centers of `count` patches, each drifting by
+    /// `drift` in a random direction every step
+    Patches { count: usize, radius: f32, drift: f32 },
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("{field} must be {expected}, got {value}")]
+    Invalid { field: &'static str, value: String, expected: String },
+    #[error("cannot read config: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid TOML config: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("invalid JSON config: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid RON config: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("cannot tell the format of {0}, expected a .toml, .json or .ron file")]
+    UnknownFormat(PathBuf),
+}
+
+// Fails with `ConfigError::Invalid` unless `valid`
+fn check(valid: bool, field: &'static str, value: impl ToString, expected: impl ToString) -> Result<(), ConfigError> {
+    if valid {
+        Ok(())
+    } else {
+        Err(ConfigError::Invalid { field, value: value.to_string(), expected: expected.to_string() })
+    }
+}
+
+fn non_negative(field: &'static str, value: f32) -> Result<(), ConfigError> {
+    check(value.is_finite() && value >= 0.0, field, value, "a finite non-negative number")
+}
+
+fn check_eye(eye: &EyeConfig, [fov_range, fov_angle, cells, hit_radius]: [&'static str; 4]) -> Result<(), ConfigError> {
+    check(eye.fov_range > 0.0 && eye.fov_range <= 1.0, fov_range, eye.fov_range, "in (0, 1]")?;
+    check(eye.fov_angle > 0.0 && eye.fov_angle <= TAU, fov_angle, eye.fov_angle, "in (0, 2π]")?;
+    check(eye.cells > 0, cells, eye.cells, "at least 1")?;
+    if let Vision::Raycast { hit_radius: radius } = eye.vision {
+        check(radius > 0.0 && radius <= 1.0, hit_radius, radius, "in (0, 1]")?;
+    }
+    Ok(())
+}
+
+fn check_brain(brain: &BrainConfig, hidden_layers: &'static str) -> Result<(), ConfigError> {
+    check(brain.hidden_layers.iter().all(|&n| n > 0), hidden_layers, format!("{:?}", brain.hidden_layers), "layers of at least 1 neuron")
+}
+
+fn check_sensors(sensors: &[SensorConfig], field: &'static str) -> Result<(), ConfigError> {
+    for sensor in sensors {
+        let valid = match *sensor {
+            SensorConfig::Smell { range } => range > 0.0 && range <= 1.0,
+            SensorConfig::Proprioception => true,
+            SensorConfig::Rays { rays, range } => rays > 0 && range > 0.0 && range <= 1.0,
+            SensorConfig::Clock { period } => period > 0,
+        };
+        check(valid, field, format!("{sensor:?}"), "ranges in (0, 1], at least 1 ray and a period of at least 1 step")?;
+    }
+    Ok(())
+}
+
+impl SimulationConfig {
+    /// Brain layers of the birds of `species`
+    pub fn brain(&self, species: Species) -> &BrainConfig {
+        match species {
+            Species::Prey => &self.brain,
+            Species::Predator => &self.predators.brain,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let Self { world, evolution, animal, eye, brain, sensors, predators, energy, reproduction, food } = self;
+        check(world.animals > 0, "world.animals", world.animals, "at least 1")?;
+        non_negative("world.eat_radius", world.eat_radius)?;
+        non_negative("world.collision_radius", world.collision_radius)?;
+        for (index, obstacle) in world.obstacles.iter().enumerate() {
+            if let Some(expected) = obstacle.invalid() {
+                check(false, "world.obstacles", format!("#{index} {obstacle:?}"), expected)?;
+            }
+        }
+
+        check(evolution.generation_length > 0, "evolution.generation_length", evolution.generation_length, "at least 1")?;
+        check((0.0..=1.0).contains(&evolution.mutation_chance), "evolution.mutation_chance", evolution.mutation_chance, "between 0 and 1")?;
+        non_negative("evolution.mutation_coeff", evolution.mutation_coeff)?;
+
+        non_negative("animal.speed_min", animal.speed_min)?;
+        non_negative("animal.speed_max", animal.speed_max)?;
+        check(animal.speed_min <= animal.speed_max, "animal.speed_max", animal.speed_max, format!("at least animal.speed_min ({})", animal.speed_min))?;
+        non_negative("animal.speed_accel", animal.speed_accel)?;
+        non_negative("animal.rotation_accel", animal.rotation_accel)?;
+
+        check_eye(eye, ["eye.fov_range", "eye.fov_angle", "eye.cells", "eye.vision.hit_radius"])?;
+        check_brain(brain, "brain.hidden_layers")?;
+        check_sensors(sensors, "sensors")?;
+
+        non_negative("predators.catch_radius", predators.catch_radius)?;
+        check(predators.caught_penalty >= 0, "predators.caught_penalty", predators.caught_penalty, "at least 0")?;
+        check_eye(
+            &predators.eye,
+            ["predators.eye.fov_range", "predators.eye.fov_angle", "predators.eye.cells", "predators.eye.vision.hit_radius"],
+        )?;
+        check_brain(&predators.brain, "predators.brain.hidden_layers")?;
+        check_sensors(&predators.sensors, "predators.sensors")?;
+
+        check(energy.capacity.is_finite() && energy.capacity > 0.0, "energy.capacity", energy.capacity, "a finite positive number")?;
+        non_negative("energy.metabolism", energy.metabolism)?;
+        non_negative("energy.speed_cost", energy.speed_cost)?;
+        non_negative("energy.turn_cost", energy.turn_cost)?;
+        non_negative("energy.food_energy", energy.food_energy)?;
+        non_negative("energy.survival_reward", energy.survival_reward)?;
+
+        check(!reproduction.enabled || energy.enabled, "reproduction.enabled", reproduction.enabled, "false unless energy.enabled")?;
+        // Birds only give birth with `reproduction.enabled`, whatever the
+        // rest of the section says
+        if reproduction.enabled {
+            check(
+                reproduction.energy_threshold > 0.0 && reproduction.energy_threshold <= energy.capacity,
+                "reproduction.energy_threshold",
+                reproduction.energy_threshold,
+                format!("in (0, energy.capacity ({})]", energy.capacity),
+            )?;
+            check(
+                (0.0..=reproduction.energy_threshold).contains(&reproduction.offspring_energy),
+                "reproduction.offspring_energy",
+                reproduction.offspring_energy,
+                format!("between 0 and reproduction.energy_threshold ({})", reproduction.energy_threshold),
+            )?;
+            non_negative("reproduction.mate_radius", reproduction.mate_radius)?;
+            check(reproduction.max_age > 0, "reproduction.max_age", reproduction.max_age, "at least 1")?;
+            check(reproduction.min_population > 0, "reproduction.min_population", reproduction.min_population, "at least 1")?;
+            check(
+                reproduction.max_population >= reproduction.min_population,
+                "reproduction.max_population",
+                reproduction.max_population,
+                format!("at least reproduction.min_population ({})", reproduction.min_population),
+            )?;
+        }
+
+        check((0.0..=1.0).contains(&food.season_amplitude), "food.season_amplitude", food.season_amplitude, "between 0 and 1")?;
+        if let FoodPlacement::Patches { count, radius, drift } = food.placement {
+            check(count > 0, "food.placement.count", count, "at least 1")?;
+            check(radius > 0.0 && radius <= 1.0, "food.placement.radius", radius, "in (0, 1]")?;
+            non_negative("food.placement.drift", drift)?;
+        }
+        for kind in &food.kinds {
+            check(kind.share.is_finite() && kind.share > 0.0, "food.kinds.share", format!("{} for {}", kind.share, kind.name), "a finite positive number")?;
+            if let Some(radius) = kind.radius {
+                non_negative("food.kinds.radius", radius)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn from_toml(config: &str) -> Result<Self, ConfigError> {
+        Self::validated(toml::from_str(config)?)
+    }
+
+    pub fn from_json(config: &str) -> Result<Self, ConfigError> {
+        Self::validated(serde_json::from_str(config)?)
+    }
+
+    pub fn from_ron(config: &str) -> Result<Self, ConfigError> {
+        Self::validated(ron::from_str(config)?)
+    }
+
+    /// Reads a config file, in the format given by its extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Self::from_toml(&content),
+            Some("json") => Self::from_json(&content),
+            Some("ron") => Self::from_ron(&content),
+            _ => Err(ConfigError::UnknownFormat(path.to_path_buf())),
+        }
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("Config can always be written as TOML")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Config can always be written as JSON")
+    }
+
+    /// Like `validate`, also checking there are enough birds for `optimizer`
+    /// to breed a generation from
+    pub fn validate_for(&self, optimizer: &dyn Optimizer<AnimalIndividual>) -> Result<(), ConfigError> {
+        self.validate()?;
+        // Birds born in the world are not bred a generation at once
+        let minimum = if self.reproduction.enabled { 1 } else { optimizer.minimum_population() };
+        check(
+            self.world.animals >= minimum,
+            "world.animals",
+            self.world.animals,
+            format!("at least {minimum} for {}", optimizer.name()),
+        )
+    }
+
+    fn validated(self) -> Result<Self, ConfigError> {
+        self.validate()?;
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_partial_configs() {
+        let expected = SimulationConfig {
+            world: WorldConfig { animals: 40, ..Default::default() },
+            eye: EyeConfig { cells: 9, ..Default::default() },
+            ..Default::default()
+        };
+        assert_eq!(SimulationConfig::from_toml("[world]\nanimals = 40\n\n[eye]\ncells = 9\n").unwrap(), expected);
+        assert_eq!(SimulationConfig::from_json(r#"{"world": {"animals": 40}, "eye": {"cells": 9}}"#).unwrap(), expected);
+        assert_eq!(SimulationConfig::from_ron("(world: (animals: 40), eye: (cells: 9))").unwrap(), expected);
+    }
+
+    #[test]
+    fn round_trips_through_toml() {
+        let config = SimulationConfig::default();
+        assert_eq!(SimulationConfig::from_toml(&config.to_toml()).unwrap(), config);
+    }
+
+    #[test]
+    fn loads_obstacles() {
+        let config = SimulationConfig::from_toml(
+            "[[world.obstacles]]\nshape = \"circle\"\ncenter = [0.5, 0.5]\nradius = 0.1\n\n\
+             [[world.obstacles]]\nshape = \"rectangle\"\ncenter = [0.2, 0.8]\nsize = [0.3, 0.05]\n\n\
+             [[world.obstacles]]\nshape = \"polyline\"\npoints = [[0.1, 0.1], [0.4, 0.1]]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.world.obstacles,
+            [
+                Obstacle::Circle { center: [0.5, 0.5].into(), radius: 0.1 },
+                Obstacle::Rectangle { center: [0.2, 0.8].into(), size: [0.3, 0.05].into(), angle: 0.0 },
+                Obstacle::Polyline { points: vec![[0.1, 0.1].into(), [0.4, 0.1].into()] },
+            ]
+        );
+        assert_eq!(SimulationConfig::from_toml(&config.to_toml()).unwrap(), config);
+
+        let err = SimulationConfig::from_json(r#"{"world": {"obstacles": [{"shape": "circle", "center": [0.5, 0.5], "radius": 0}]}}"#).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "world.obstacles", .. }));
+        let err = SimulationConfig::from_json(r#"{"world": {"obstacles": [{"shape": "polyline", "points": [[0.5, 0.5]]}]}}"#).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "world.obstacles", .. }));
+    }
+
+    #[test]
+    fn rejects_invalid_configs() {
+        let err = SimulationConfig::from_toml("[animal]\nspeed_min = 0.01\nspeed_max = 0.002\n").unwrap_err();
+        assert_eq!(err.to_string(), "animal.speed_max must be at least animal.speed_min (0.01), got 0.002");
+
+        let err = SimulationConfig::from_json(r#"{"eye": {"cells": 0}}"#).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "eye.cells", .. }));
+
+        let err = SimulationConfig::from_toml("[world]\nanimals = 0\n").unwrap_err();
+        assert_eq!(err.to_string(), "world.animals must be at least 1, got 0");
+
+        // Differential evolution needs a target and three donors
+        let mut config = SimulationConfig::default();
+        config.world.animals = 3;
+        let optimizer = genetic_algorithm::DifferentialEvolution::new(genetic_algorithm::DifferentialStrategy::Rand1Bin, 0.8, 0.9).unwrap();
+        let err = config.validate_for(&optimizer).unwrap_err();
+        assert_eq!(err.to_string(), "world.animals must be at least 4 for differential evolution (rand/1/bin), got 3");
+        config.world.animals = 4;
+        assert!(config.validate_for(&optimizer).is_ok());
+
+        let err = SimulationConfig::from_toml("[predators]\ncount = 2\n[predators.eye]\ncells = 0\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "predators.eye.cells", .. }));
+        let err = SimulationConfig::from_toml("[eye.vision]\nkind = \"raycast\"\nhit_radius = 0\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "eye.vision.hit_radius", .. }));
+
+        let err = SimulationConfig::from_toml("[energy]\nenabled = true\ncapacity = 0\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "energy.capacity", .. }));
+
+        let err = SimulationConfig::from_toml("[reproduction]\nenabled = true\n").unwrap_err();
+        assert_eq!(err.to_string(), "reproduction.enabled must be false unless energy.enabled, got true");
+
+        let err = SimulationConfig::from_toml("[food.placement]\nkind = \"patches\"\ncount = 0\nradius = 0.1\ndrift = 0\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "food.placement.count", .. }));
+
+        let err = SimulationConfig::from_toml("[[sensors]]\nkind = \"rays\"\nrays = 0\nrange = 0.5\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "sensors", .. }));
+
+        let err = SimulationConfig::from_toml("[world]\nbirds = 3\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Toml(_)));
+
+        let err = SimulationConfig::load("missing.toml").unwrap_err();
+        assert!(matches!(err, ConfigError::Io(_)));
+
+        let path = std::env::temp_dir().join(format!("lib-simulation-config-{}.yaml", std::process::id()));
+        std::fs::write(&path, "world:\n  animals: 40\n").unwrap();
+        let err = SimulationConfig::load(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(err, ConfigError::UnknownFormat(p) if p == path));
+    }
+
+    #[test]
+    fn ignores_disabled_sections() {
+        let config = SimulationConfig::from_toml("[reproduction]\nenabled = false\nenergy_threshold = 0\nmax_age = 0\n").unwrap();
+        assert_eq!(config.reproduction.max_age, 0);
+
+        let err = SimulationConfig::from_toml("[energy]\nenabled = true\n[reproduction]\nenabled = true\nmax_age = 0\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "reproduction.max_age", .. }));
+    }
+}
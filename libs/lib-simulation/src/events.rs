@@ -0,0 +1,87 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+
+use genetic_algorithm::{Chromosome, EvolutionObserver, Parent};
+
+use crate::Statistics;
+
+/// Something that happened inside a `Simulation`, as seen by subscribers.
+///
+/// `child` is the index of the bird being born in the next generation,
+/// `index` the one of the parent in the generation that just ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationEvent {
+    ParentSelected { child: usize, parent: Parent, index: usize, score: f32 },
+    Crossover { child: usize, genes_from_first_parent: usize },
+    Mutation { child: usize, mutated_genes: usize },
+    GenerationEnd { generation: usize, statistics: Statistics },
+}
+
+/// Fans events out to every subscriber, forgetting about the ones that
+/// dropped their receiver
+#[derive(Debug, Default)]
+pub(crate) struct EventBus {
+    subscribers: Mutex<Vec<Sender<SimulationEvent>>>,
+}
+
+impl EventBus {
+    pub(crate) fn subscribe(&self) -> Receiver<SimulationEvent> {
+        let (sender, receiver) = channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    pub(crate) fn publish(&self, event: SimulationEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|s| s.send(event.clone()).is_ok());
+    }
+
+    fn has_subscribers(&self) -> bool {
+        !self.subscribers.lock().unwrap().is_empty()
+    }
+}
+
+impl EvolutionObserver for EventBus {
+    fn on_selection(&self, child: usize, parent: Parent, index: usize, fitness: f32) {
+        self.publish(SimulationEvent::ParentSelected { child, parent, index, score: fitness });
+    }
+
+    fn on_crossover(&self, child: usize, parent_a: &Chromosome, _parent_b: &Chromosome, offspring: &Chromosome) {
+        let genes_from_first_parent = parent_a.iter().zip(offspring.iter()).filter(|(p, o)| p == o).count();
+        self.publish(SimulationEvent::Crossover { child, genes_from_first_parent });
+    }
+
+    fn on_mutation(&self, child: usize, before: &Chromosome, after: &Chromosome) {
+        if !self.has_subscribers() {
+            return;
+        }
+        let mutated_genes = before.iter().zip(after.iter()).filter(|(b, a)| b != a).count();
+        self.publish(SimulationEvent::Mutation { child, mutated_genes });
+    }
+
+    // `GenerationEnd` is published by the simulation itself, which knows
+    // the scores of the birds and not only their fitness
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn subscribers_receive_generation_events() {
+        let mut simulation = Simulation::random(42, 5, 10, 0.1, 0.5, 10);
+        let events = simulation.subscribe();
+        let dropped = simulation.subscribe();
+        drop(dropped);
+
+        let statistics = (0..=10).find_map(|_| simulation.step().unwrap()).expect("Generation should end");
+        let events: Vec<_> = events.try_iter().collect();
+
+        assert_eq!(events[0], SimulationEvent::GenerationEnd { generation: 0, statistics });
+        let selections = events.iter().filter(|e| matches!(e, SimulationEvent::ParentSelected { .. })).count();
+        let crossovers = events.iter().filter(|e| matches!(e, SimulationEvent::Crossover { .. })).count();
+        let mutations = events.iter().filter(|e| matches!(e, SimulationEvent::Mutation { .. })).count();
+        assert_eq!((selections, crossovers, mutations), (10, 5, 5));
+        assert_eq!(simulation.generation(), 1);
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+
+use genetic_algorithm::{GeneticError, RunController, StopCondition};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{ConfigError, Simulation, SimulationConfig};
+
+/// A parameter sweep: which `SimulationConfig` fields to vary, how to pick
+/// their values and how long to run every point of the sweep.
+///
+/// Fields are named by their path in the config, e.g. in TOML:
+///
+/// ```toml
+/// generations = 50
+/// seeds = 5
+///
+/// [base.world]
+/// food = 30
+///
+/// [parameters]
+/// "evolution.mutation_chance" = [0.01, 0.05, 0.1]
+/// "world.animals" = { min = 10, max = 40, steps = 4 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExperimentSpec {
+    /// Config every point starts from
+    #[serde(default)]
+    pub base: SimulationConfig,
+    #[serde(default)]
+    pub search: Search,
+    pub parameters: BTreeMap<String, ParameterValues>,
+    /// Generations every run lasts
+    pub generations: usize,
+    /// Runs per point, seeded `seed`, `seed + 1`, ...; every point is run with
+    /// the same seeds so that points differ by their parameters only
+    #[serde(default = "default_seeds")]
+    pub seeds: u64,
+    /// First seed of the runs, also seeds the random search
+    #[serde(default)]
+    pub seed: u64,
+}
+
+fn default_seeds() -> u64 {
+    5
+}
+
+/// How the points of a sweep are picked
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Search {
+    /// Every combination of the parameters' values
+    #[default]
+    Grid,
+    /// `samples` points, each parameter drawn at random from its values
+    Random { samples: usize },
+}
+
+/// Values a parameter takes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum ParameterValues {
+    List(Vec<f64>),
+    /// `steps` evenly spaced values from `min` to `max` in a grid search;
+    /// the random search draws uniformly from the range and ignores `steps`
+    Range { min: f64, max: f64, steps: Option<usize> },
+}
+
+#[derive(Debug, Error)]
+pub enum ExperimentError {
+    #[error("{0} is not a field of the simulation config")]
+    UnknownParameter(String),
+    #[error("{0} is not a number, it cannot be swept")]
+    NotANumber(String),
+    #[error("{0} needs `steps` to be part of a grid search")]
+    MissingSteps(String),
+    #[error("{0} has no values")]
+    NoValues(String),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("run failed: {0}")]
+    Run(#[from] GeneticError),
+}
+
+/// One point of the sweep
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExperimentPoint {
+    /// Value of every parameter, in the order of `ExperimentSpec::parameters`
+    pub values: Vec<f64>,
+    pub config: SimulationConfig,
+}
+
+/// Final fitness of one run
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RunOutcome {
+    pub point: usize,
+    pub seed: u64,
+    /// Average score of the last generation
+    pub avg_fitness: f32,
+    /// Best score of the last generation
+    pub best_fitness: f32,
+}
+
+/// Final fitness of the runs of one point, as mean and half width of the 95%
+/// confidence interval of the mean
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PointResult {
+    pub values: Vec<f64>,
+    pub runs: usize,
+    pub avg_fitness: f32,
+    pub avg_fitness_ci: f32,
+    pub best_fitness: f32,
+    pub best_fitness_ci: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExperimentResults {
+    pub parameters: Vec<String>,
+    pub points: Vec<PointResult>,
+}
+
+impl ExperimentSpec {
+    pub fn from_toml(spec: &str) -> Result<Self, ExperimentError> {
+        Ok(toml::from_str(spec).map_err(ConfigError::from)?)
+    }
+
+    pub fn from_json(spec: &str) -> Result<Self, ExperimentError> {
+        Ok(serde_json::from_str(spec).map_err(ConfigError::from)?)
+    }
+
+    /// Reads a spec file, in the format given by its extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ExperimentError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(ConfigError::from)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Self::from_toml(&content),
+            Some("json") => Self::from_json(&content),
+            _ => Err(ConfigError::UnknownFormat(path.to_path_buf()).into()),
+        }
+    }
+
+    /// Every point of the sweep, with its config checked
+    pub fn points(&self) -> Result<Vec<ExperimentPoint>, ExperimentError> {
+        let combinations = match self.search {
+            Search::Grid => {
+                let axes = self
+                    .parameters
+                    .iter()
+                    .map(|(name, values)| values.grid(name))
+                    .collect::<Result<Vec<_>, _>>()?;
+                axes.iter().fold(vec![vec![]], |combinations, axis| {
+                    combinations
+                        .iter()
+                        .flat_map(|c| axis.iter().map(move |&v| c.iter().copied().chain([v]).collect()))
+                        .collect()
+                })
+            }
+            Search::Random { samples } => {
+                let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+                (0..samples)
+                    .map(|_| {
+                        self.parameters
+                            .iter()
+                            .map(|(name, values)| values.sample(name, &mut rng))
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+        };
+        let base = serde_json::to_value(&self.base).expect("Config can always be written as JSON");
+        combinations
+            .into_iter()
+            .map(|values: Vec<f64>| {
+                let mut config = base.clone();
+                for (name, &value) in self.parameters.keys().zip(&values) {
+                    set_field(&mut config, name, value)?;
+                }
+                let config = SimulationConfig::from_json(&config.to_string())?;
+                Ok(ExperimentPoint { values, config })
+            })
+            .collect()
+    }
+
+    /// Runs every point with every seed; see `run_with_progress`
+    pub fn run(&self) -> Result<ExperimentResults, ExperimentError> {
+        self.run_with_progress(|_| {})
+    }
+
+    /// Runs every point with every seed, on a rayon thread pool with the
+    /// `parallel` feature, and calls `progress` as each run ends
+    pub fn run_with_progress(&self, progress: impl Fn(&RunOutcome) + Sync) -> Result<ExperimentResults, ExperimentError> {
+        let points = self.points()?;
+        let runs: Vec<(usize, u64)> = (0..points.len())
+            .flat_map(|point| (0..self.seeds).map(move |i| (point, self.seed + i)))
+            .collect();
+        let controller = RunController::new(StopCondition::Generations(self.generations.max(1)));
+        let run = |&(point, seed): &(usize, u64)| -> Result<RunOutcome, ExperimentError> {
+            let mut simulation = Simulation::from_config(seed, points[point].config.clone())?;
+            simulation.run(&controller)?;
+            let last = simulation.history().last().expect("At least one generation ran");
+            let outcome = RunOutcome { point, seed, avg_fitness: last.avg_score, best_fitness: last.max_score };
+            progress(&outcome);
+            Ok(outcome)
+        };
+        #[cfg(feature = "parallel")]
+        let outcomes: Vec<RunOutcome> = runs.par_iter().map(run).collect::<Result<_, _>>()?;
+        #[cfg(not(feature = "parallel"))]
+        let outcomes: Vec<RunOutcome> = runs.iter().map(run).collect::<Result<_, _>>()?;
+
+        let points = points
+            .into_iter()
+            .enumerate()
+            .map(|(index, point)| {
+                let outcomes: Vec<&RunOutcome> = outcomes.iter().filter(|o| o.point == index).collect();
+                let (avg_fitness, avg_fitness_ci) = mean_ci(outcomes.iter().map(|o| o.avg_fitness));
+                let (best_fitness, best_fitness_ci) = mean_ci(outcomes.iter().map(|o| o.best_fitness));
+                PointResult { values: point.values, runs: outcomes.len(), avg_fitness, avg_fitness_ci, best_fitness, best_fitness_ci }
+            })
+            .collect();
+        Ok(ExperimentResults { parameters: self.parameters.keys().cloned().collect(), points })
+    }
+}
+
+impl ParameterValues {
+    fn grid(&self, name: &str) -> Result<Vec<f64>, ExperimentError> {
+        let values = match *self {
+            Self::List(ref values) => values.clone(),
+            Self::Range { min, max, steps: Some(steps) } => match steps {
+                0 => vec![],
+                1 => vec![min],
+                _ => (0..steps).map(|i| min + (max - min) * i as f64 / (steps - 1) as f64).collect(),
+            },
+            Self::Range { steps: None, .. } => return Err(ExperimentError::MissingSteps(name.to_string())),
+        };
+        if values.is_empty() {
+            return Err(ExperimentError::NoValues(name.to_string()));
+        }
+        Ok(values)
+    }
+
+    fn sample(&self, name: &str, rng: &mut ChaCha8Rng) -> Result<f64, ExperimentError> {
+        match *self {
+            Self::List(ref values) if values.is_empty() => Err(ExperimentError::NoValues(name.to_string())),
+            Self::List(ref values) => Ok(values[rng.gen_range(0..values.len())]),
+            Self::Range { min, max, .. } if min < max => Ok(rng.gen_range(min..=max)),
+            Self::Range { min, .. } => Ok(min),
+        }
+    }
+}
+
+// Sets the number at the dotted `path` of a config written as JSON, rounded
+// when the field holds an integer
+fn set_field(config: &mut Value, path: &str, value: f64) -> Result<(), ExperimentError> {
+    let field = path
+        .split('.')
+        .try_fold(config, |node, key| node.get_mut(key))
+        .ok_or_else(|| ExperimentError::UnknownParameter(path.to_string()))?;
+    *field = match field {
+        Value::Number(n) if n.is_u64() => Value::from(value.round().max(0.0) as u64),
+        Value::Number(_) => Value::from(value),
+        _ => return Err(ExperimentError::NotANumber(path.to_string())),
+    };
+    Ok(())
+}
+
+// Two-sided 95% quantiles of Student's t distribution, by degrees of freedom
+const T_95: [f32; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+    2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+    2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+];
+
+// Mean of `samples` and half width of its 95% confidence interval
+fn mean_ci(samples: impl Iterator<Item = f32>) -> (f32, f32) {
+    let samples: Vec<f32> = samples.collect();
+    let n = samples.len();
+    if n == 0 {
+        return (0.0, 0.0);
+    }
+    let mean = samples.iter().sum::<f32>() / n as f32;
+    if n == 1 {
+        return (mean, 0.0);
+    }
+    let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / (n - 1) as f32;
+    let t = T_95.get(n - 2).copied().unwrap_or(1.96);
+    (mean, t * (variance / n as f32).sqrt())
+}
+
+impl ExperimentResults {
+    /// One row per point: the parameters, then the number of runs and the
+    /// mean and confidence interval of both fitnesses
+    pub fn write_csv(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(out, "{},runs,avg_fitness,avg_fitness_ci,best_fitness,best_fitness_ci", self.parameters.join(","))?;
+        for point in &self.points {
+            let values: Vec<String> = point.values.iter().map(|v| v.to_string()).collect();
+            writeln!(
+                out,
+                "{},{},{},{},{},{}",
+                values.join(","), point.runs, point.avg_fitness, point.avg_fitness_ci, point.best_fitness, point.best_fitness_ci
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The point with the highest mean average fitness
+    pub fn best(&self) -> Option<&PointResult> {
+        self.points.iter().max_by(|a, b| a.avg_fitness.total_cmp(&b.avg_fitness))
+    }
+}
+
+/// Aligned text table, best average fitness first
+impl fmt::Display for ExperimentResults {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let widths: Vec<usize> = self.parameters.iter().map(|p| p.len().max(8)).collect();
+        for (parameter, width) in self.parameters.iter().zip(&widths) {
+            write!(f, "{:>width$}  ", parameter)?;
+        }
+        writeln!(f, "{:>4}  {:>16}  {:>16}", "runs", "avg fitness", "best fitness")?;
+        let mut points: Vec<&PointResult> = self.points.iter().collect();
+        points.sort_by(|a, b| b.avg_fitness.total_cmp(&a.avg_fitness));
+        for point in points {
+            for (value, width) in point.values.iter().zip(&widths) {
+                write!(f, "{:>width$}  ", format!("{:.4}", value))?;
+            }
+            writeln!(
+                f,
+                "{:>4}  {:>16}  {:>16}",
+                point.runs,
+                format!("{:.2} ± {:.2}", point.avg_fitness, point.avg_fitness_ci),
+                format!("{:.2} ± {:.2}", point.best_fitness, point.best_fitness_ci)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(search: &str) -> ExperimentSpec {
+        ExperimentSpec::from_toml(&format!(
+            "generations = 2\nseeds = 3\n{search}\n\
+             [base.world]\nanimals = 4\nfood = 8\n[base.evolution]\ngeneration_length = 20\n\
+             [parameters]\n\"evolution.mutation_chance\" = [0.05, 0.2]\n\"world.food\" = {{ min = 5, max = 15, steps = 3 }}\n"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn grid_covers_every_combination() {
+        let points = spec("").points().unwrap();
+        let values: Vec<Vec<f64>> = points.iter().map(|p| p.values.clone()).collect();
+        assert_eq!(values.len(), 6);
+        assert_eq!(values[0], [0.05, 5.0]);
+        assert_eq!(values[5], [0.2, 15.0]);
+        assert_eq!(points[1].config.world.food, 10);
+        assert_eq!(points[1].config.world.animals, 4);
+        assert_eq!(points[5].config.evolution.mutation_chance, 0.2);
+    }
+
+    #[test]
+    fn random_search_is_seeded() {
+        let spec = spec("[search]\nkind = \"random\"\nsamples = 4");
+        let points = spec.points().unwrap();
+        assert_eq!(points.len(), 4);
+        assert_eq!(points, spec.points().unwrap());
+        assert!(points.iter().all(|p| (5..=15).contains(&p.config.world.food)));
+    }
+
+    #[test]
+    fn aggregates_runs() {
+        let spec = spec("");
+        let results = spec.run().unwrap();
+        assert_eq!(results.parameters, ["evolution.mutation_chance", "world.food"]);
+        assert_eq!(results.points.len(), 6);
+        assert!(results.points.iter().all(|p| p.runs == 3 && p.avg_fitness_ci >= 0.0));
+        assert_eq!(results, spec.run().unwrap());
+
+        let mut csv = vec![];
+        results.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().next(), Some("evolution.mutation_chance,world.food,runs,avg_fitness,avg_fitness_ci,best_fitness,best_fitness_ci"));
+        assert_eq!(csv.lines().count(), 7);
+    }
+
+    #[test]
+    fn rejects_bad_parameters() {
+        let mut spec = spec("");
+        spec.parameters.insert("world.birds".into(), ParameterValues::List(vec![1.0]));
+        assert!(matches!(spec.points(), Err(ExperimentError::UnknownParameter(p)) if p == "world.birds"));
+
+        let mut spec = self::spec("");
+        spec.parameters.insert("brain.hidden_layers".into(), ParameterValues::List(vec![1.0]));
+        assert!(matches!(spec.points(), Err(ExperimentError::NotANumber(_))));
+
+        let mut spec = self::spec("");
+        spec.parameters.insert("evolution.mutation_coeff".into(), ParameterValues::Range { min: 0.0, max: 1.0, steps: None });
+        assert!(matches!(spec.points(), Err(ExperimentError::MissingSteps(_))));
+
+        let mut spec = self::spec("");
+        spec.parameters.insert("evolution.mutation_chance".into(), ParameterValues::List(vec![1.5]));
+        assert!(matches!(spec.points(), Err(ExperimentError::Config(ConfigError::Invalid { field: "evolution.mutation_chance", .. }))));
+    }
+
+    #[test]
+    fn confidence_interval_of_the_mean() {
+        assert_eq!(mean_ci([3.0].into_iter()), (3.0, 0.0));
+        let (mean, ci) = mean_ci([1.0, 2.0, 3.0, 4.0].into_iter());
+        assert_eq!(mean, 2.5);
+        approx::assert_relative_eq!(ci, 3.182 * (1.6667f32 / 4.0).sqrt(), epsilon = 1e-3);
+    }
+}
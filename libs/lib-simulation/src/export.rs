@@ -0,0 +1,271 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use genetic_algorithm::Individual;
+use serde::{Deserialize, Serialize};
+
+use crate::{Animal, AnimalId, AnimalIndividual, SimulationConfig, Species, Statistics, StatisticsHistory};
+
+/// How a run was configured, written as the first record of every export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunHeader {
+    /// Seed the simulation RNG was created from
+    pub seed: u64,
+    /// Name of the `Optimizer` evolving the birds
+    pub optimizer: String,
+    pub config: SimulationConfig,
+}
+
+/// Fitness and a summary of the genes of one bird
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct IndividualSummary {
+    pub id: AnimalId,
+    pub fitness: f32,
+    pub gene_mean: f32,
+    pub gene_std_dev: f32,
+    pub gene_min: f32,
+    pub gene_max: f32,
+}
+
+impl From<&AnimalIndividual> for IndividualSummary {
+    fn from(value: &AnimalIndividual) -> Self {
+        let genes = value.chromosome().genes();
+        let count = genes.len().max(1) as f32;
+        let gene_mean = genes.iter().sum::<f32>() / count;
+        let variance = genes.iter().map(|g| (g - gene_mean).powi(2)).sum::<f32>() / count;
+        Self {
+            id: value.id(),
+            fitness: value.fitness(),
+            gene_mean,
+            gene_std_dev: variance.sqrt(),
+            gene_min: genes.iter().copied().fold(f32::INFINITY, f32::min),
+            gene_max: genes.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+        }
+    }
+}
+
+/// Brain of the fittest bird seen so far, with the layer sizes needed to
+/// rebuild it as a `NeuralNetwork::from_weights`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Champion {
+    pub id: AnimalId,
+    /// Generation the bird was scored in
+    pub generation: usize,
+    pub fitness: f32,
+    /// Neurons of each layer, inputs first
+    pub layers: Vec<usize>,
+    pub weights: Vec<f32>,
+}
+
+impl Champion {
+    pub(crate) fn new(individual: &AnimalIndividual, generation: usize, config: &SimulationConfig) -> Self {
+        let layers = Animal::brain_layers(config, Species::Prey);
+        Self {
+            id: individual.id(),
+            generation,
+            fitness: individual.fitness(),
+            layers: layers.iter().map(|l| l.neurons).collect(),
+            weights: individual.chromosome().genes().to_vec(),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Comma separated values; the header record is a `#` comment line and
+    /// generations and individuals share the same columns
+    Csv,
+    /// One JSON object per line, tagged by its `type`
+    JsonLines,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    Header(&'a RunHeader),
+    Generation {
+        generation: usize,
+        #[serde(flatten)]
+        statistics: &'a Statistics,
+    },
+    Individual {
+        generation: usize,
+        #[serde(flatten)]
+        individual: &'a IndividualSummary,
+    },
+}
+
+const CSV_COLUMNS: &str = "record,generation,id,\
+min_score,avg_score,max_score,median_score,std_dev_score,p10,p25,p75,p90,\
+mean_pairwise_distance,mean_gene_variance,distinct_brains,food_eaten,collisions,mean_speed,\
+starved,population,births,food_regrown,food_rotten,mean_food_available,\
+fitness,gene_mean,gene_std_dev,gene_min,gene_max";
+
+// Statistics columns left empty on individual rows, and the other way round
+const CSV_STATISTICS_COLUMNS: usize = 21;
+const CSV_INDIVIDUAL_COLUMNS: usize = 5;
+
+/// Streams the evolution history of a run, one generation at a time
+pub struct HistoryWriter<W: Write> {
+    out: W,
+    format: ExportFormat,
+}
+
+impl<W: Write> HistoryWriter<W> {
+    /// Creates the writer and writes the header record right away
+    pub fn new(mut out: W, format: ExportFormat, header: &RunHeader) -> io::Result<Self> {
+        match format {
+            ExportFormat::Csv => {
+                writeln!(
+                    out,
+                    "# seed={} optimizer=\"{}\" config={}",
+                    header.seed,
+                    header.optimizer,
+                    serde_json::to_string(&header.config)?
+                )?;
+                writeln!(out, "{}", CSV_COLUMNS)?;
+            }
+            ExportFormat::JsonLines => write_json_line(&mut out, &Record::Header(header))?,
+        }
+        Ok(Self { out, format })
+    }
+
+    pub fn write_generation(&mut self, generation: usize, statistics: &Statistics) -> io::Result<()> {
+        match self.format {
+            ExportFormat::Csv => {
+                let s = statistics;
+                let p = &s.percentiles;
+                let values: [&dyn Display; CSV_STATISTICS_COLUMNS] = [
+                    &s.min_score, &s.avg_score, &s.max_score, &s.median_score, &s.std_dev_score,
+                    &p.p10, &p.p25, &p.p75, &p.p90,
+                    &s.mean_pairwise_distance, &s.mean_gene_variance, &s.distinct_brains,
+                    &s.food_eaten, &s.collisions, &s.mean_speed, &s.starved, &s.population, &s.births,
+                    &s.food_regrown, &s.food_rotten, &s.mean_food_available,
+                ];
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                writeln!(self.out, "generation,{},,{}{}", generation, values.join(","), ",".repeat(CSV_INDIVIDUAL_COLUMNS))
+            }
+            ExportFormat::JsonLines => write_json_line(&mut self.out, &Record::Generation { generation, statistics }),
+        }
+    }
+
+    pub fn write_individuals(&mut self, generation: usize, individuals: &[IndividualSummary]) -> io::Result<()> {
+        for individual in individuals {
+            match self.format {
+                ExportFormat::Csv => {
+                    let i = individual;
+                    writeln!(
+                        self.out,
+                        "individual,{},{},{}{},{},{},{},{}",
+                        generation, i.id.0, ",".repeat(CSV_STATISTICS_COLUMNS),
+                        i.fitness, i.gene_mean, i.gene_std_dev, i.gene_min, i.gene_max
+                    )?;
+                }
+                ExportFormat::JsonLines => {
+                    write_json_line(&mut self.out, &Record::Individual { generation, individual })?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes every generation of `history`, numbering them from 0
+    pub fn write_history(&mut self, history: &StatisticsHistory) -> io::Result<()> {
+        for (generation, statistics) in history.iter().enumerate() {
+            self.write_generation(generation, statistics)?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+fn write_json_line(out: &mut impl Write, record: &Record) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, record)?;
+    writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> RunHeader {
+        RunHeader {
+            seed: 42,
+            optimizer: "genetic algorithm".to_string(),
+            config: SimulationConfig::default(),
+        }
+    }
+
+    fn individual() -> IndividualSummary {
+        IndividualSummary { id: AnimalId(7), fitness: 3.0, gene_mean: 0.5, gene_std_dev: 0.25, gene_min: 0.0, gene_max: 1.0 }
+    }
+
+    #[test]
+    fn writes_csv() {
+        let mut writer = HistoryWriter::new(vec![], ExportFormat::Csv, &header()).unwrap();
+        writer.write_generation(0, &Statistics { max_score: 3.0, avg_score: 1.5, ..Default::default() }).unwrap();
+        writer.write_individuals(0, &[individual()]).unwrap();
+        let csv = String::from_utf8(writer.into_inner()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        let config = lines[0].strip_prefix("# seed=42 optimizer=\"genetic algorithm\" config=").unwrap();
+        assert_eq!(SimulationConfig::from_json(config).unwrap(), SimulationConfig::default());
+        let columns = lines[1].split(',').count();
+        assert!(lines[2..].iter().all(|l| l.split(',').count() == columns));
+        assert_eq!(lines[2], "generation,0,,0,1.5,3,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,,,,,");
+        assert_eq!(lines[3], "individual,0,7,,,,,,,,,,,,,,,,,,,,,,3,0.5,0.25,0,1");
+    }
+
+    #[test]
+    fn writes_json_lines() {
+        let mut writer = HistoryWriter::new(vec![], ExportFormat::JsonLines, &header()).unwrap();
+        writer.write_generation(0, &Statistics { max_score: 3.0, ..Default::default() }).unwrap();
+        writer.write_individuals(0, &[individual()]).unwrap();
+        let jsonl = String::from_utf8(writer.into_inner()).unwrap();
+        let records: Vec<serde_json::Value> = jsonl.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0]["type"], "header");
+        assert_eq!(records[0]["seed"], 42);
+        assert_eq!(records[1]["type"], "generation");
+        assert_eq!(records[1]["max_score"], 3.0);
+        assert_eq!(records[1]["percentiles"]["p90"], 0.0);
+        assert_eq!(records[2]["type"], "individual");
+        assert_eq!(records[2]["id"], 7);
+    }
+
+    #[test]
+    fn exports_a_simulation() {
+        use crate::Simulation;
+
+        let mut simulation = Simulation::random(42, 5, 10, 0.1, 0.5, 10);
+        let mut writer = HistoryWriter::new(vec![], ExportFormat::JsonLines, &simulation.run_header()).unwrap();
+        for _ in 0..2 {
+            let statistics = (0..=10).find_map(|_| simulation.step().unwrap()).unwrap();
+            let generation = simulation.history().len() - 1;
+            writer.write_generation(generation, &statistics).unwrap();
+            writer.write_individuals(generation, simulation.last_generation()).unwrap();
+        }
+        let jsonl = String::from_utf8(writer.into_inner()).unwrap();
+
+        assert_eq!(simulation.last_generation().len(), 5);
+        assert!(simulation.last_generation().iter().all(|i| i.id.0 >= 5));
+        assert_eq!(jsonl.lines().count(), 1 + 2 * (1 + 5));
+        let header: serde_json::Value = serde_json::from_str(jsonl.lines().next().unwrap()).unwrap();
+        assert_eq!(header["seed"], 42);
+        assert_eq!(header["optimizer"], "genetic algorithm");
+        assert_eq!(header["config"]["world"]["animals"], 5);
+        assert_eq!(header["config"]["evolution"]["generation_length"], 10);
+    }
+}
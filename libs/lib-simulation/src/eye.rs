@@ -1,66 +1,248 @@
-use crate::*;
 use std::f32::consts::*;
 use nalgebra::{Rotation2,Vector2,Point2,wrap};
 
-pub(crate) const FOV_RANGE: f32 = 0.75;
-pub(crate) const FOV_ANGLE: f32 = FRAC_PI_4;
-pub(crate) const CELLS: usize = 13;
 
+use crate::obstacle::{self, Obstacle};
+use crate::sensor::{Sensor, Surroundings};
+use crate::{Animal, EyeConfig, SimulationConfig, SpatialGrid, Species, Topology, Vision};
+
+/// What the cells of one channel of an eye react to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// Food of the kind at this index of `FoodConfig::kinds`
+    Food(usize),
+    Prey,
+    Predators,
+    Obstacles,
+}
+
+/// What a bird sees, split into `cells` across its field of view, centered
+/// on its heading like its flight: along the x axis turned by its rotation
 #[derive(Debug)]
 pub struct Eye {
     fov_range: f32,
     fov_angle: f32,
-    cells: usize
+    cells: usize,
+    /// One set of `cells` each, in the order the brain gets them
+    channels: Vec<Channel>,
+    vision: Vision,
 }
 
 impl Eye {
 
     pub fn new(fov_range: f32, fov_angle: f32, cells: usize) -> Self {
-        assert!(fov_range <= 1.0 && fov_range >= 0.0);
+        assert!((0.0..=1.0).contains(&fov_range));
         assert!(cells > 0);
-        Self { fov_range, fov_angle, cells }
+        Self { fov_range, fov_angle, cells, channels: vec![Channel::Food(0), Channel::Prey], vision: Vision::Cone }
     }
 
-    pub(crate) fn process_vision(&self, position: Point2<f32>, rotation: Rotation2<f32>, elements: &[Point2<f32>]) -> Vec<f32> {
+    /// The eye of the birds of `species` in a simulation: prey see each
+    /// visible kind of food and each other, then predators if there are any;
+    /// predators see prey and each other. Both see obstacles when the world
+    /// has some.
+    pub(crate) fn for_species(config: &SimulationConfig, species: Species) -> Self {
+        let (eye, mut channels) = match species {
+            Species::Prey => {
+                let kinds = config.food.kinds();
+                let food = kinds.iter().enumerate().filter(|(_, kind)| kind.visible).map(|(index, _)| Channel::Food(index));
+                let mut channels: Vec<Channel> = food.chain([Channel::Prey]).collect();
+                if config.predators.count > 0 {
+                    channels.push(Channel::Predators);
+                }
+                (&config.eye, channels)
+            }
+            Species::Predator => (&config.predators.eye, vec![Channel::Prey, Channel::Predators]),
+        };
+        if !config.world.obstacles.is_empty() {
+            channels.push(Channel::Obstacles);
+        }
+        Self { channels, ..Self::from(eye) }
+    }
+
+    /// Things hidden behind an obstacle are not seen
+    pub(crate) fn process_vision(
+        &self,
+        position: Point2<f32>,
+        rotation: Rotation2<f32>,
+        elements: impl IntoIterator<Item = Point2<f32>>,
+        obstacles: &[Obstacle],
+        topology: Topology,
+    ) -> Vec<f32> {
         let mut activated_cells: Vec<f32> = (0..self.cells).map(|_| 0.0).collect();
 
-        for f in elements.iter().filter(|f| !position.eq(f)) {
+        let nearby = obstacle::nearby(obstacles, topology, position, self.fov_range);
+        for f in elements.into_iter().filter(|f| !position.eq(f)) {
             let dist_vec = f - position;
             let dist = dist_vec.norm();
             if dist > self.fov_range {
-                return activated_cells;
+                continue;
             }
-            let angle = Rotation2::rotation_between(&Vector2::y(), &dist_vec).angle() - rotation.angle();
+            let angle = Rotation2::rotation_between(&Vector2::x(), &dist_vec).angle() - rotation.angle();
             let angle = wrap(angle, -PI, PI);
             if angle < - self.fov_angle / 2.0 || angle > self.fov_angle / 2.0 {
-                return activated_cells;
+                continue;
             }
             let angle = angle + self.fov_angle / 2.0;
             let cell = angle / self.fov_angle;
             let cell = cell * (self.cells as f32);
             let cell = (cell as usize).min(self.cells - 1);
+            if obstacle::cast_among(&nearby, position, dist_vec / dist, dist).is_some() {
+                continue;
+            }
             let energy = (self.fov_range - dist) / self.fov_range;
             activated_cells[cell] += energy;
         }
         activated_cells
     }
 
+    /// One ray through the middle of each cell, activated the more the
+    /// closer it meets an obstacle
+    pub(crate) fn process_obstacles(&self, position: Point2<f32>, rotation: Rotation2<f32>, obstacles: &[Obstacle], topology: Topology) -> Vec<f32> {
+        let nearby = obstacle::nearby(obstacles, topology, position, self.fov_range);
+        (0..self.cells)
+            .map(|cell| {
+                obstacle::cast_among(&nearby, position, self.ray(rotation, cell), self.fov_range)
+                    .map_or(0.0, |hit| (self.fov_range - hit.distance) / self.fov_range)
+            })
+            .collect()
+    }
+
+    /// One ray through the middle of each cell, stopping at the first thing
+    /// it meets: an obstacle, or one of the `elements` seen on each channel,
+    /// as wide as `hit_radius`. Only the cell of the channel of that thing is
+    /// activated, the more the closer it is, so whatever is behind it stays
+    /// hidden.
+    pub(crate) fn process_rays(
+        &self,
+        position: Point2<f32>,
+        rotation: Rotation2<f32>,
+        elements: &[Vec<Point2<f32>>],
+        obstacles: &[Obstacle],
+        topology: Topology,
+        hit_radius: f32,
+    ) -> Vec<f32> {
+        let mut activated_cells = vec![0.0; self.cells * self.channels.len()];
+
+        let nearby = obstacle::nearby(obstacles, topology, position, self.fov_range);
+        let obstacle_channel = self.channels.iter().position(|&channel| channel == Channel::Obstacles);
+        for cell in 0..self.cells {
+            let direction = self.ray(rotation, cell);
+            let obstacle = obstacle::cast_among(&nearby, position, direction, self.fov_range).map(|hit| (hit.distance, obstacle_channel));
+            let seen = elements.iter().enumerate().flat_map(|(channel, elements)| {
+                elements
+                    .iter()
+                    .filter(|&f| !position.eq(f))
+                    .filter_map(move |&f| ray_hit(position, direction, f, hit_radius))
+                    .map(move |distance| (distance, Some(channel)))
+            });
+            let first = obstacle.into_iter().chain(seen).filter(|&(distance, _)| distance <= self.fov_range).min_by(|a, b| a.0.total_cmp(&b.0));
+            // Obstacles still hide what is behind them from eyes without a
+            // channel to see them
+            if let Some((distance, Some(channel))) = first {
+                activated_cells[channel * self.cells + cell] = (self.fov_range - distance) / self.fov_range;
+            }
+        }
+        activated_cells
+    }
+
+    // Direction of the ray through the middle of `cell`; like birds flying,
+    // eyes look along the x axis turned by `rotation`
+    fn ray(&self, rotation: Rotation2<f32>, cell: usize) -> Vector2<f32> {
+        let angle = (cell as f32 + 0.5) / self.cells as f32 * self.fov_angle - self.fov_angle / 2.0;
+        Rotation2::new(rotation.angle() + angle) * Vector2::x()
+    }
+
+    pub fn fov_range(&self) -> f32 {
+        self.fov_range
+    }
+
     pub fn cells(&self) -> usize {
         self.cells
     }
 
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    pub fn vision(&self) -> Vision {
+        self.vision
+    }
+
+}
+
+// How far along the ray from `origin` going along the unit `direction` it
+// meets the disk of `radius` around `element`, if it does
+fn ray_hit(origin: Point2<f32>, direction: Vector2<f32>, element: Point2<f32>, radius: f32) -> Option<f32> {
+    let offset = element - origin;
+    let along = offset.dot(&direction);
+    let across = offset.norm_squared() - along * along;
+    if along < 0.0 || across > radius * radius {
+        return None;
+    }
+    Some((along - (radius * radius - across).sqrt()).max(0.0))
+}
+
+/// One set of cells per channel, each seeing only what is in range, the way
+/// the topology shows it
+impl Sensor for Eye {
+    fn inputs(&self) -> usize {
+        self.cells * self.channels.len()
+    }
+
+    fn sense(&self, animal: &Animal, surroundings: &Surroundings, out: &mut Vec<f32>) {
+        let topology = surroundings.topology();
+        let grid = |channel: Channel| -> Option<&SpatialGrid> {
+            match channel {
+                Channel::Food(kind) => Some(surroundings.food(kind)),
+                Channel::Prey => Some(surroundings.prey()),
+                Channel::Predators => Some(surroundings.predators()),
+                Channel::Obstacles => None,
+            }
+        };
+        match self.vision {
+            Vision::Cone => {
+                for &channel in &self.channels {
+                    let Some(grid) = grid(channel) else {
+                        out.extend(self.process_obstacles(animal.position, animal.rotation, surroundings.obstacles(), topology));
+                        continue;
+                    };
+                    let seen = grid.within(animal.position, self.fov_range).map(|(_, p)| p);
+                    out.extend(self.process_vision(animal.position, animal.rotation, seen, surroundings.obstacles(), topology));
+                }
+            }
+            Vision::Raycast { hit_radius } => {
+                let seen: Vec<Vec<Point2<f32>>> = self
+                    .channels
+                    .iter()
+                    .map(|&channel| {
+                        grid(channel).map_or_else(Vec::new, |grid| grid.within(animal.position, self.fov_range + hit_radius).map(|(_, p)| p).collect())
+                    })
+                    .collect();
+                out.extend(self.process_rays(animal.position, animal.rotation, &seen, surroundings.obstacles(), topology, hit_radius));
+            }
+        }
+    }
 }
 
 
 impl Default for Eye {
     fn default() -> Self {
-        Self::new(FOV_RANGE, FOV_ANGLE, CELLS)
+        Self::from(&EyeConfig::default())
+    }
+}
+
+impl From<&EyeConfig> for Eye {
+    fn from(config: &EyeConfig) -> Self {
+        Self { vision: config.vision, ..Self::new(config.fov_range, config.fov_angle, config.cells) }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Food;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
     use test_case::test_case;
 
     struct TestCase {
@@ -90,18 +272,126 @@ mod tests {
 
         fn run(self) {
             let eye = Eye {fov_range: self.fov_range, fov_angle: self.fov_angle, ..Default::default()};
-            let actual_vision = eye.process_vision(Point2::new(self.x, self.y), Rotation2::new(self.rot), self.foods.as_slice());
+            let foods: Vec<Point2<f32>> = self.foods.iter().map(|f| f.position).collect();
+            let actual_vision = eye.process_vision(Point2::new(self.x, self.y), Rotation2::new(self.rot), foods.iter().copied(), &[], Topology::Torus);
             let actual_vision = Self::vision_to_string_repr(&actual_vision);
             assert_eq!(actual_vision.as_str(), self.expected_vision);
         }
     }
 
     fn food(x: f32, y: f32) -> Food {
-        Food {
-            position: Point2::new(x, y),
+        Food::new(Point2::new(x, y), 0)
+    }
+
+    /// What a raycast eye sees on its food, prey and obstacle channels, one
+    /// after the other and split by `|`, from a bird flying among `birds`
+    struct RayTestCase {
+        foods: Vec<Point2<f32>>,
+        birds: Vec<Point2<f32>>,
+        obstacles: Vec<Obstacle>,
+        topology: Topology,
+        x: f32,
+        y: f32,
+        rot: f32,
+        expected_vision: &'static str,
+    }
+
+    impl RayTestCase {
+        fn run(self) {
+            let eye = Eye {
+                fov_range: 1.0,
+                fov_angle: FRAC_PI_2,
+                channels: vec![Channel::Food(0), Channel::Prey, Channel::Obstacles],
+                vision: Vision::Raycast { hit_radius: 0.01 },
+                ..Default::default()
+            };
+            let mut config = SimulationConfig::default();
+            config.world.topology = self.topology;
+            let mut animal = Animal::random(&mut ChaCha8Rng::seed_from_u64(0));
+            animal.position = Point2::new(self.x, self.y);
+            animal.rotation = Rotation2::new(self.rot);
+            // The bird is among the birds it could see, but never sees itself
+            let birds: Vec<_> = self.birds.into_iter().chain([animal.position]).collect();
+            let food = [SpatialGrid::new(self.topology, &self.foods)];
+            let birds = SpatialGrid::new(self.topology, &birds);
+            let no_predators = SpatialGrid::new(self.topology, &[]);
+            let surroundings =
+                Surroundings { food: &food, prey: &birds, predators: &no_predators, obstacles: &self.obstacles, config: &config, clock: 0 };
+
+            let mut vision = vec![];
+            eye.sense(&animal, &surroundings, &mut vision);
+            assert_eq!(vision.len(), eye.inputs());
+            let channels: Vec<_> = vision.chunks(eye.cells).map(TestCase::vision_to_string_repr).collect();
+            assert_eq!(channels.join("|"), self.expected_vision);
         }
     }
 
+    // The bird looks up, its first cells seeing to its right
+    #[test_case(vec![Point2::new(0.5, 0.7)], vec![], "      #      |             |             ")] // Food ahead
+    #[test_case(vec![Point2::new(0.5, 0.7), Point2::new(0.5, 0.9)], vec![], "      #      |             |             ")] // It hides the food behind
+    #[test_case(vec![Point2::new(0.5, 0.9)], vec![Point2::new(0.5, 0.7)], "             |      #      |             ")] // So does a bird
+    #[test_case(vec![Point2::new(0.5, 0.7)], vec![Point2::new(0.5, 0.9)], "      #      |             |             ")] // But not one behind the food
+    #[test_case(vec![Point2::new(0.4, 0.9), Point2::new(0.6, 0.9)], vec![], "    +   +    |             |             ")] // Rays see only along them
+    #[test_case(vec![Point2::new(0.5, 0.3)], vec![Point2::new(0.5, 0.2)], "             |             |             ")] // Out of sight
+    fn rays_see_the_first_thing_they_meet(foods: Vec<Point2<f32>>, birds: Vec<Point2<f32>>, expected_vision: &'static str) {
+        RayTestCase { foods, birds, obstacles: vec![], topology: Topology::Torus, x: 0.5, y: 0.5, rot: FRAC_PI_2, expected_vision }.run()
+    }
+
+    #[test]
+    fn rays_stop_at_obstacles() {
+        RayTestCase {
+            foods: vec![Point2::new(0.4, 0.9), Point2::new(0.6, 0.9)],
+            birds: vec![],
+            obstacles: vec![Obstacle::Polyline { points: vec![Point2::new(0.3, 0.7), Point2::new(0.49, 0.7)] }],
+            topology: Topology::Walls,
+            x: 0.5,
+            y: 0.5,
+            rot: FRAC_PI_2,
+            expected_vision: "    +        |             |       ######",
+        }
+        .run()
+    }
+
+    #[test_case(Topology::Torus, "      #      |             |             ")] // Across the edge
+    #[test_case(Topology::Walls, "             |             |             ")]
+    #[test_case(Topology::Reflecting, "             |             |             ")]
+    fn rays_wrap_around(topology: Topology, expected_vision: &'static str) {
+        RayTestCase { foods: vec![Point2::new(0.5, 0.1)], birds: vec![], obstacles: vec![], topology, x: 0.5, y: 0.9, rot: FRAC_PI_2, expected_vision }.run()
+    }
+
+    #[test_case(0.0)]
+    #[test_case(1.0)]
+    #[test_case(-2.5)]
+    fn looks_where_it_flies(heading: f32) {
+        let eye = Eye { fov_range: 1.0, fov_angle: FRAC_PI_2, ..Default::default() };
+        let position = Point2::new(0.5, 0.5);
+        let (ahead, _) = Topology::Walls.advance(position, Rotation2::new(heading), 0.3);
+        let vision = eye.process_vision(position, Rotation2::new(heading), [ahead], &[], Topology::Walls);
+        assert_eq!(TestCase::vision_to_string_repr(&vision), "      #      ");
+    }
+
+    #[test]
+    fn does_not_see_through_obstacles() {
+        let eye = Eye { fov_range: 1.0, fov_angle: FRAC_PI_2, ..Default::default() };
+        let wall = [Obstacle::Polyline { points: vec![Point2::new(0.3, 0.7), Point2::new(0.5, 0.7)] }];
+        let foods = [Point2::new(0.4, 0.9), Point2::new(0.6, 0.9)];
+        let position = Point2::new(0.5, 0.5);
+
+        let seen = |obstacles: &[Obstacle]| {
+            let vision = eye.process_vision(position, Rotation2::new(FRAC_PI_2), foods, obstacles, Topology::Walls);
+            TestCase::vision_to_string_repr(&vision)
+        };
+        assert_eq!(seen(&[]), "    +   +    ");
+        assert_eq!(seen(&wall), "    +        ");
+
+        // The wall fills the left half of the eye, the nearer the further
+        // ahead
+        let vision = eye.process_obstacles(position, Rotation2::new(FRAC_PI_2), &wall, Topology::Walls);
+        assert!(vision[..6].iter().all(|&v| v == 0.0), "{vision:?}");
+        assert!(vision[7..].iter().all(|&v| v > 0.7), "{vision:?}");
+        assert!(vision[7] > vision[12], "{vision:?}");
+    }
+
     #[test_case(1.0, "      +      ")] // Food is inside the FOV
     #[test_case(0.9, "      +      ")] // ditto
     #[test_case(0.8, "      +      ")] // ditto
@@ -119,22 +409,23 @@ mod tests {
             fov_angle: FRAC_PI_2,
             x: 0.5,
             y: 0.5,
-            rot: 0.0,
-            expected_vision: expected_vision
+            rot: FRAC_PI_2,
+            expected_vision
         }.run()
     }
 
-    #[test_case(0.00 * PI, "         +   ")] // Food is to our right
-    #[test_case(0.25 * PI, "        +    ")]
-    #[test_case(0.50 * PI, "      +      ")] // Food is in front of us
-    #[test_case(0.75 * PI, "    +        ")]
-    #[test_case(1.00 * PI, "   +         ")] // Food is to our left
-    #[test_case(1.25 * PI, " +           ")]
-    #[test_case(1.50 * PI, "            +")] // Food is behind us
-    #[test_case(1.75 * PI, "           + ")] // (we continue to see it
-    #[test_case(2.00 * PI, "         +   ")] // due to 360° fov_angle.)
-    #[test_case(2.25 * PI, "        +    ")]
-    #[test_case(2.50 * PI, "      +      ")]
+    #[test_case(0.00 * PI, "+            ")] // Food is right behind us, where
+                                             // both edges of the eye meet
+    #[test_case(0.25 * PI, "           + ")]
+    #[test_case(0.50 * PI, "         +   ")] // Food is to our right
+    #[test_case(0.75 * PI, "        +    ")]
+    #[test_case(1.00 * PI, "      +      ")] // Food is in front of us
+    #[test_case(1.25 * PI, "    +        ")]
+    #[test_case(1.50 * PI, "   +         ")] // Food is to our left
+    #[test_case(1.75 * PI, " +           ")]
+    #[test_case(2.00 * PI, "+            ")] // Food is behind us
+    #[test_case(2.25 * PI, "           + ")] // (we continue to see it
+    #[test_case(2.50 * PI, "         +   ")] // due to 360° fov_angle.)
     fn rotations(rot: f32, expected_vision: &'static str) {
         TestCase {
             foods: vec![food(0.0, 0.5)],
@@ -171,13 +462,13 @@ mod tests {
     #[test_case(0.5, 0.7, "  + +        ")]
     #[test_case(0.5, 0.8, "+  +         ")]
     #[test_case(0.5, 0.9, ". +          ")]
-    #[test_case(0.5, 1.0, "             ")]
+    #[test_case(0.5, 1.0, "+            ")] // The other food is out of sight, not this one
     fn positions(x: f32, y: f32, expected_vision: &'static str) {
         TestCase {
             foods: vec![food(1.0, 0.4), food(1.0, 0.6)],
             fov_range: 1.0,
             fov_angle: FRAC_PI_2,
-            rot: 3.0 * FRAC_PI_2,
+            rot: 0.0,
             x,
             y,
             expected_vision,
@@ -1,22 +1,147 @@
 use nalgebra::geometry::Point2;
-use nalgebra::{Rotation2, wrap, distance};
-use rand::{rngs, Rng, RngCore};
+use nalgebra::Rotation2;
+use rand::{Rng, RngCore};
+
+use crate::obstacle;
+use crate::{FoodKind, FoodPlacement, SimulationConfig};
 
 
 #[derive(Debug)]
 pub struct Food {
-   pub(crate) position: Point2<f32>
+   pub(crate) position: Point2<f32>,
+   /// Index of its kind in `FoodConfig::kinds`
+   pub(crate) kind: usize,
+   /// Whether the food was eaten, or rotted, and has not grown back yet
+   pub(crate) eaten: bool,
+   /// Steps left before eaten food may grow back
+   pub(crate) regrows_in: u32,
+   /// Steps the food has been available for since it grew
+   pub(crate) age: u32,
+   /// Whether the season lets this food grow at all
+   pub(crate) in_season: bool,
 }
 
 
 impl Food {
     pub fn random(rng: &mut dyn RngCore) -> Self {
-        Self {
-            position: rng.gen()
-        }
+        Self::new(rng.gen(), 0)
+    }
+
+    pub(crate) fn new(position: Point2<f32>, kind: usize) -> Self {
+        Self { position, kind, eaten: false, regrows_in: 0, age: 0, in_season: true }
     }
 
     pub fn position(&self) -> Point2<f32> {
         self.position
     }
+
+    /// Index of the kind of this food in `FoodConfig::kinds`
+    pub fn kind(&self) -> usize {
+        self.kind
+    }
+
+    /// Whether birds can see and eat this food right now
+    pub fn is_available(&self) -> bool {
+        !self.eaten && self.in_season
+    }
+
+    /// Takes the food out of the world until it grows back, in `delay` steps
+    /// at the earliest
+    pub(crate) fn remove(&mut self, delay: u32) {
+        self.eaten = true;
+        self.regrows_in = delay;
+    }
+
+    /// Grows the food back, fresh, at `position`
+    pub(crate) fn grow(&mut self, position: Point2<f32>) {
+        self.position = position;
+        self.eaten = false;
+        self.age = 0;
+    }
+}
+
+/// Where food grows, as `config.food.placement` says: anywhere outside the
+/// obstacles, or around one of the `patches`
+pub(crate) fn grow_position(config: &SimulationConfig, patches: &[Point2<f32>], rng: &mut dyn RngCore) -> Point2<f32> {
+    let topology = config.world.topology;
+    match config.food.placement {
+        FoodPlacement::Uniform => obstacle::free_position(&config.world.obstacles, topology, rng),
+        FoodPlacement::Patches { radius, .. } => obstacle::free_position_with(&config.world.obstacles, topology, rng, |rng| {
+            let center = patches[rng.gen_range(0..patches.len())];
+            // Uniform over the disk of the patch
+            let distance = radius * rng.gen::<f32>().sqrt();
+            topology.advance(center, rng.gen::<Rotation2<f32>>(), distance).0
+        }),
+    }
+}
+
+/// Kind of each of `count` foods, in proportion to the shares of `kinds` and
+/// spread evenly, so that the seasons take some of every kind away
+pub(crate) fn assign_kinds(kinds: &[FoodKind], count: usize) -> Vec<usize> {
+    let total: f32 = kinds.iter().map(|k| k.share).sum();
+    let mut assigned = vec![0; kinds.len()];
+    (0..count)
+        .map(|index| {
+            let due = |kind: usize| (index + 1) as f32 * kinds[kind].share / total - assigned[kind] as f32;
+            // The first of the kinds most behind their share
+            let kind = (0..kinds.len()).rev().max_by(|&a, &b| due(a).total_cmp(&due(b))).expect("There is always a kind of food");
+            assigned[kind] += 1;
+            kind
+        })
+        .collect()
+}
+
+/// Share of the food in season after `clock` steps, following a cycle of
+/// `length` steps that starts at its high; always 1 without seasons
+pub(crate) fn season(clock: u64, length: u32, amplitude: f32) -> f32 {
+    if length == 0 {
+        return 1.0;
+    }
+    let phase = (clock % length as u64) as f32 / length as f32;
+    1.0 - amplitude * (1.0 - (std::f32::consts::TAU * phase).cos()) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FoodConfig, Topology};
+    use approx::assert_relative_eq;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use test_case::test_case;
+
+    #[test_case(0, 1.0)]
+    #[test_case(25, 0.8)]
+    #[test_case(50, 0.6)]
+    #[test_case(75, 0.8)]
+    #[test_case(100, 1.0)]
+    fn seasons_come_and_go(clock: u64, expected: f32) {
+        assert_relative_eq!(season(clock, 100, 0.4), expected, epsilon = 1e-6);
+        assert_eq!(season(clock, 0, 0.4), 1.0);
+    }
+
+    #[test]
+    fn spreads_kinds_by_share() {
+        let kinds = [
+            FoodKind { share: 3.0, ..Default::default() },
+            FoodKind { share: 1.0, ..Default::default() },
+            FoodKind { share: 0.5, ..Default::default() },
+        ];
+        assert_eq!(assign_kinds(&kinds, 9), [0, 1, 0, 0, 2, 0, 0, 1, 0]);
+        assert_eq!(assign_kinds(&kinds[..1], 3), [0, 0, 0]);
+    }
+
+    #[test]
+    fn grows_in_patches() {
+        let mut config = SimulationConfig::default();
+        config.world.topology = Topology::Walls;
+        config.food = FoodConfig { placement: FoodPlacement::Patches { count: 2, radius: 0.05, drift: 0.0 }, ..Default::default() };
+        let patches = [Point2::new(0.2, 0.2), Point2::new(0.99, 0.5)];
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        for _ in 0..100 {
+            let position = grow_position(&config, &patches, &mut rng);
+            assert!(patches.iter().any(|p| nalgebra::distance(p, &position) <= 0.05 + 1e-6), "{position}");
+            assert!(position.x <= 1.0);
+        }
+    }
 }
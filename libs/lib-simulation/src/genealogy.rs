@@ -0,0 +1,285 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+
+use genetic_algorithm::Parent;
+use serde::{Deserialize, Serialize};
+
+use crate::SimulationEvent;
+
+/// Stable identifier of a bird, never reused within a `Simulation`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AnimalId(pub u64);
+
+impl fmt::Display for AnimalId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// How a bird came to be
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineageRecord {
+    pub id: AnimalId,
+    /// Generation the bird was born in; founders belong to generation 0
+    pub generation: usize,
+    /// Both parents, `None` for the founders of the simulation
+    pub parents: Option<[AnimalId; 2]>,
+    /// How many genes the crossover took from the first parent
+    pub genes_from_first_parent: usize,
+    /// How many genes were changed by the mutation
+    pub mutated_genes: usize,
+    /// Score at the end of the bird's life, `None` while it is still flying
+    pub score: Option<i32>,
+}
+
+/// Family tree of every bird that lived in a `Simulation`
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Genealogy {
+    records: BTreeMap<AnimalId, LineageRecord>,
+    next_id: u64,
+}
+
+#[derive(Default)]
+struct Birth {
+    parents: [Option<usize>; 2],
+    genes_from_first_parent: usize,
+    mutated_genes: usize,
+}
+
+impl Genealogy {
+    pub(crate) fn with_founders(founders: impl IntoIterator<Item = AnimalId>) -> Self {
+        let mut genealogy = Self::default();
+        for id in founders {
+            genealogy.next_id = genealogy.next_id.max(id.0 + 1);
+            genealogy.records.insert(id, LineageRecord {
+                id,
+                generation: 0,
+                parents: None,
+                genes_from_first_parent: 0,
+                mutated_genes: 0,
+                score: None,
+            });
+        }
+        genealogy
+    }
+
+    pub(crate) fn next_id(&mut self) -> AnimalId {
+        let id = AnimalId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    pub(crate) fn record_score(&mut self, id: AnimalId, score: i32) {
+        if let Some(record) = self.records.get_mut(&id) {
+            record.score = Some(score);
+        }
+    }
+
+    /// Records the birth of `children` from `parents`, using the evolution
+    /// events emitted while breeding them. Each child comes with the index
+    /// the events know it by.
+    pub(crate) fn record_generation(
+        &mut self,
+        generation: usize,
+        parents: &[AnimalId],
+        children: &[(usize, AnimalId)],
+        events: impl IntoIterator<Item = SimulationEvent>,
+    ) {
+        let mut births: BTreeMap<usize, Birth> = children.iter().map(|&(child, _)| (child, Birth::default())).collect();
+        for event in events {
+            match event {
+                SimulationEvent::ParentSelected { child, parent, index, .. } => {
+                    let slot = if parent == Parent::First { 0 } else { 1 };
+                    if let Some(birth) = births.get_mut(&child) {
+                        birth.parents[slot] = Some(index);
+                    }
+                }
+                SimulationEvent::Crossover { child, genes_from_first_parent } => {
+                    if let Some(birth) = births.get_mut(&child) {
+                        birth.genes_from_first_parent = genes_from_first_parent;
+                    }
+                }
+                SimulationEvent::Mutation { child, mutated_genes } => {
+                    if let Some(birth) = births.get_mut(&child) {
+                        birth.mutated_genes = mutated_genes;
+                    }
+                }
+                _ => {}
+            }
+        }
+        for &(child, id) in children {
+            let birth = births.remove(&child).unwrap_or_default();
+            let parents = match birth.parents {
+                [Some(a), Some(b)] => parents.get(a).zip(parents.get(b)).map(|(&a, &b)| [a, b]),
+                _ => None,
+            };
+            self.record_birth(id, generation, parents, birth.genes_from_first_parent, birth.mutated_genes);
+        }
+    }
+
+    /// Records the birth of a single bird, e.g. one born in the world rather
+    /// than bred by the optimizer; a bird with a single parent has it as both
+    pub(crate) fn record_birth(
+        &mut self,
+        id: AnimalId,
+        generation: usize,
+        parents: Option<[AnimalId; 2]>,
+        genes_from_first_parent: usize,
+        mutated_genes: usize,
+    ) {
+        self.records.insert(id, LineageRecord { id, generation, parents, genes_from_first_parent, mutated_genes, score: None });
+    }
+
+    pub fn get(&self, id: AnimalId) -> Option<&LineageRecord> {
+        self.records.get(&id)
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &LineageRecord> {
+        self.records.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Every known ancestor of `id`, not including `id` itself
+    pub fn ancestors(&self, id: AnimalId) -> BTreeSet<AnimalId> {
+        let mut ancestors = BTreeSet::new();
+        let mut to_visit = vec![id];
+        while let Some(current) = to_visit.pop() {
+            let parents = self.records.get(&current).and_then(|r| r.parents);
+            for parent in parents.into_iter().flatten() {
+                if ancestors.insert(parent) {
+                    to_visit.push(parent);
+                }
+            }
+        }
+        ancestors
+    }
+
+    /// The family tree of `id` alone: the bird and all of its ancestors
+    pub fn ancestry(&self, id: AnimalId) -> Genealogy {
+        let mut members = self.ancestors(id);
+        members.insert(id);
+        Genealogy {
+            records: members
+                .into_iter()
+                .filter_map(|m| self.records.get(&m).map(|r| (m, r.clone())))
+                .collect(),
+            next_id: self.next_id,
+        }
+    }
+
+    /// Graphviz representation of the tree, with an edge from each parent
+    /// to its children
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph lineage {\n    rankdir=TB;\n");
+        for record in self.records.values() {
+            let score = record.score.map_or("-".to_string(), |s| s.to_string());
+            let _ = writeln!(
+                dot,
+                "    {} [label=\"{}\\ngen {}\\nscore {}\"];",
+                record.id.0, record.id, record.generation, score
+            );
+        }
+        for record in self.records.values() {
+            for parent in record.parents.into_iter().flatten().collect::<BTreeSet<_>>() {
+                if self.records.contains_key(&parent) {
+                    let _ = writeln!(dot, "    {} -> {};", parent.0, record.id.0);
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// JSON array with one `LineageRecord` per bird
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&self.records.values().collect::<Vec<_>>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn genealogy() -> Genealogy {
+        let mut genealogy = Genealogy::with_founders((0..3).map(AnimalId));
+        let founders = [AnimalId(0), AnimalId(1), AnimalId(2)];
+        let children = [(0, genealogy.next_id()), (1, genealogy.next_id())];
+        genealogy.record_generation(1, &founders, &children, [
+            SimulationEvent::ParentSelected { child: 0, parent: Parent::First, index: 0, score: 1.0 },
+            SimulationEvent::ParentSelected { child: 0, parent: Parent::Second, index: 1, score: 2.0 },
+            SimulationEvent::Crossover { child: 0, genes_from_first_parent: 4 },
+            SimulationEvent::Mutation { child: 0, mutated_genes: 2 },
+            SimulationEvent::ParentSelected { child: 1, parent: Parent::First, index: 2, score: 3.0 },
+            SimulationEvent::ParentSelected { child: 1, parent: Parent::Second, index: 2, score: 3.0 },
+        ]);
+        let grandchild = [(0, genealogy.next_id())];
+        genealogy.record_generation(2, &children.map(|(_, id)| id), &grandchild, [
+            SimulationEvent::ParentSelected { child: 0, parent: Parent::First, index: 0, score: 5.0 },
+            SimulationEvent::ParentSelected { child: 0, parent: Parent::Second, index: 0, score: 5.0 },
+        ]);
+        genealogy.record_score(AnimalId(3), 5);
+        genealogy
+    }
+
+    #[test]
+    fn records_births() {
+        let genealogy = genealogy();
+        assert_eq!(genealogy.len(), 6);
+        let child = genealogy.get(AnimalId(3)).unwrap();
+        assert_eq!(child.generation, 1);
+        assert_eq!(child.parents, Some([AnimalId(0), AnimalId(1)]));
+        assert_eq!(child.genes_from_first_parent, 4);
+        assert_eq!(child.mutated_genes, 2);
+        assert_eq!(child.score, Some(5));
+        assert_eq!(genealogy.get(AnimalId(0)).unwrap().parents, None);
+    }
+
+    #[test]
+    fn finds_ancestors() {
+        let genealogy = genealogy();
+        assert_eq!(genealogy.ancestors(AnimalId(5)), BTreeSet::from([AnimalId(0), AnimalId(1), AnimalId(3)]));
+        assert_eq!(genealogy.ancestors(AnimalId(4)), BTreeSet::from([AnimalId(2)]));
+        assert!(genealogy.ancestors(AnimalId(0)).is_empty());
+    }
+
+    #[test]
+    fn simulation_tracks_lineage() {
+        use crate::Simulation;
+
+        let mut simulation = Simulation::random(42, 5, 10, 0.1, 0.5, 10);
+        while simulation.step().unwrap().is_none() {}
+
+        let genealogy = simulation.genealogy();
+        assert_eq!(genealogy.len(), 10);
+        for animal in simulation.world().animals() {
+            let record = genealogy.get(animal.id()).unwrap();
+            assert_eq!(record.generation, 1);
+            assert!(record.id.0 >= 5);
+            let [a, b] = record.parents.expect("Children should have parents");
+            assert!(a.0 < 5 && b.0 < 5);
+            assert!(genealogy.get(a).unwrap().score.is_some());
+        }
+    }
+
+    #[test]
+    fn exports_dot() {
+        let dot = genealogy().ancestry(AnimalId(4)).to_dot();
+        assert_eq!(dot, "digraph lineage {\n    rankdir=TB;\n    2 [label=\"#2\\ngen 0\\nscore -\"];\n    4 [label=\"#4\\ngen 1\\nscore -\"];\n    2 -> 4;\n}\n");
+    }
+
+    #[test]
+    fn exports_json() {
+        let json = genealogy().ancestry(AnimalId(4)).to_json().unwrap();
+        let records: Vec<LineageRecord> = serde_json::from_str(&json).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].parents, Some([AnimalId(2), AnimalId(2)]));
+    }
+}
@@ -1,167 +1,1046 @@
 mod world;
 mod food;
 mod animal;
+mod builder;
+mod config;
 mod eye;
+mod events;
+mod experiment;
+mod export;
+mod genealogy;
+mod obstacle;
+mod runner;
+mod sensor;
+mod snapshot;
+mod spatial;
+mod statistics;
+mod topology;
 
-pub use self::{animal::*, food::*, world::*};
+pub use self::{animal::*, builder::SimulationBuilder, config::*, events::SimulationEvent, experiment::*, export::*, food::*, genealogy::*, obstacle::Obstacle, sensor::{Clock, EnergyLevel, Proprioception, Rays, Sensor, Smell, Surroundings}, snapshot::*, spatial::SpatialGrid, statistics::*, topology::Topology, world::*};
+pub use genetic_algorithm::{
+    BoxedCrossover, BoxedMutation, BoxedSelection, CmaEs, Crossover, DifferentialEvolution, DifferentialStrategy,
+    FitnessSelection, GaussianMutation, GeneticError, Mutation, Optimizer, Parent, RankSelection, Replacement,
+    RouletteWheelSelection, RunController, RunSummary, Selection, SinglePointCrossover, StopCondition, StopReason,
+    TournamentSelection, UniformCrossover,
+};
+use events::EventBus;
+use genetic_algorithm::GeneticAlgorithm;
+use statistics::Activity;
 use nalgebra::geometry::Point2;
-use genetic_algorithm::{GeneticAlgorithm, Chromosome, Individual, RouletteWheelSelection, UniformCrossover, GaussianMutation};
-use nalgebra::{Rotation2, wrap, distance};
-use rand::{rngs, Rng, RngCore};
-use std::f32::consts::{FRAC_PI_4, PI};
-
-/// Minimum speed of a bird.
-///
-/// Keeping it above zero prevents birds from getting stuck in one place.
-const SPEED_MIN: f32 = 0.001;
-
-/// Maximum speed of a bird.
-///
-/// Keeping it "sane" prevents birds from accelerating up to infinity,
-/// which makes the simulation... unrealistic :-)
-const SPEED_MAX: f32 = 0.005;
-
-/// Speed acceleration; determines how much the brain can affect bird's
-/// speed during one step.
-///
-/// Assuming our bird is currently flying with speed=0.5, when the brain
-/// yells "stop flying!", a SPEED_ACCEL of:
-///
-/// - 0.1 = makes it take 5 steps ("5 seconds") for the bird to actually
-///         slow down to SPEED_MIN,
-///
-/// - 0.5 = makes it take 1 step for the bird to slow down to SPEED_MIN.
-///
-/// This improves simulation faithfulness, because - as in real life -
-/// it's not possible to increase speed from 1km/h to 50km/h in one
-/// instant, even if your brain very much wants to.
-const SPEED_ACCEL: f32 = 0.2;
-
-/// Ditto, but for rotation:
-///
-/// - 2 * PI = it takes one step for the bird to do a 360° rotation,
-/// - PI = it takes two steps for the bird to do a 360° rotation,
-///
-/// I've chosen PI/2, because - as our motto goes - this value seems
-/// to play nice.
-const ROTATION_ACCEL: f32 = FRAC_PI_4;
+use nalgebra::{Rotation2, Vector2, distance};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::f32::consts::PI;
+use std::sync::{mpsc::Receiver, Arc};
 
+// How far from an obstacle a bird flying into it stops
+const OBSTACLE_CLEARANCE: f32 = 1e-4;
+
+// How birds are replaced as the world runs under steady-state replacement
+struct SteadyState {
+    // Birds replaced at a time
+    count: usize,
+    // Steps between two replacements, so that about the whole flock is
+    // replaced every generation
+    interval: u32,
+    // Age from which birds can be replaced
+    maturity: u32,
+}
 
 pub struct Simulation  {
     world: World,
-    evolution_algorithm: GeneticAlgorithm<RouletteWheelSelection, UniformCrossover, GaussianMutation>,
+    seed: u64,
+    // Every random draw of the simulation comes from here, so that a seed
+    // and a config are enough to replay a run
+    rng: ChaCha8Rng,
+    config: SimulationConfig,
+    evolution_algorithm: Box<dyn Optimizer<AnimalIndividual> + Send>,
+    // Predators evolve apart from the prey, a whole generation at a time
+    predator_evolution: Box<dyn Optimizer<AnimalIndividual> + Send>,
+    replacement: Replacement,
+    generation: usize,
     age: u32,
-    generation_length: u32
+    events: Arc<EventBus>,
+    genealogy: Genealogy,
+    // Our own subscription to `events`, used to keep `genealogy` up to date
+    lineage: Receiver<SimulationEvent>,
+    activity: Activity,
+    history: StatisticsHistory,
+    predator_history: StatisticsHistory,
+    last_generation: Vec<IndividualSummary>,
+    champion: Option<Champion>
 }
 
 
 impl Simulation {
-    pub fn random(rng: &mut dyn RngCore, num_animals: usize, num_food: usize, mut_chance: f32, mut_coeff: f32, generation_length: u32) -> Self {
-        Self {
-            world: World::random(rng, num_animals, num_food),
-            evolution_algorithm: GeneticAlgorithm::new(
-                RouletteWheelSelection {},
-                UniformCrossover{},
-                GaussianMutation::new(mut_chance,mut_coeff).expect("Cannot instatiate mutation algorithm")
-            ),
-            age: 0,
-            generation_length
+    pub fn random(seed: u64, num_animals: usize, num_food: usize, mut_chance: f32, mut_coeff: f32, generation_length: u32) -> Self {
+        let mut config = SimulationConfig::default();
+        config.world.animals = num_animals;
+        config.world.food = num_food;
+        config.evolution = EvolutionConfig { generation_length, mutation_chance: mut_chance, mutation_coeff: mut_coeff };
+        Self::from_config(seed, config).expect("Cannot instatiate simulation")
+    }
+
+    /// A simulation evolved by the default genetic algorithm, set up as
+    /// `config` says
+    pub fn from_config(seed: u64, config: SimulationConfig) -> Result<Self, ConfigError> {
+        SimulationBuilder::from_config(config)?.seed(seed).build()
+    }
+
+    /// Like `from_config`, but the birds are evolved by `optimizer` instead of
+    /// the default genetic algorithm, e.g. a `DifferentialEvolution` or a `CmaEs`
+    pub fn with_optimizer(
+        seed: u64,
+        config: SimulationConfig,
+        optimizer: impl Optimizer<AnimalIndividual> + Send + 'static,
+    ) -> Result<Self, ConfigError> {
+        config.validate_for(&optimizer)?;
+        let events = Arc::new(EventBus::default());
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut world = World::from_config(&mut rng, &config);
+        let mut genealogy = Genealogy::with_founders(world.animals.iter().map(|a| a.id));
+        // Predators have no family tree, but their ids are not given again
+        for predator in &mut world.predators {
+            predator.id = genealogy.next_id();
         }
+        let mut evolution_algorithm: Box<dyn Optimizer<AnimalIndividual> + Send> = Box::new(optimizer);
+        evolution_algorithm.add_observer(Box::new(events.clone()));
+        Ok(Self {
+            genealogy,
+            lineage: events.subscribe(),
+            world,
+            seed,
+            rng,
+            predator_evolution: Self::predator_evolution(&config),
+            config,
+            evolution_algorithm,
+            replacement: Replacement::default(),
+            generation: 0,
+            age: 0,
+            events,
+            activity: Activity::default(),
+            history: StatisticsHistory::default(),
+            predator_history: StatisticsHistory::default(),
+            last_generation: Vec::new(),
+            champion: None
+        })
+    }
+
+    // The default genetic algorithm, set up as `config` says
+    pub(crate) fn predator_evolution(config: &SimulationConfig) -> Box<dyn Optimizer<AnimalIndividual> + Send> {
+        let EvolutionConfig { mutation_chance, mutation_coeff, .. } = config.evolution;
+        let mutation = GaussianMutation::new(mutation_chance, mutation_coeff).expect("Mutation chance was validated with the config");
+        Box::new(GeneticAlgorithm::new(RouletteWheelSelection {}, UniformCrossover, mutation))
+    }
+
+    /// Seed of the RNG this simulation was created with; the same seed and
+    /// config replay the very same run
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn config(&self) -> &SimulationConfig {
+        &self.config
+    }
+
+    /// Changes how birds make room for their children:
+    ///
+    /// - `Generational` replaces the whole flock at the end of a generation,
+    /// - `GenerationalGap` only replaces its least fit part, leaving the
+    ///   others flying where they are,
+    /// - `SteadyState(n)` replaces the `n` least fit birds as the world runs,
+    ///   often enough that about the whole flock is replaced every
+    ///   `generation_length` steps; birds are then compared on how fast they
+    ///   score, since they do not all have the same age, and only those that
+    ///   flew for at least half a generation can be replaced.
+    pub fn with_replacement(mut self, replacement: Replacement) -> Self {
+        self.replacement = replacement;
+        self
+    }
+
+    pub fn replacement(&self) -> Replacement {
+        self.replacement
     }
 
     pub fn world(&self) -> &World {
         &self.world
     }
 
+    /// Number of generations that ended so far
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Statistics of every generation that ended so far
+    pub fn history(&self) -> &StatisticsHistory {
+        &self.history
+    }
+
+    /// Statistics of every generation of predators that ended so far, empty
+    /// without predators
+    pub fn predator_history(&self) -> &StatisticsHistory {
+        &self.predator_history
+    }
+
+    /// Fitness and genes of the birds of the generation that ended last
+    pub fn last_generation(&self) -> &[IndividualSummary] {
+        &self.last_generation
+    }
+
+    /// Fittest bird of all the generations that ended so far
+    pub fn champion(&self) -> Option<&Champion> {
+        self.champion.as_ref()
+    }
+
+    /// Configuration of this simulation, to be written at the top of an export
+    pub fn run_header(&self) -> RunHeader {
+        RunHeader {
+            seed: self.seed,
+            optimizer: self.evolution_algorithm.name().to_string(),
+            config: self.config.clone(),
+        }
+    }
+
+    /// Family tree of every bird that lived in this simulation
+    pub fn genealogy(&self) -> &Genealogy {
+        &self.genealogy
+    }
+
+    /// Receives every `SimulationEvent` from now on, until the receiver is dropped
+    pub fn subscribe(&self) -> Receiver<SimulationEvent> {
+        self.events.subscribe()
+    }
+
     fn process_movements(&mut self) {
-        for animal in &mut self.world.animals {
-            animal.position.x = wrap(animal.position.x + animal.speed * animal.rotation.angle().cos(), 0.0, 1.0);
-            animal.position.y = wrap(animal.position.y + animal.speed * animal.rotation.angle().sin(), 0.0, 1.0)
+        let topology = self.config.world.topology;
+        let World { animals, predators, obstacles, .. } = &mut self.world;
+        for animal in animals.iter_mut().filter(|a| !a.exhausted) {
+            Self::fly(animal, obstacles, topology);
+            self.activity.speed_sum += animal.speed;
+            self.activity.speed_samples += 1;
+        }
+        for predator in predators.iter_mut().filter(|p| !p.exhausted) {
+            Self::fly(predator, obstacles, topology);
         }
     }
 
-    fn process_collisions(&mut self, rng: &mut dyn RngCore) {
-        let animal_pos: Vec<Point2<f32>> = self.world.animals.iter().map(|a| a.position).collect();
-        for animal in &mut self.world.animals {
-            for food in &mut self.world.food {
-                if distance(&animal.position, &food.position) < 0.01 {
-                    food.position = rng.gen();
-                    animal.score += 1;
+    fn fly(animal: &mut Animal, obstacles: &[Obstacle], topology: Topology) {
+        let angle = animal.rotation.angle();
+        let heading = Vector2::new(angle.cos(), angle.sin());
+        match obstacle::cast(obstacles, topology, animal.position, heading, animal.speed) {
+            // Stop just short of the obstacle and bounce off it
+            Some(hit) => {
+                (animal.position, _) = topology.advance(animal.position, animal.rotation, (hit.distance - OBSTACLE_CLEARANCE).max(0.0));
+                let bounced = heading - 2.0 * heading.dot(&hit.normal) * hit.normal;
+                animal.rotation = Rotation2::new(bounced.y.atan2(bounced.x));
+            }
+            None => (animal.position, animal.rotation) = topology.advance(animal.position, animal.rotation, animal.speed),
+        }
+        animal.age += 1;
+    }
+
+    // Steps since the simulation started
+    fn clock(&self) -> u64 {
+        self.generation as u64 * (self.config.evolution.generation_length as u64 + 1) + self.age as u64
+    }
+
+    // Whether food can grow back once more in this generation
+    fn may_regrow(config: &SimulationConfig, activity: &Activity) -> bool {
+        config.food.regrowth_limit.is_none_or(|limit| activity.food_regrown < limit)
+    }
+
+    // Patches drift, food rots, grows back and comes and goes with the
+    // seasons, as `config.food` says
+    fn process_food(&mut self) {
+        let FoodConfig { regrowth_delay, lifetime, season_length, season_amplitude, .. } = self.config.food;
+        let topology = self.config.world.topology;
+        if let FoodPlacement::Patches { drift, .. } = self.config.food.placement {
+            if drift > 0.0 {
+                for patch in &mut self.world.food_patches {
+                    *patch = topology.advance(*patch, self.rng.gen(), drift).0;
                 }
+            }
+        }
 
+        // The first foods are the last to go out of season
+        let in_season = (self.world.food.len() as f32 * food::season(self.clock(), season_length, season_amplitude)).round() as usize;
+        for (index, food) in self.world.food.iter_mut().enumerate() {
+            food.in_season = index < in_season;
+            if food.is_available() {
+                food.age += 1;
+                if lifetime > 0 && food.age >= lifetime {
+                    food.remove(regrowth_delay);
+                    self.activity.food_rotten += 1;
+                }
             }
-            for other_animal in animal_pos.iter().filter(|o| !animal.position.eq(o)) {
-                if distance(&animal.position, other_animal) < 0.01 {
+            if food.eaten {
+                if food.regrows_in > 0 {
+                    food.regrows_in -= 1;
+                } else if Self::may_regrow(&self.config, &self.activity) {
+                    food.grow(food::grow_position(&self.config, &self.world.food_patches, &mut self.rng));
+                    self.activity.food_regrown += 1;
+                }
+            }
+            self.activity.food_available_sum += food.is_available() as u64;
+        }
+        self.activity.steps += 1;
+    }
+
+    // Whether `animal` can still be seen, bumped into and caught
+    fn in_world(&self, animal: &Animal) -> bool {
+        !animal.exhausted || self.config.energy.exhaustion == Exhaustion::Stop
+    }
+
+    fn process_collisions(&mut self) {
+        let animal_pos: Vec<Point2<f32>> = self.world.animals.iter().map(|a| a.position).collect();
+        let food_pos: Vec<Point2<f32>> = self.world.food.iter().map(|f| f.position).collect();
+        let in_world: Vec<bool> = self.world.animals.iter().map(|a| self.in_world(a)).collect();
+        let WorldConfig { eat_radius, collision_radius, topology, .. } = self.config.world;
+        let energy = &self.config.energy;
+        let animal_grid = SpatialGrid::new(topology, &animal_pos);
+        let food_grid = SpatialGrid::new(topology, &food_pos);
+        let regrowth_delay = self.config.food.regrowth_delay;
+        let kinds = self.config.food.kinds();
+        let radii: Vec<f32> = kinds.iter().map(|k| k.radius.unwrap_or(eat_radius)).collect();
+        let reach = radii.iter().copied().fold(0.0, f32::max);
+        for (index, animal) in self.world.animals.iter_mut().enumerate().filter(|(_, a)| !a.exhausted) {
+            for (f, _) in food_grid.within(animal.position, reach) {
+                // Food eaten earlier in this step is gone or has moved elsewhere
+                let food = &mut self.world.food[f];
+                if food.is_available() && topology.distance(animal.position, food.position) < radii[food.kind] {
+                    let value = kinds[food.kind].value;
+                    food.remove(regrowth_delay);
+                    // Food growing back at once does so before the next bird
+                    // looks for it
+                    if regrowth_delay == 0 && Self::may_regrow(&self.config, &self.activity) {
+                        food.grow(food::grow_position(&self.config, &self.world.food_patches, &mut self.rng));
+                        self.activity.food_regrown += 1;
+                    }
+                    animal.score += value;
+                    animal.refuel(energy, value);
+                    self.activity.food_eaten += 1;
+                }
+            }
+            for (other, position) in animal_grid.within(animal.position, collision_radius) {
+                if other != index && in_world[other] && distance(&animal.position, &position) < collision_radius {
                     animal.rotation = Rotation2::new(animal.rotation.angle() + PI);
+                    self.activity.collisions += 1;
+                }
+            }
+        }
+
+        let PredatorConfig { catch_radius, caught_penalty, .. } = self.config.predators;
+        for predator in self.world.predators.iter_mut().filter(|p| !p.exhausted) {
+            for (p, _) in animal_grid.within(predator.position, catch_radius) {
+                // Prey caught earlier in this step has been put elsewhere
+                let prey = &mut self.world.animals[p];
+                if in_world[p] && topology.distance(predator.position, prey.position) < catch_radius {
+                    prey.position = obstacle::free_position(&self.config.world.obstacles, topology, &mut self.rng);
+                    prey.score -= caught_penalty;
+                    predator.score += 1;
+                    predator.refuel(energy, 1);
                 }
             }
         }
     }
 
     fn process_brains(&mut self) {
-        let foods: Vec<Point2<f32>> = self.world.food.iter().map(|f| f.position).collect();
-        let animals: Vec<Point2<f32>> = self.world.animals.iter().map(|a| a.position).collect();
-        for (i, animal) in self.world.animals.iter_mut().enumerate() {
-            let mut vision = animal.eye.process_vision(animal.position, animal.rotation, &foods);
-            vision.extend(animal.eye.process_vision(animal.position, animal.rotation, &animals));
+        let topology = self.config.world.topology;
+        let grid = |animals: &[Animal]| {
+            SpatialGrid::new(topology, &animals.iter().filter(|a| self.in_world(a)).map(|a| a.position).collect::<Vec<_>>())
+        };
+        let food_grids: Vec<SpatialGrid> = (0..self.config.food.kinds().len())
+            .map(|kind| {
+                let food = self.world.food.iter().filter(|f| f.kind == kind && f.is_available());
+                SpatialGrid::new(topology, &food.map(|f| f.position).collect::<Vec<_>>())
+            })
+            .collect();
+        let prey_grid = grid(&self.world.animals);
+        let predator_grid = grid(&self.world.predators);
+        let surroundings = Surroundings {
+            food: &food_grids,
+            prey: &prey_grid,
+            predators: &predator_grid,
+            obstacles: &self.world.obstacles,
+            config: &self.config,
+            clock: self.clock(),
+        };
+        let prey_sensors = sensor::for_species(&self.config, Species::Prey);
+        let predator_sensors = sensor::for_species(&self.config, Species::Predator);
+        let AnimalConfig { speed_min, speed_max, speed_accel, rotation_accel } = self.config.animal;
+        let energy = &self.config.energy;
+        // Tells whether the bird ran out of energy thinking
+        let think = |animal: &mut Animal| {
+            if animal.exhausted {
+                return false;
+            }
+            let sensors = match animal.species {
+                Species::Prey => &prey_sensors,
+                Species::Predator => &predator_sensors,
+            };
+            let mut vision = Vec::with_capacity(animal.eye.inputs() + sensors.iter().map(|s| s.inputs()).sum::<usize>());
+            animal.eye.sense(animal, &surroundings, &mut vision);
+            for sensor in sensors {
+                sensor.sense(animal, &surroundings, &mut vision);
+            }
             let brain_response = animal.brain.propagate(vision.as_slice());
-            let speed = brain_response[0].clamp(-SPEED_ACCEL, SPEED_ACCEL);
-            let rotation = brain_response[1].clamp(-ROTATION_ACCEL, ROTATION_ACCEL);
-            animal.speed = (animal.speed + speed).clamp(SPEED_MIN, SPEED_MAX);
+            let speed = brain_response[0].clamp(-speed_accel, speed_accel);
+            let rotation = brain_response[1].clamp(-rotation_accel, rotation_accel);
+            animal.speed = (animal.speed + speed).clamp(speed_min, speed_max);
             animal.rotation = Rotation2::new(animal.rotation.angle() + rotation);
-        }
+            energy.enabled && animal.spend_energy(energy, rotation)
+        };
+        #[cfg(feature = "parallel")]
+        let starved = self.world.animals.par_iter_mut().chain(self.world.predators.par_iter_mut()).map(think).filter(|&s| s).count();
+        #[cfg(not(feature = "parallel"))]
+        let starved = self.world.animals.iter_mut().chain(self.world.predators.iter_mut()).map(think).filter(|&s| s).count();
+        self.activity.starved += starved as u32;
     }
 
 
-    pub fn step(&mut self, rng: &mut dyn RngCore) -> Option<Statistics> {
+    fn process_step(&mut self) -> Result<(), GeneticError> {
         self.age += 1;
+        self.process_food();
         self.process_movements();
-        self.process_collisions(rng);
+        self.process_collisions();
         self.process_brains();
+        if self.config.reproduction.enabled {
+            self.process_life_cycle()?;
+        } else if let Some(SteadyState { count, interval, maturity }) = self.steady_state() {
+            if self.age.is_multiple_of(interval) {
+                // Younger birds have not had the time to show what they are
+                // worth yet
+                let population = self.population();
+                let mut mature: Vec<usize> = (0..population.len()).filter(|&i| self.world.animals[i].age >= maturity).collect();
+                mature.sort_by(|&a, &b| population[a].fitness.total_cmp(&population[b].fitness));
+                mature.truncate(count);
+                if !mature.is_empty() {
+                    self.replace(population, &mature)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn steady_state(&self) -> Option<SteadyState> {
+        if !matches!(self.replacement, Replacement::SteadyState(_)) {
+            return None;
+        }
+        let generation_length = self.config.evolution.generation_length;
+        let count = self.replacement.replaced(self.world.animals.len());
+        let interval = generation_length as usize * count / self.world.animals.len().max(1);
+        Some(SteadyState { count, interval: interval.max(1) as u32, maturity: (generation_length / 2).max(1) })
+    }
+
+    // Birds out of energy or too old die, those with enough of it give
+    // birth, and random birds fly in when a species is almost gone
+    fn process_life_cycle(&mut self) -> Result<(), GeneticError> {
+        let max_age = self.config.reproduction.max_age;
+        let mut dead = Vec::new();
+        self.world.animals.retain(|a| {
+            let dies = a.exhausted || a.age >= max_age;
+            if dies {
+                dead.push((a.id, a.score));
+            }
+            !dies
+        });
+        for (id, score) in dead {
+            self.genealogy.record_score(id, score);
+        }
+        self.world.predators.retain(|p| !p.exhausted && p.age < max_age);
+
+        self.give_birth(Species::Prey)?;
+        self.give_birth(Species::Predator)
+    }
+
+    fn give_birth(&mut self, species: Species) -> Result<(), GeneticError> {
+        let Self { world, rng, config, genealogy, activity, generation, evolution_algorithm, predator_evolution, .. } = self;
+        let (animals, founders, optimizer) = match species {
+            Species::Prey => (&mut world.animals, config.world.animals, evolution_algorithm),
+            Species::Predator => (&mut world.predators, config.predators.count, predator_evolution),
+        };
+        let ReproductionConfig { energy_threshold, offspring_energy, mate_radius, max_population, min_population, .. } = config.reproduction;
+
+        let ready: Vec<usize> = (0..animals.len()).filter(|&i| animals[i].energy >= energy_threshold).collect();
+        let mut busy = vec![false; animals.len()];
+        let mut newborns = Vec::new();
+        for &i in &ready {
+            if animals.len() + newborns.len() >= max_population {
+                break;
+            }
+            if busy[i] {
+                continue;
+            }
+            busy[i] = true;
+            let position = animals[i].position;
+            let mate = ready.iter().copied()
+                .find(|&j| !busy[j] && config.world.topology.distance(position, animals[j].position) < mate_radius);
+            // A bird without a mate around breeds with itself
+            let first = AnimalIndividual::from(&animals[i]);
+            let (second, parents) = match mate {
+                Some(j) => {
+                    busy[j] = true;
+                    animals[i].energy -= offspring_energy / 2.0;
+                    animals[j].energy -= offspring_energy / 2.0;
+                    (Some(AnimalIndividual::from(&animals[j])), [animals[i].id, animals[j].id])
+                }
+                None => {
+                    animals[i].energy -= offspring_energy;
+                    (None, [animals[i].id; 2])
+                }
+            };
+            let second = second.as_ref().unwrap_or(&first);
+            let mut individual = optimizer.reproduce(&first, second, rng)?;
+            let genes = || individual.chromosome.iter().zip(first.chromosome.iter().zip(second.chromosome.iter()));
+            let genes_from_first_parent = genes().filter(|(gene, (a, _))| gene == a).count();
+            let mutated_genes = genes().filter(|(gene, (a, b))| gene != a && gene != b).count();
+
+            individual.id = genealogy.next_id();
+            let mut child = Animal::from_individual(&individual, config, species);
+            child.position = position;
+            child.rotation = rng.gen();
+            child.energy = offspring_energy;
+            // Predators have no family tree, as when they evolve by generations
+            if species == Species::Prey {
+                genealogy.record_birth(child.id, *generation, Some(parents), genes_from_first_parent, mutated_genes);
+                activity.births += 1;
+            }
+            newborns.push(child);
+        }
+        animals.extend(newborns);
+
+        while founders > 0 && animals.len() < min_population {
+            let mut animal = Animal::random_with_config(rng, config, species);
+            animal.id = genealogy.next_id();
+            if species == Species::Prey {
+                genealogy.record_birth(animal.id, *generation, None, 0, 0);
+            }
+            animals.push(animal);
+        }
+        Ok(())
+    }
+
+    fn generation_over(&self) -> bool {
+        self.age > self.config.evolution.generation_length
+    }
+
+    fn population(&self) -> Vec<AnimalIndividual> {
+        self.individuals(&self.world.animals)
+    }
+
+    fn individuals(&self, animals: &[Animal]) -> Vec<AnimalIndividual> {
+        animals.iter().map(|animal| self.individual(animal)).collect()
+    }
+
+    // Scores `animal` on the food it ate or the prey it caught, and on how
+    // long it stayed alive when birds run on energy
+    fn individual(&self, animal: &Animal) -> AnimalIndividual {
+        let mut individual = AnimalIndividual::from(animal);
+        let generation_length = self.config.evolution.generation_length as f32;
+        let steady_state = self.steady_state().filter(|_| animal.species == Species::Prey);
+        if let Some(SteadyState { maturity, .. }) = steady_state {
+            // Score a generation's worth of steps at the bird's pace, which
+            // a single lucky meal right after birth says little about
+            individual.fitness *= generation_length / animal.age.max(maturity) as f32;
+        }
+        let energy = &self.config.energy;
+        if energy.enabled {
+            // Birds of every age are compared as they are now, alive or not,
+            // under steady-state replacement
+            let survived = if steady_state.is_some() {
+                if animal.exhausted { 0.0 } else { 1.0 }
+            } else {
+                (animal.age as f32 / generation_length).min(1.0)
+            };
+            individual.fitness += energy.survival_reward * survived;
+        }
+        individual
+    }
+
+    // Scores the generation that just ended and lets subscribers know about it
+    fn close_generation(&mut self) -> (Vec<AnimalIndividual>, Statistics) {
+        let population = self.population();
+        let statistics = Statistics::from_population(&population, &mut self.rng).with_activity(&self.activity);
+        self.activity = Activity::default();
+        self.history.push(statistics);
+        self.last_generation = population.iter().map(IndividualSummary::from).collect();
+        if let Some(best) = population.iter().max_by(|a, b| a.fitness.total_cmp(&b.fitness)) {
+            if self.champion.as_ref().is_none_or(|c| best.fitness > c.fitness) {
+                self.champion = Some(Champion::new(best, self.generation, &self.config));
+            }
+        }
+        // Birds born in the world get their score when they die
+        if !self.config.reproduction.enabled {
+            for animal in &self.world.animals {
+                self.genealogy.record_score(animal.id, animal.score);
+            }
+        }
+        if !self.world.predators.is_empty() {
+            let predators = self.individuals(&self.world.predators);
+            self.predator_history.push(Statistics::from_population(&predators, &mut self.rng));
+        }
+        self.events.publish(SimulationEvent::GenerationEnd { generation: self.generation, statistics });
+        self.generation += 1;
+        self.age = 0;
+        (population, statistics)
+    }
+
+    // Closes the generation and makes room for the next one, as `replacement` says
+    fn end_generation(&mut self) -> (Vec<AnimalIndividual>, Statistics, Result<(), GeneticError>) {
+        let (population, statistics) = self.close_generation();
+        if self.config.reproduction.enabled {
+            // Birds are born and die in the world, the end of a generation
+            // only takes a sample of their statistics
+            return (population, statistics, Ok(()));
+        }
+        let evolved = match self.replacement {
+            Replacement::Generational => self.evolve(),
+            Replacement::GenerationalGap(_) => {
+                let evolved = self.replace_worst(self.replacement.replaced(population.len()));
+                // Survivors start the new generation on equal terms with the newborns
+                for animal in &mut self.world.animals {
+                    animal.score = 0;
+                    if self.config.energy.enabled {
+                        animal.age = 0;
+                        animal.energy = self.config.energy.capacity;
+                        animal.exhausted = false;
+                    }
+                }
+                evolved
+            }
+            Replacement::SteadyState(_) => Ok(()),
+        };
+        let predators_evolved = self.evolve_predators();
+        (population, statistics, evolved.and(predators_evolved))
+    }
 
-        if self.age > self.generation_length {
-            let population: Vec<_> = self.world.animals.iter().map(|a| AnimalIndividual::from(a)).collect();
-            let statistics = Statistics::from_population(&population);
-            self.age = 0;
-            self.evolve(rng);
-            return Some(statistics);
+    /// Moves the world one step forward, ending the generation when it is
+    /// over, and returns the statistics of the generation that just ended.
+    ///
+    /// A generation whose birds cannot be evolved still ends, its flock
+    /// flying on as it was, but the error is returned instead; so are those
+    /// of birds replaced as the world runs, the step going no further.
+    pub fn step(&mut self) -> Result<Option<Statistics>, GeneticError> {
+        self.process_step()?;
+        if self.generation_over() {
+            let (_, statistics, evolved) = self.end_generation();
+            evolved?;
+            return Ok(Some(statistics));
         }
-        None
+        Ok(None)
     }
 
-    pub fn evolve(&mut self, rng: &mut dyn RngCore) {
-        let population: Vec<AnimalIndividual> = self.world.animals().iter().map(|a| a.into()).collect();
-        let new_population = self.evolution_algorithm.evolve(&population, rng).expect("Cannot evolve population");
-        self.world.animals = new_population.iter().map(|i| Animal::from(i)).collect();
+    pub fn evolve(&mut self) -> Result<(), GeneticError> {
+        // Leftovers of a previous evolution that failed halfway
+        self.lineage.try_iter().for_each(drop);
+        let parents: Vec<AnimalId> = self.world.animals.iter().map(|a| a.id).collect();
+        let new_population = self.evolution_algorithm.evolve(&selectable(self.population()), &mut self.rng)?;
+        self.world.animals = new_population.iter().map(|child| Animal::from_individual(child, &self.config, Species::Prey)).collect();
         for animal in &mut self.world.animals {
-            animal.position = rng.gen();
-            animal.rotation = rng.gen();
+            animal.id = self.genealogy.next_id();
+            animal.position = obstacle::free_position(&self.config.world.obstacles, self.config.world.topology, &mut self.rng);
+            animal.rotation = self.rng.gen();
         }
+        let children: Vec<(usize, AnimalId)> = self.world.animals.iter().map(|a| a.id).enumerate().collect();
+        self.genealogy.record_generation(self.generation, &parents, &children, self.lineage.try_iter());
+        Ok(())
     }
-}
 
+    /// Replaces every predator by a child of the predators that hunted in the
+    /// generation that just ended
+    fn evolve_predators(&mut self) -> Result<(), GeneticError> {
+        if self.world.predators.is_empty() {
+            return Ok(());
+        }
+        let population = selectable(self.individuals(&self.world.predators));
+        let children = self.predator_evolution.evolve(&population, &mut self.rng)?;
+        self.world.predators.clear();
+        for child in &children {
+            let mut predator = Animal::from_individual(child, &self.config, Species::Predator);
+            predator.id = self.genealogy.next_id();
+            predator.position = obstacle::free_position(&self.config.world.obstacles, self.config.world.topology, &mut self.rng);
+            predator.rotation = self.rng.gen();
+            self.world.predators.push(predator);
+        }
+        Ok(())
+    }
 
-#[derive(Debug,Clone,Copy)]
-pub struct Statistics {
-    pub min_score: u32,
-    pub avg_score: f32,
-    pub max_score: u32
-}
+    /// Replaces the `count` least fit birds by children of the whole flock,
+    /// leaving the other birds where they are
+    pub fn replace_worst(&mut self, count: usize) -> Result<(), GeneticError> {
+        let population = self.population();
+        let mut worst: Vec<usize> = (0..population.len()).collect();
+        worst.sort_by(|&a, &b| population[a].fitness.total_cmp(&population[b].fitness));
+        worst.truncate(count);
+        self.replace(population, &worst)
+    }
 
-impl Statistics {
-    pub fn from_population<I>(population: &[I]) -> Self
-where
-I: Individual {
-    let pop_scores: Vec<u32> = population.iter().map(|i| i.fitness() as u32).collect();
-    let min_score = pop_scores.iter().min().unwrap_or(&0);
-    let max_score = pop_scores.iter().max().unwrap_or(&0);
-    let tot_score: u32 = pop_scores.iter().sum();
-    Self {
-        min_score: *min_score,
-        max_score: *max_score,
-        avg_score: tot_score as f32 / pop_scores.len() as f32
+    // Replaces the birds at `indices` by children of the whole flock, scored
+    // as `population`
+    fn replace(&mut self, population: Vec<AnimalIndividual>, indices: &[usize]) -> Result<(), GeneticError> {
+        self.lineage.try_iter().for_each(drop);
+        let parents: Vec<AnimalId> = self.world.animals.iter().map(|a| a.id).collect();
+        let children = self.evolution_algorithm.replace_at(&selectable(population), indices, &mut self.rng)?;
+        let mut born = Vec::with_capacity(children.len());
+        for (index, child) in children {
+            let dead = &self.world.animals[index];
+            self.genealogy.record_score(dead.id, dead.score);
+            let mut animal = Animal::from_individual(&child, &self.config, Species::Prey);
+            animal.id = self.genealogy.next_id();
+            animal.position = obstacle::free_position(&self.config.world.obstacles, self.config.world.topology, &mut self.rng);
+            animal.rotation = self.rng.gen();
+            born.push((index, animal.id));
+            self.world.animals[index] = animal;
+        }
+        self.genealogy.record_generation(self.generation, &parents, &born, self.lineage.try_iter());
+        Ok(())
     }
+}
 
+// Fitness handed to an optimizer cannot go below zero, so the flock's scores
+// are shifted up until the worst is zero rather than clamped, not to tell a
+// poisoned bird from one that ate nothing. Statistics keep the raw scores.
+fn selectable(mut population: Vec<AnimalIndividual>) -> Vec<AnimalIndividual> {
+    let floor = population.iter().map(|i| i.fitness).fold(0.0, f32::min);
+    for individual in &mut population {
+        individual.fitness -= floor;
+    }
+    population
 }
 
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::eye::{Channel, Eye};
+    use std::collections::HashSet;
+
+    // Every number describing `world`, bit for bit
+    pub(crate) fn world_bits(world: &World) -> Vec<u32> {
+        let animals = world.animals().iter().chain(world.predators()).flat_map(|a| {
+            [a.position.x, a.position.y, a.rotation.angle(), a.speed]
+                .into_iter()
+                .chain(a.brain.weights())
+                .map(f32::to_bits)
+                .chain([a.score as u32, a.age])
+        });
+        let food = world.food().iter().flat_map(|f| [f.position.x.to_bits(), f.position.y.to_bits(), f.eaten as u32, f.regrows_in, f.age]);
+        animals.chain(food).collect()
+    }
+
+    #[test]
+    fn same_seed_replays_the_same_run() {
+        let config = SimulationConfig {
+            evolution: EvolutionConfig { generation_length: 50, ..Default::default() },
+            ..Default::default()
+        };
+        let mut a = Simulation::from_config(7, config.clone()).unwrap();
+        let mut b = Simulation::from_config(7, config.clone()).unwrap();
+        for _ in 0..175 {
+            assert_eq!(a.step().unwrap(), b.step().unwrap());
+        }
+        assert_eq!(a.generation(), 3);
+        assert_eq!(world_bits(a.world()), world_bits(b.world()));
+        assert_eq!(a.seed(), 7);
+
+        let other_seed = Simulation::from_config(8, config.clone()).unwrap();
+        let same_seed = Simulation::from_config(7, config).unwrap();
+        assert_ne!(world_bits(other_seed.world()), world_bits(same_seed.world()));
+    }
+
+    #[test]
+    fn birds_stay_inside_bounded_worlds() {
+        for topology in [Topology::Walls, Topology::Reflecting] {
+            let mut config = SimulationConfig::default();
+            config.world.topology = topology;
+            config.animal = AnimalConfig { speed_min: 0.01, speed_max: 0.02, ..Default::default() };
+            let mut simulation = Simulation::from_config(42, config).unwrap();
+            for _ in 0..500 {
+                simulation.step().unwrap();
+                let inside = simulation.world().animals().iter().all(|a| (0.0..=1.0).contains(&a.position.x) && (0.0..=1.0).contains(&a.position.y));
+                assert!(inside, "{topology:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn birds_fly_around_obstacles() {
+        let mut config = SimulationConfig::default();
+        config.world.obstacles = vec![
+            Obstacle::Circle { center: Point2::new(0.5, 0.5), radius: 0.2 },
+            Obstacle::Rectangle { center: Point2::new(0.0, 0.0), size: Vector2::new(0.3, 0.2), angle: 0.3 },
+            Obstacle::Polyline { points: vec![Point2::new(0.1, 0.9), Point2::new(0.9, 0.9)] },
+        ];
+        config.animal = AnimalConfig { speed_min: 0.01, speed_max: 0.02, ..Default::default() };
+        let mut simulation = Simulation::from_config(42, config).unwrap();
+        assert_eq!(simulation.world().animals()[0].brain.weights().len(), (13 * 3 + 1) * 10 + (10 + 1) * 5 + (5 + 1) * 10 + (10 + 1) * 2);
+        for _ in 0..300 {
+            let before: Vec<_> = simulation.world().animals().iter().map(|a| a.position.y).collect();
+            simulation.step().unwrap();
+            let world = simulation.world();
+            for (animal, before) in world.animals().iter().zip(before) {
+                let inside = obstacle::blocked(world.obstacles(), Topology::Torus, animal.position);
+                assert!(!inside, "bird {} flew into an obstacle at {}", animal.id, animal.position);
+                // Not counting birds wrapping around the bottom edge
+                let crossed = (before - 0.9).signum() != (animal.position.y - 0.9).signum()
+                    && (before - animal.position.y).abs() < 0.5
+                    && (0.1..0.9).contains(&animal.position.x);
+                assert!(!crossed, "bird {} flew through the wall at {}", animal.id, animal.position);
+            }
+        }
+    }
+
+    #[test]
+    fn predators_hunt_prey() {
+        let mut config = SimulationConfig::default();
+        config.world.animals = 20;
+        config.evolution.generation_length = 100;
+        config.predators = PredatorConfig { count: 5, catch_radius: 0.1, caught_penalty: 2, brain: BrainConfig { hidden_layers: vec![4] }, ..Default::default() };
+        let mut simulation = Simulation::from_config(42, config).unwrap();
+        let world = simulation.world();
+        assert_eq!(world.predators().len(), 5);
+        assert!(world.predators().iter().all(|p| p.species() == Species::Predator));
+        // Prey see food, prey and predators, predators see prey and predators
+        assert_eq!(world.animals()[0].brain.weights().len(), (13 * 3 + 1) * 10 + (10 + 1) * 5 + (5 + 1) * 10 + (10 + 1) * 2);
+        assert_eq!(world.predators()[0].brain.weights().len(), (13 * 2 + 1) * 4 + (4 + 1) * 2);
+        let ids: HashSet<AnimalId> = world.animals().iter().chain(world.predators()).map(|a| a.id).collect();
+        assert_eq!(ids.len(), 25);
+
+        for _ in 0..100 {
+            simulation.step().unwrap();
+        }
+        let world = simulation.world();
+        let catches: i32 = world.predators().iter().map(|p| p.score).sum();
+        assert!(catches > 0);
+        // Every catch cost its prey 2 points, on top of what it ate
+        let eaten: i32 = simulation.activity.food_eaten as i32;
+        assert_eq!(world.animals().iter().map(|a| a.score).sum::<i32>(), eaten - 2 * catches);
+
+        let hunters: Vec<AnimalId> = world.predators().iter().map(|p| p.id).collect();
+        let best_hunter = world.predators().iter().map(|p| p.score).max().unwrap();
+        assert!(simulation.step().unwrap().is_some());
+        assert_eq!(simulation.predator_history().len(), 1);
+        assert!(simulation.predator_history().get(0).unwrap().max_score >= best_hunter as f32);
+        assert!(simulation.world().predators().iter().all(|p| p.score == 0 && !hunters.contains(&p.id)));
+    }
+
+    #[test]
+    fn poisoned_birds_score_below_those_that_ate_nothing() {
+        let mut simulation = Simulation::random(42, 4, 10, 0.1, 0.5, 100);
+        for (animal, score) in simulation.world.animals.iter_mut().zip([-3, 0, 2, -1]) {
+            animal.score = score;
+        }
+        let fitness: Vec<f32> = selectable(simulation.population()).iter().map(|i| i.fitness).collect();
+        assert_eq!(fitness, [0.0, 3.0, 5.0, 2.0]);
+
+        // Nothing moves while nobody ate poison
+        for (animal, score) in simulation.world.animals.iter_mut().zip([1, 0, 2, 4]) {
+            animal.score = score;
+        }
+        let fitness: Vec<f32> = selectable(simulation.population()).iter().map(|i| i.fitness).collect();
+        assert_eq!(fitness, [1.0, 0.0, 2.0, 4.0]);
+    }
+
+    #[test]
+    fn poison_shows_in_statistics_and_champions() {
+        // Without food, birds keep the scores they are given
+        let mut simulation = Simulation::random(42, 4, 0, 0.1, 0.5, 10);
+        let end_generation = |simulation: &mut Simulation, scores: [i32; 4]| {
+            for (animal, score) in simulation.world.animals.iter_mut().zip(scores) {
+                animal.score = score;
+            }
+            loop {
+                if let Some(statistics) = simulation.step().unwrap() {
+                    return statistics;
+                }
+            }
+        };
+        let statistics = end_generation(&mut simulation, [14, 0, 0, 0]);
+        assert_eq!((statistics.min_score, statistics.max_score), (0.0, 14.0));
+
+        // A 5 is no better than a 14 for coming after a -10
+        let statistics = end_generation(&mut simulation, [5, -10, 0, 0]);
+        assert_eq!((statistics.min_score, statistics.max_score), (-10.0, 5.0));
+        let champion = simulation.champion().unwrap();
+        assert_eq!((champion.fitness, champion.generation), (14.0, 0));
+        assert!(simulation.last_generation().iter().any(|bird| bird.fitness == -10.0));
+    }
+
+    #[test]
+    fn brains_take_the_inputs_of_every_sensor() {
+        let mut config = SimulationConfig::default();
+        config.evolution.generation_length = 50;
+        config.sensors = vec![
+            SensorConfig::Smell { range: 0.3 },
+            SensorConfig::Proprioception,
+            SensorConfig::Rays { rays: 3, range: 0.2 },
+            SensorConfig::Clock { period: 20 },
+        ];
+        config.predators = PredatorConfig { count: 2, sensors: vec![SensorConfig::Clock { period: 20 }], ..Default::default() };
+        let mut simulation = Simulation::from_config(42, config.clone()).unwrap();
+
+        let inputs = |animal: &Animal| animal.brain.get_layers()[0].get_neurons()[0].get_weights().len();
+        for (species, extra) in [(Species::Prey, 2 + 4 + 3 + 2), (Species::Predator, 2)] {
+            let eye = Eye::for_species(&config, species);
+            assert_eq!(Animal::brain_layers(&config, species)[0].neurons, eye.inputs() + extra);
+        }
+        assert_eq!(inputs(&simulation.world().animals()[0]), 13 * 3 + 11);
+        assert_eq!(inputs(&simulation.world().predators()[0]), Animal::brain_layers(&config, Species::Predator)[0].neurons);
+
+        // Thinking with every sensor, on to the birds of the next generation
+        while simulation.step().unwrap().is_none() {}
+        assert_eq!(inputs(&simulation.world().animals()[0]), 13 * 3 + 11);
+    }
+
+    #[test]
+    fn birds_starve_without_food() {
+        let mut config = SimulationConfig::default();
+        config.world.food = 0;
+        config.evolution.generation_length = 300;
+        config.energy = EnergyConfig { enabled: true, metabolism: 0.01, survival_reward: 30.0, ..Default::default() };
+        let mut simulation = Simulation::from_config(42, config).unwrap();
+        // Energy left is one more input
+        assert_eq!(simulation.world().animals()[0].brain.weights().len(), (13 * 2 + 2) * 10 + (10 + 1) * 5 + (5 + 1) * 10 + (10 + 1) * 2);
+
+        for _ in 0..150 {
+            simulation.step().unwrap();
+        }
+        let world = simulation.world();
+        assert!(world.animals().iter().all(|a| a.is_exhausted() && a.energy() == 0.0));
+        let resting: Vec<_> = world.animals().iter().map(|a| (a.position, a.age)).collect();
+        simulation.step().unwrap();
+        assert_eq!(simulation.world().animals().iter().map(|a| (a.position, a.age)).collect::<Vec<_>>(), resting);
+
+        let statistics = loop {
+            if let Some(statistics) = simulation.step().unwrap() {
+                break statistics;
+            }
+        };
+        assert_eq!(statistics.starved, 10);
+        // Nothing to eat, so birds only score the share of the generation
+        // they lived through: at most a hundred steps of three hundred
+        assert!((1.0..=10.0).contains(&statistics.max_score), "{statistics:?}");
+        assert!(simulation.world().animals().iter().all(|a| !a.is_exhausted() && a.energy() == 1.0));
+    }
+
+    #[test]
+    fn birds_give_birth_and_die_in_the_world() {
+        let mut config = SimulationConfig::default();
+        config.world.food = 60;
+        config.world.eat_radius = 0.03;
+        config.evolution.generation_length = 100;
+        config.energy = EnergyConfig { enabled: true, ..Default::default() };
+        config.reproduction = ReproductionConfig { enabled: true, energy_threshold: 0.9, max_age: 250, max_population: 30, ..Default::default() };
+        let mut simulation = Simulation::from_config(42, config.clone()).unwrap();
+        let founders: HashSet<AnimalId> = simulation.world().animals().iter().map(|a| a.id).collect();
+
+        let mut samples = vec![];
+        for _ in 0..505 {
+            samples.extend(simulation.step().unwrap());
+            let world = simulation.world();
+            assert!((2..=30).contains(&world.animals().len()));
+            assert!(world.animals().iter().all(|a| a.age < 250 && !a.is_exhausted()));
+        }
+        // No generations, only samples of the flock every hundred steps
+        assert_eq!(samples.len(), 5);
+        assert!(samples.iter().map(|s| s.births).sum::<u32>() > 0);
+        assert!(samples.iter().all(|s| s.population >= 2));
+
+        let world = simulation.world();
+        assert!(world.animals().iter().all(|a| !founders.contains(&a.id)), "founders should have died of age");
+        let ids: HashSet<AnimalId> = world.animals().iter().map(|a| a.id).collect();
+        assert_eq!(ids.len(), world.animals().len());
+        let born = simulation.genealogy().records().filter(|r| r.parents.is_some_and(|p| p.iter().all(|id| simulation.genealogy().get(*id).is_some())));
+        assert!(born.count() > 0);
+
+        let mut replay = Simulation::from_config(42, config).unwrap();
+        for _ in 0..505 {
+            replay.step().unwrap();
+        }
+        assert_eq!(world_bits(replay.world()), world_bits(simulation.world()));
+    }
+
+    #[test]
+    fn food_rots_grows_back_and_follows_the_seasons() {
+        let mut config = SimulationConfig::default();
+        config.world.food = 20;
+        config.evolution.generation_length = 199;
+        config.food = FoodConfig {
+            regrowth_delay: 10,
+            placement: FoodPlacement::Patches { count: 2, radius: 0.05, drift: 0.01 },
+            lifetime: 30,
+            season_length: 100,
+            season_amplitude: 0.5,
+            regrowth_limit: Some(15),
+            kinds: vec![],
+        };
+        let mut simulation = Simulation::from_config(42, config.clone()).unwrap();
+        assert_eq!(simulation.world().food_patches().len(), 2);
+
+        let statistics = loop {
+            if let Some(statistics) = simulation.step().unwrap() {
+                break statistics;
+            }
+            let available = simulation.world().food().iter().filter(|f| f.is_available()).count();
+            // Half the food is gone in the middle of each cycle
+            if simulation.age % 100 == 50 {
+                assert!(available <= 10, "{available} food available");
+            }
+        };
+        assert_eq!(statistics.food_regrown, 15);
+        assert!(statistics.food_rotten >= 20, "{statistics:?}");
+        assert!(statistics.mean_food_available < 15.0, "{statistics:?}");
+
+        // Food grows back as soon as a new generation starts
+        simulation.step().unwrap();
+        assert!(simulation.world().food().iter().any(|f| f.age == 0 && f.is_available()));
+
+        let mut replay = Simulation::from_config(42, config).unwrap();
+        for _ in 0..201 {
+            replay.step().unwrap();
+        }
+        assert_eq!(world_bits(replay.world()), world_bits(simulation.world()));
+        assert_eq!(replay.world().food_patches(), simulation.world().food_patches());
+    }
+
+    #[test]
+    fn birds_eat_food_of_each_kind_for_its_value() {
+        let mut config = SimulationConfig::default();
+        config.world.food = 30;
+        config.food.kinds = vec![
+            FoodKind { name: "fruit".to_string(), share: 2.0, value: 3, radius: Some(0.03), ..Default::default() },
+            // Out of reach, so never eaten
+            FoodKind { name: "berry".to_string(), value: -2, radius: Some(0.0), visible: false, ..Default::default() },
+        ];
+        let mut simulation = Simulation::from_config(42, config).unwrap();
+        let world = simulation.world();
+        assert_eq!(world.food().iter().filter(|f| f.kind() == 0).count(), 20);
+        // Berries cannot be seen, so prey only see fruit and each other
+        assert_eq!(world.animals()[0].eye.channels(), [Channel::Food(0), Channel::Prey]);
+
+        for _ in 0..300 {
+            simulation.step().unwrap();
+        }
+        let eaten = simulation.activity.food_eaten as i32;
+        assert!(eaten > 0);
+        assert_eq!(simulation.world().animals().iter().map(|a| a.score).sum::<i32>(), 3 * eaten);
+        assert!(simulation.world().food().iter().filter(|f| f.kind() == 1).all(|f| f.age == 300));
+    }
+
+    #[test]
+    fn keeps_the_fittest_bird_as_champion() {
+        let mut simulation = Simulation::random(42, 10, 40, 0.1, 0.5, 100);
+        assert_eq!(simulation.champion(), None);
+        simulation.run(&RunController::new(StopCondition::Generations(3))).unwrap();
+
+        let champion = simulation.champion().unwrap();
+        let best = simulation.history().iter().map(|s| s.max_score).fold(f32::MIN, f32::max);
+        assert_eq!(champion.fitness, best);
+        assert_eq!(simulation.history().get(champion.generation).unwrap().max_score, best);
+        assert_eq!(champion.layers, [26, 10, 5, 10, 2]);
+        let layers: Vec<_> = champion.layers.iter().map(|&neurons| neural_network::LayerTopology { neurons }).collect();
+        let brain = neural_network::NeuralNetwork::from_weights(&layers, &champion.weights).unwrap();
+        assert_eq!(brain.weights(), champion.weights);
+    }
 }
@@ -0,0 +1,288 @@
+use nalgebra::{Point2, Rotation2, Vector2};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::Topology;
+
+/// Something static birds cannot fly through nor see through, placed in the
+/// unit square of the `World` by the config, e.g. in TOML:
+///
+/// ```toml
+/// [[world.obstacles]]
+/// shape = "circle"
+/// center = [0.5, 0.5]
+/// radius = 0.1
+///
+/// [[world.obstacles]]
+/// shape = "rectangle"
+/// center = [0.2, 0.8]
+/// size = [0.3, 0.05]
+/// angle = 0.5
+///
+/// [[world.obstacles]]
+/// shape = "polyline"
+/// points = [[0.1, 0.1], [0.4, 0.1], [0.4, 0.3]]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Obstacle {
+    Circle { center: Point2<f32>, radius: f32 },
+    /// `size` is the full width and height before turning it by `angle`
+    /// radians around its center; without an angle it is axis-aligned
+    Rectangle {
+        center: Point2<f32>,
+        size: Vector2<f32>,
+        #[serde(default)]
+        angle: f32,
+    },
+    /// Segments joining the points one after the other, a thin wall
+    Polyline { points: Vec<Point2<f32>> },
+}
+
+/// Where a ray first meets an obstacle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Hit {
+    /// From the origin of the ray, along its direction
+    pub(crate) distance: f32,
+    /// Unit normal of the surface that was hit, facing the ray
+    pub(crate) normal: Vector2<f32>,
+}
+
+// Below this, a ray is taken as parallel to a side or a segment
+const PARALLEL: f32 = 1e-9;
+
+impl Obstacle {
+    /// Why the obstacle makes no sense, if it does not
+    pub(crate) fn invalid(&self) -> Option<&'static str> {
+        let finite = |p: &Point2<f32>| p.x.is_finite() && p.y.is_finite();
+        let positive = |x: f32| x.is_finite() && x > 0.0;
+        let (valid, expected) = match self {
+            Obstacle::Circle { center, radius } => (finite(center) && positive(*radius), "circles with a positive radius"),
+            Obstacle::Rectangle { center, size, angle } => (
+                finite(center) && angle.is_finite() && positive(size.x) && positive(size.y),
+                "rectangles with a positive size",
+            ),
+            Obstacle::Polyline { points } => (points.len() >= 2 && points.iter().all(finite), "polylines of at least 2 points"),
+        };
+        (!valid).then_some(expected)
+    }
+
+    /// Whether `point` is inside the obstacle; nothing is inside a polyline
+    pub fn contains(&self, point: Point2<f32>) -> bool {
+        match self {
+            Obstacle::Circle { center, radius } => (point - center).norm_squared() < radius * radius,
+            Obstacle::Rectangle { center, size, angle } => {
+                let local = Rotation2::new(-angle) * (point - center);
+                local.x.abs() < size.x / 2.0 && local.y.abs() < size.y / 2.0
+            }
+            Obstacle::Polyline { .. } => false,
+        }
+    }
+
+    /// Circle around the whole obstacle
+    fn bounds(&self) -> (Point2<f32>, f32) {
+        match self {
+            Obstacle::Circle { center, radius } => (*center, *radius),
+            Obstacle::Rectangle { center, size, .. } => (*center, size.norm() / 2.0),
+            Obstacle::Polyline { points } => {
+                let center = Point2::from(points.iter().map(|p| p.coords).sum::<Vector2<f32>>() / points.len() as f32);
+                (center, points.iter().map(|p| (p - center).norm()).fold(0.0, f32::max))
+            }
+        }
+    }
+
+    /// Where the ray from `origin` along the unit vector `direction` enters
+    /// the obstacle, if it does within `max_distance`. A ray starting inside
+    /// a circle or rectangle does not hit it, so that whatever ended up
+    /// inside can get out.
+    pub(crate) fn cast(&self, origin: Point2<f32>, direction: Vector2<f32>, max_distance: f32) -> Option<Hit> {
+        let hit = match self {
+            Obstacle::Circle { center, radius } => cast_circle(*center, *radius, origin, direction),
+            Obstacle::Rectangle { center, size, angle } => cast_rectangle(*center, *size, *angle, origin, direction),
+            Obstacle::Polyline { points } => points
+                .windows(2)
+                .filter_map(|segment| cast_segment(segment[0], segment[1], origin, direction))
+                .min_by(|a, b| a.distance.total_cmp(&b.distance)),
+        };
+        hit.filter(|hit| hit.distance <= max_distance)
+    }
+}
+
+fn cast_circle(center: Point2<f32>, radius: f32, origin: Point2<f32>, direction: Vector2<f32>) -> Option<Hit> {
+    let m = origin - center;
+    let b = m.dot(&direction);
+    let c = m.norm_squared() - radius * radius;
+    let discriminant = b * b - c;
+    if c < 0.0 || b > 0.0 || discriminant < 0.0 {
+        return None;
+    }
+    let distance = -b - discriminant.sqrt();
+    let normal = (origin + direction * distance - center) / radius;
+    Some(Hit { distance, normal })
+}
+
+fn cast_rectangle(center: Point2<f32>, size: Vector2<f32>, angle: f32, origin: Point2<f32>, direction: Vector2<f32>) -> Option<Hit> {
+    // In the frame of the rectangle, where it is axis-aligned
+    let rotation = Rotation2::new(angle);
+    let o = rotation.inverse() * (origin - center);
+    let d = rotation.inverse() * direction;
+    let half = size / 2.0;
+
+    let (mut near, mut far) = (f32::NEG_INFINITY, f32::INFINITY);
+    let mut normal = Vector2::zeros();
+    for axis in 0..2 {
+        if d[axis].abs() < PARALLEL {
+            if o[axis].abs() >= half[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (-half[axis] - o[axis]) / d[axis];
+        let t2 = (half[axis] - o[axis]) / d[axis];
+        let (enter, exit) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+        if enter > near {
+            near = enter;
+            normal = Vector2::zeros();
+            normal[axis] = -d[axis].signum();
+        }
+        far = far.min(exit);
+    }
+    if near > far || near < 0.0 {
+        return None;
+    }
+    Some(Hit { distance: near, normal: rotation * normal })
+}
+
+fn cast_segment(a: Point2<f32>, b: Point2<f32>, origin: Point2<f32>, direction: Vector2<f32>) -> Option<Hit> {
+    let cross = |u: Vector2<f32>, v: Vector2<f32>| u.x * v.y - u.y * v.x;
+    let edge = b - a;
+    let denominator = cross(direction, edge);
+    if denominator.abs() < PARALLEL {
+        return None;
+    }
+    let w = a - origin;
+    let distance = cross(w, edge) / denominator;
+    let along = cross(w, direction) / denominator;
+    if distance < 0.0 || !(0.0..=1.0).contains(&along) {
+        return None;
+    }
+    let normal = Vector2::new(-edge.y, edge.x).normalize();
+    let normal = if normal.dot(&direction) > 0.0 { -normal } else { normal };
+    Some(Hit { distance, normal })
+}
+
+/// First obstacle met by the ray from `origin` along the unit vector
+/// `direction` within `max_distance`, including the copies of the obstacles
+/// across the edges of a torus
+pub(crate) fn cast(obstacles: &[Obstacle], topology: Topology, origin: Point2<f32>, direction: Vector2<f32>, max_distance: f32) -> Option<Hit> {
+    cast_among(&nearby(obstacles, topology, origin, max_distance), origin, direction, max_distance)
+}
+
+/// The obstacles, each with the shift of one of its copies, that some ray
+/// from `origin` may meet within `max_distance`, to cast many rays from the
+/// same place with `cast_among`
+pub(crate) fn nearby(obstacles: &[Obstacle], topology: Topology, origin: Point2<f32>, max_distance: f32) -> Vec<(&Obstacle, Vector2<f32>)> {
+    topology
+        .images()
+        .flat_map(|shift| obstacles.iter().map(move |obstacle| (obstacle, shift)))
+        .filter(|(obstacle, shift)| {
+            let (center, radius) = obstacle.bounds();
+            (center + shift - origin).norm() <= max_distance + radius
+        })
+        .collect()
+}
+
+pub(crate) fn cast_among(nearby: &[(&Obstacle, Vector2<f32>)], origin: Point2<f32>, direction: Vector2<f32>, max_distance: f32) -> Option<Hit> {
+    nearby
+        .iter()
+        .filter_map(|(obstacle, shift)| obstacle.cast(origin - shift, direction, max_distance))
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
+
+/// Whether `point` is inside one of the obstacles, or of their copies across
+/// the edges of a torus
+pub(crate) fn blocked(obstacles: &[Obstacle], topology: Topology, point: Point2<f32>) -> bool {
+    topology.images().any(|shift| obstacles.iter().any(|obstacle| obstacle.contains(point - shift)))
+}
+
+// Draws before giving up on finding a free spot in a crowded world
+const PLACEMENT_ATTEMPTS: usize = 100;
+
+/// A random point of the unit square outside every obstacle, if one is
+/// found in a reasonable number of draws; without obstacles this is exactly
+/// one draw
+pub(crate) fn free_position(obstacles: &[Obstacle], topology: Topology, rng: &mut dyn RngCore) -> Point2<f32> {
+    free_position_with(obstacles, topology, rng, |rng| rng.gen())
+}
+
+/// Like `free_position`, with the points drawn by `draw`
+pub(crate) fn free_position_with(
+    obstacles: &[Obstacle],
+    topology: Topology,
+    rng: &mut dyn RngCore,
+    mut draw: impl FnMut(&mut dyn RngCore) -> Point2<f32>,
+) -> Point2<f32> {
+    let mut position = draw(rng);
+    for _ in 1..PLACEMENT_ATTEMPTS {
+        if !blocked(obstacles, topology, position) {
+            break;
+        }
+        position = draw(rng);
+    }
+    position
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use std::f32::consts::FRAC_PI_4;
+    use test_case::test_case;
+
+    fn circle() -> Obstacle {
+        Obstacle::Circle { center: Point2::new(0.5, 0.5), radius: 0.1 }
+    }
+
+    fn rectangle(angle: f32) -> Obstacle {
+        Obstacle::Rectangle { center: Point2::new(0.5, 0.5), size: Vector2::new(0.2, 0.2), angle }
+    }
+
+    fn wall() -> Obstacle {
+        Obstacle::Polyline { points: vec![Point2::new(0.4, 0.6), Point2::new(0.4, 0.4), Point2::new(0.6, 0.4)] }
+    }
+
+    #[test_case(circle(), 0.5, 0.2)]
+    #[test_case(rectangle(0.0), 0.5, 0.2)]
+    #[test_case(rectangle(FRAC_PI_4), 0.52, 0.32 - 0.1 * std::f32::consts::SQRT_2)]
+    #[test_case(wall(), 0.5, 0.2)]
+    fn stops_rays_on_their_surface(obstacle: Obstacle, y: f32, distance: f32) {
+        let hit = obstacle.cast(Point2::new(0.2, y), Vector2::x(), 1.0).unwrap();
+        assert_relative_eq!(hit.distance, distance, epsilon = 1e-6);
+        assert!(hit.normal.x < 0.0, "{:?}", hit.normal);
+        assert_relative_eq!(hit.normal.norm(), 1.0, epsilon = 1e-6);
+
+        assert_eq!(obstacle.cast(Point2::new(0.2, y), Vector2::x(), distance - 0.01), None);
+        assert_eq!(obstacle.cast(Point2::new(0.2, y), -Vector2::x(), 1.0), None);
+        assert_eq!(obstacle.cast(Point2::new(0.2, 0.8), Vector2::x(), 1.0), None);
+    }
+
+    #[test]
+    fn lets_what_is_inside_out() {
+        assert!(circle().contains(Point2::new(0.55, 0.5)));
+        assert!(rectangle(FRAC_PI_4).contains(Point2::new(0.5, 0.63)));
+        assert!(!rectangle(0.0).contains(Point2::new(0.5, 0.63)));
+        assert!(!wall().contains(Point2::new(0.4, 0.5)));
+        assert_eq!(circle().cast(Point2::new(0.5, 0.5), Vector2::x(), 1.0), None);
+        assert_eq!(rectangle(0.0).cast(Point2::new(0.5, 0.5), Vector2::x(), 1.0), None);
+    }
+
+    #[test]
+    fn blocks_across_the_edges_of_a_torus() {
+        let obstacles = [Obstacle::Circle { center: Point2::new(0.0, 0.5), radius: 0.1 }];
+        let origin = Point2::new(0.8, 0.5);
+        assert_relative_eq!(cast(&obstacles, Topology::Torus, origin, Vector2::x(), 1.0).unwrap().distance, 0.1, epsilon = 1e-6);
+        assert_eq!(cast(&obstacles, Topology::Walls, origin, Vector2::x(), 1.0), None);
+        assert!(blocked(&obstacles, Topology::Torus, Point2::new(0.95, 0.5)));
+        assert!(!blocked(&obstacles, Topology::Walls, Point2::new(0.95, 0.5)));
+    }
+}
@@ -0,0 +1,101 @@
+use genetic_algorithm::{GeneticError, RunController, RunSummary};
+
+use crate::Simulation;
+
+impl Simulation {
+    /// Runs whole generations, without anybody watching, until `controller`
+    /// says to stop.
+    ///
+    /// Each generation lives for `generation_length` steps and is then
+    /// evolved exactly like `step` would do, following the simulation's
+    /// `Replacement`; the returned summary describes
+    /// the generations as they were scored, before being evolved.
+    pub fn run(&mut self, controller: &RunController) -> Result<RunSummary, GeneticError> {
+        controller.run(|_| {
+            while !self.generation_over() {
+                self.process_step()?;
+            }
+            let (population, _, evolved) = self.end_generation();
+            evolved?;
+            Ok(population)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn runs_until_stop_condition() {
+        let mut simulation = Simulation::random(42, 10, 20, 0.1, 0.5, 50);
+        let summary = simulation
+            .run(&RunController::new(StopCondition::Generations(3)))
+            .unwrap();
+        assert_eq!(summary.generations, 3);
+        assert_eq!(summary.reason, StopReason::Generations(3));
+        assert_eq!(simulation.age, 0);
+    }
+
+    #[test]
+    fn runs_with_other_optimizers() {
+        let optimizers: Vec<Box<dyn Optimizer<AnimalIndividual> + Send>> = vec![
+            Box::new(DifferentialEvolution::new(DifferentialStrategy::Best1Bin, 0.8, 0.9).unwrap()),
+            Box::new(CmaEs::new(0.5).unwrap()),
+        ];
+        for optimizer in optimizers {
+            let name = optimizer.name();
+            let config = SimulationConfig {
+                world: WorldConfig { animals: 6, food: 20, ..Default::default() },
+                evolution: EvolutionConfig { generation_length: 20, ..Default::default() },
+                ..Default::default()
+            };
+            let mut simulation = Simulation::with_optimizer(42, config, optimizer).unwrap();
+            let summary = simulation
+                .run(&RunController::new(StopCondition::Generations(3)))
+                .unwrap();
+            assert_eq!(summary.generations, 3);
+            assert_eq!(simulation.generation(), 3);
+            assert_eq!(simulation.world().animals().len(), 6);
+            assert_eq!(simulation.run_header().optimizer, name);
+        }
+    }
+
+    #[test]
+    fn generational_gap_keeps_the_fittest() {
+        let mut simulation = Simulation::random(42, 10, 40, 0.1, 0.5, 100)
+            .with_replacement(Replacement::GenerationalGap(0.3));
+        let founders: Vec<AnimalId> = simulation.world().animals().iter().map(|a| a.id()).collect();
+        simulation.run(&RunController::new(StopCondition::Generations(1))).unwrap();
+
+        let survivors = simulation.world().animals().iter().filter(|a| founders.contains(&a.id())).count();
+        assert_eq!(survivors, 7);
+        assert!(simulation.world().animals().iter().all(|a| a.score == 0));
+        assert_eq!(simulation.genealogy().len(), 13);
+    }
+
+    #[test]
+    fn steady_state_replaces_birds_as_they_fly() {
+        let mut simulation = Simulation::random(42, 10, 40, 0.1, 0.5, 100)
+            .with_replacement(Replacement::SteadyState(2));
+        // Two birds every 20 steps, once they flew for half a generation
+        for _ in 0..40 {
+            assert_eq!(simulation.step().unwrap(), None);
+        }
+        assert_eq!(simulation.genealogy().len(), 10);
+        for _ in 0..20 {
+            assert_eq!(simulation.step().unwrap(), None);
+        }
+        assert_eq!(simulation.genealogy().len(), 12);
+        let newborns: Vec<AnimalId> = simulation.world().animals().iter().filter(|a| a.age == 0).map(|a| a.id()).collect();
+        assert_eq!(newborns.len(), 2);
+        assert!(newborns.iter().all(|&id| simulation.genealogy().get(id).unwrap().parents.is_some()));
+
+        // However poorly they do, the newborns are not replaced before then
+        while simulation.step().unwrap().is_none() {}
+        assert_eq!(simulation.generation(), 1);
+        assert_eq!(simulation.genealogy().len(), 16);
+        assert!(newborns.iter().all(|id| simulation.world().animals().iter().any(|a| a.id() == *id)));
+    }
+}
@@ -0,0 +1,283 @@
+use std::f32::consts::TAU;
+use std::fmt;
+
+use nalgebra::{Point2, Vector2};
+
+use crate::obstacle::{self, Obstacle};
+use crate::{Animal, SensorConfig, SimulationConfig, SpatialGrid, Species, Topology};
+
+/// Something a bird senses, about itself or the world around it, turned into
+/// some of the inputs of its brain.
+///
+/// The brain of a bird gets the inputs of its `Eye` first, then those of the
+/// other sensors of its species in order, so its input layer is as big as
+/// all of them together.
+pub trait Sensor: fmt::Debug + Send + Sync {
+    /// Number of inputs the sensor gives the brain
+    fn inputs(&self) -> usize;
+
+    /// Appends exactly `inputs()` values sensed by `animal` to `out`
+    fn sense(&self, animal: &Animal, surroundings: &Surroundings, out: &mut Vec<f32>);
+}
+
+/// What there is around the birds during a step, for sensors to look at
+pub struct Surroundings<'a> {
+    pub(crate) food: &'a [SpatialGrid],
+    pub(crate) prey: &'a SpatialGrid,
+    pub(crate) predators: &'a SpatialGrid,
+    pub(crate) obstacles: &'a [Obstacle],
+    pub(crate) config: &'a SimulationConfig,
+    pub(crate) clock: u64,
+}
+
+impl Surroundings<'_> {
+    /// Available food of the kind at `kind` in `FoodConfig::kinds`
+    pub fn food(&self, kind: usize) -> &SpatialGrid {
+        &self.food[kind]
+    }
+
+    /// Available food of every kind
+    pub fn all_food(&self) -> &[SpatialGrid] {
+        self.food
+    }
+
+    pub fn prey(&self) -> &SpatialGrid {
+        self.prey
+    }
+
+    pub fn predators(&self) -> &SpatialGrid {
+        self.predators
+    }
+
+    pub fn obstacles(&self) -> &[Obstacle] {
+        self.obstacles
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.config.world.topology
+    }
+
+    pub fn config(&self) -> &SimulationConfig {
+        self.config
+    }
+
+    /// Steps since the simulation started
+    pub fn clock(&self) -> u64 {
+        self.clock
+    }
+}
+
+/// Sensors of the birds of `species` besides their eye: how much energy is
+/// left when birds run on energy, then those the config asks for
+pub(crate) fn for_species(config: &SimulationConfig, species: Species) -> Vec<Box<dyn Sensor>> {
+    let configured = match species {
+        Species::Prey => &config.sensors,
+        Species::Predator => &config.predators.sensors,
+    };
+    let energy = config.energy.enabled.then(|| Box::new(EnergyLevel) as Box<dyn Sensor>);
+    energy.into_iter().chain(configured.iter().map(SensorConfig::build)).collect()
+}
+
+impl SensorConfig {
+    pub fn build(&self) -> Box<dyn Sensor> {
+        match *self {
+            SensorConfig::Smell { range } => Box::new(Smell { range }),
+            SensorConfig::Proprioception => Box::new(Proprioception),
+            SensorConfig::Rays { rays, range } => Box::new(Rays { rays, range }),
+            SensorConfig::Clock { period } => Box::new(Clock { period }),
+        }
+    }
+}
+
+// Where `direction` points to, seen from a bird heading along `animal.rotation`:
+// how much ahead, then how much to the left
+fn egocentric(animal: &Animal, direction: Vector2<f32>) -> [f32; 2] {
+    let local = animal.rotation.inverse() * direction;
+    [local.x, local.y]
+}
+
+/// How much energy is left, from 0 to 1
+#[derive(Debug)]
+pub struct EnergyLevel;
+
+impl Sensor for EnergyLevel {
+    fn inputs(&self) -> usize {
+        1
+    }
+
+    fn sense(&self, animal: &Animal, surroundings: &Surroundings, out: &mut Vec<f32>) {
+        out.push(animal.energy / surroundings.config.energy.capacity);
+    }
+}
+
+/// Which way the nearest food within `range` is, of any kind, as a vector
+/// ahead and to the left of the bird, longer the closer the food is
+#[derive(Debug)]
+pub struct Smell {
+    pub range: f32,
+}
+
+impl Sensor for Smell {
+    fn inputs(&self) -> usize {
+        2
+    }
+
+    fn sense(&self, animal: &Animal, surroundings: &Surroundings, out: &mut Vec<f32>) {
+        let nearest = surroundings
+            .all_food()
+            .iter()
+            .flat_map(|grid| grid.within(animal.position, self.range))
+            .map(|(_, food)| food - animal.position)
+            .filter(|offset| offset.norm() <= self.range)
+            .min_by(|a, b| a.norm().total_cmp(&b.norm()));
+        let gradient = match nearest {
+            Some(offset) if offset.norm() > 0.0 => offset.normalize() * (self.range - offset.norm()) / self.range,
+            _ => Vector2::zeros(),
+        };
+        out.extend(egocentric(animal, gradient));
+    }
+}
+
+/// The bird's own speed (relative to `animal.speed_max`), heading and energy
+/// left, full when birds do not run on energy
+#[derive(Debug)]
+pub struct Proprioception;
+
+impl Sensor for Proprioception {
+    fn inputs(&self) -> usize {
+        4
+    }
+
+    fn sense(&self, animal: &Animal, surroundings: &Surroundings, out: &mut Vec<f32>) {
+        let config = surroundings.config;
+        let energy = if config.energy.enabled { animal.energy / config.energy.capacity } else { 1.0 };
+        let heading = animal.rotation.angle();
+        out.extend([animal.speed / config.animal.speed_max.max(f32::EPSILON), heading.cos(), heading.sin(), energy]);
+    }
+}
+
+/// `rays` rays spread evenly around the bird, the first one straight ahead,
+/// each activated the more the closer it meets an obstacle or, when the world
+/// does not wrap around, one of its edges within `range`
+#[derive(Debug)]
+pub struct Rays {
+    pub rays: usize,
+    pub range: f32,
+}
+
+impl Sensor for Rays {
+    fn inputs(&self) -> usize {
+        self.rays
+    }
+
+    fn sense(&self, animal: &Animal, surroundings: &Surroundings, out: &mut Vec<f32>) {
+        let topology = surroundings.topology();
+        let nearby = obstacle::nearby(surroundings.obstacles, topology, animal.position, self.range);
+        out.extend((0..self.rays).map(|ray| {
+            let angle = animal.rotation.angle() + TAU * ray as f32 / self.rays as f32;
+            let direction = Vector2::new(angle.cos(), angle.sin());
+            let obstacle = obstacle::cast_among(&nearby, animal.position, direction, self.range).map(|hit| hit.distance);
+            let edge = (!topology.wraps()).then(|| distance_to_edge(animal.position, direction)).filter(|&d| d <= self.range);
+            obstacle.into_iter().chain(edge).reduce(f32::min).map_or(0.0, |distance| (self.range - distance) / self.range)
+        }));
+    }
+}
+
+// How far from `position` a ray along `direction` leaves the unit square
+fn distance_to_edge(position: Point2<f32>, direction: Vector2<f32>) -> f32 {
+    let along = |coordinate: f32, step: f32| match step {
+        step if step > 0.0 => (1.0 - coordinate) / step,
+        step if step < 0.0 => -coordinate / step,
+        _ => f32::INFINITY,
+    };
+    along(position.x, direction.x).min(along(position.y, direction.y))
+}
+
+/// A signal cycling every `period` steps of the simulation, as the sine and
+/// cosine of its phase
+#[derive(Debug)]
+pub struct Clock {
+    pub period: u32,
+}
+
+impl Sensor for Clock {
+    fn inputs(&self) -> usize {
+        2
+    }
+
+    fn sense(&self, _animal: &Animal, surroundings: &Surroundings, out: &mut Vec<f32>) {
+        let phase = TAU * (surroundings.clock % self.period as u64) as f32 / self.period as f32;
+        out.extend([phase.sin(), phase.cos()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use nalgebra::Rotation2;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn bird(x: f32, y: f32, heading: f32) -> Animal {
+        let mut animal = Animal::random(&mut ChaCha8Rng::seed_from_u64(0));
+        animal.position = Point2::new(x, y);
+        animal.rotation = Rotation2::new(heading);
+        animal.speed = 0.0025;
+        animal
+    }
+
+    fn sense(sensor: &dyn Sensor, animal: &Animal, config: &SimulationConfig, food: &[Point2<f32>], obstacles: &[Obstacle]) -> Vec<f32> {
+        let topology = config.world.topology;
+        let food = [SpatialGrid::new(topology, food)];
+        let empty = SpatialGrid::new(topology, &[]);
+        let surroundings = Surroundings { food: &food, prey: &empty, predators: &empty, obstacles, config, clock: 25 };
+        let mut out = vec![];
+        sensor.sense(animal, &surroundings, &mut out);
+        assert_eq!(out.len(), sensor.inputs());
+        out
+    }
+
+    #[test]
+    fn smells_the_nearest_food() {
+        let config = SimulationConfig::default();
+        // Heading up, the food on the right of the bird is behind the nearest one
+        let food = [Point2::new(0.7, 0.5), Point2::new(0.5, 0.6)];
+        let smell = sense(&Smell { range: 0.4 }, &bird(0.5, 0.5, FRAC_PI_2), &config, &food, &[]);
+        assert_relative_eq!(smell.as_slice(), [0.75, 0.0].as_slice(), epsilon = 1e-6);
+        // Across the edge of a torus, to the left of a bird heading right
+        let smell = sense(&Smell { range: 0.4 }, &bird(0.5, 0.9, 0.0), &config, &[Point2::new(0.5, 0.1)], &[]);
+        assert_relative_eq!(smell.as_slice(), [0.0, 0.5].as_slice(), epsilon = 1e-6);
+        assert_eq!(sense(&Smell { range: 0.1 }, &bird(0.5, 0.5, 0.0), &config, &food, &[]), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn feels_its_own_body() {
+        let config = SimulationConfig::default();
+        let body = sense(&Proprioception, &bird(0.5, 0.5, FRAC_PI_2), &config, &[], &[]);
+        assert_relative_eq!(body.as_slice(), [0.5, 0.0, 1.0, 1.0].as_slice(), epsilon = 1e-6);
+    }
+
+    #[test]
+    fn casts_rays_at_walls_and_obstacles() {
+        let mut config = SimulationConfig::default();
+        config.world.topology = Topology::Walls;
+        let obstacles = [Obstacle::Circle { center: Point2::new(0.5, 0.7), radius: 0.1 }];
+        // Ahead, left, behind and right of a bird heading right
+        let rays = sense(&Rays { rays: 4, range: 0.5 }, &bird(0.9, 0.5, 0.0), &config, &[], &obstacles);
+        assert_relative_eq!(rays.as_slice(), [0.8, 0.0, 0.0, 0.0].as_slice(), epsilon = 1e-6);
+        let rays = sense(&Rays { rays: 4, range: 0.5 }, &bird(0.5, 0.5, 0.0), &config, &[], &obstacles);
+        assert_relative_eq!(rays.as_slice(), [0.0, 0.8, 0.0, 0.0].as_slice(), epsilon = 1e-6);
+
+        config.world.topology = Topology::Torus;
+        let rays = sense(&Rays { rays: 4, range: 0.5 }, &bird(0.9, 0.5, 0.0), &config, &[], &[]);
+        assert_eq!(rays, [0.0; 4]);
+    }
+
+    #[test]
+    fn ticks_with_the_simulation() {
+        let clock = sense(&Clock { period: 100 }, &bird(0.5, 0.5, 0.0), &SimulationConfig::default(), &[], &[]);
+        assert_relative_eq!(clock.as_slice(), [1.0, 0.0].as_slice(), epsilon = 1e-6);
+    }
+}
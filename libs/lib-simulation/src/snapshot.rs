@@ -0,0 +1,358 @@
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use genetic_algorithm::{Optimizer, Replacement};
+use nalgebra::{Point2, Rotation2};
+use neural_network::NeuralNetwork;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::events::EventBus;
+use crate::food;
+use crate::eye::Eye;
+use crate::statistics::Activity;
+use crate::{
+    Animal, AnimalId, AnimalIndividual, Champion, ConfigError, Food, Genealogy, IndividualSummary, Simulation, SimulationBuilder,
+    SimulationConfig, Species, StatisticsHistory, World,
+};
+
+/// Version of the snapshot format written by this build; bumped whenever a
+/// snapshot written by an older build could not be read back as it was
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Everything needed to resume a `Simulation` exactly where it was: the
+/// world, the counters, the RNG state, the config and the records kept so
+/// far.
+///
+/// The optimizer is not part of it, so a simulation evolved by an optimizer
+/// that learns across generations (`DifferentialEvolution`, `CmaEs`) resumes
+/// with a fresh one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    version: u32,
+    seed: u64,
+    rng: ChaCha8Rng,
+    config: SimulationConfig,
+    replacement: Replacement,
+    generation: usize,
+    age: u32,
+    animals: Vec<AnimalSnapshot>,
+    food: Vec<Point2<f32>>,
+    activity: Activity,
+    genealogy: Genealogy,
+    history: StatisticsHistory,
+    last_generation: Vec<IndividualSummary>,
+    #[serde(default)]
+    champion: Option<Champion>,
+    #[serde(default)]
+    predators: Vec<AnimalSnapshot>,
+    #[serde(default)]
+    predator_history: StatisticsHistory,
+    #[serde(default)]
+    food_state: Vec<FoodSnapshot>,
+    #[serde(default)]
+    food_patches: Vec<Point2<f32>>,
+}
+
+// What there is to know about some food besides its position
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FoodSnapshot {
+    eaten: bool,
+    regrows_in: u32,
+    age: u32,
+    in_season: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct AnimalSnapshot {
+    id: AnimalId,
+    position: Point2<f32>,
+    rotation: Rotation2<f32>,
+    speed: f32,
+    score: i32,
+    age: u32,
+    brain: Vec<f32>,
+    #[serde(default)]
+    energy: f32,
+    #[serde(default)]
+    exhausted: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("snapshot version {found} is not supported, expected {supported}")]
+    UnsupportedVersion { found: u32, supported: u32 },
+    #[error("snapshot brain of bird {id} has {actual} weights, the config asks for {expected}")]
+    BrainSize { id: AnimalId, expected: usize, actual: usize },
+    #[error("snapshot has {actual} predators, the config asks for {expected}")]
+    Predators { expected: usize, actual: usize },
+    #[error("snapshot has {actual} pieces of food, the config asks for {expected}")]
+    Food { expected: usize, actual: usize },
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("cannot read or write snapshot: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid snapshot: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl From<&Animal> for AnimalSnapshot {
+    fn from(animal: &Animal) -> Self {
+        Self {
+            id: animal.id,
+            position: animal.position,
+            rotation: animal.rotation,
+            speed: animal.speed,
+            score: animal.score,
+            age: animal.age,
+            brain: animal.brain.weights(),
+            energy: animal.energy,
+            exhausted: animal.exhausted,
+        }
+    }
+}
+
+impl AnimalSnapshot {
+    // Birds of `species`, with brains as big as `config` says
+    fn restore_all(animals: Vec<Self>, config: &SimulationConfig, species: Species) -> Result<Vec<Animal>, SnapshotError> {
+        let topology = Animal::brain_layers(config, species);
+        let expected: usize = topology.windows(2).map(|l| (l[0].neurons + 1) * l[1].neurons).sum();
+        animals
+            .into_iter()
+            .map(|animal| {
+                if animal.brain.len() != expected {
+                    return Err(SnapshotError::BrainSize { id: animal.id, expected, actual: animal.brain.len() });
+                }
+                Ok(Animal {
+                    id: animal.id,
+                    species,
+                    eye: Eye::for_species(config, species),
+                    brain: NeuralNetwork::from_weights(&topology, &animal.brain).expect("Brain size was checked above"),
+                    position: animal.position,
+                    rotation: animal.rotation,
+                    speed: animal.speed,
+                    score: animal.score,
+                    age: animal.age,
+                    energy: animal.energy,
+                    exhausted: animal.exhausted,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Snapshot {
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn config(&self) -> &SimulationConfig {
+        &self.config
+    }
+
+    /// Number of generations that ended before the snapshot was taken
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Writes the snapshot as JSON
+    pub fn save(&self, out: impl Write) -> Result<(), SnapshotError> {
+        serde_json::to_writer(out, self)?;
+        Ok(())
+    }
+
+    /// Reads a snapshot written by `save`, refusing the ones written in
+    /// another version of the format
+    pub fn load(input: impl Read) -> Result<Self, SnapshotError> {
+        let value: serde_json::Value = serde_json::from_reader(input)?;
+        let found = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+        if found != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion { found, supported: SNAPSHOT_VERSION });
+        }
+        let snapshot: Self = serde_json::from_value(value)?;
+        snapshot.config.validate()?;
+        Ok(snapshot)
+    }
+}
+
+impl Simulation {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            seed: self.seed,
+            rng: self.rng.clone(),
+            config: self.config.clone(),
+            replacement: self.replacement,
+            generation: self.generation,
+            age: self.age,
+            animals: self.world.animals.iter().map(AnimalSnapshot::from).collect(),
+            food: self.world.food.iter().map(|f| f.position).collect(),
+            activity: self.activity,
+            genealogy: self.genealogy.clone(),
+            history: self.history.clone(),
+            last_generation: self.last_generation.clone(),
+            champion: self.champion.clone(),
+            predators: self.world.predators.iter().map(AnimalSnapshot::from).collect(),
+            predator_history: self.predator_history.clone(),
+            food_state: self.world.food.iter()
+                .map(|f| FoodSnapshot { eaten: f.eaten, regrows_in: f.regrows_in, age: f.age, in_season: f.in_season })
+                .collect(),
+            food_patches: self.world.food_patches.clone(),
+        }
+    }
+
+    /// Resumes a simulation from `snapshot`, evolved by the default genetic
+    /// algorithm set up as the snapshot's config says
+    pub fn restore(snapshot: Snapshot) -> Result<Self, SnapshotError> {
+        SimulationBuilder::from_config(snapshot.config.clone())?.restore(snapshot)
+    }
+
+    /// Like `restore`, but the birds are evolved by `optimizer` from now on
+    pub fn restore_with_optimizer(
+        snapshot: Snapshot,
+        optimizer: impl Optimizer<AnimalIndividual> + Send + 'static,
+    ) -> Result<Self, SnapshotError> {
+        let Snapshot {
+            seed, rng, config, replacement, generation, age, animals, food, activity, genealogy, history, last_generation, champion,
+            predators, predator_history, food_state, food_patches, ..
+        } = snapshot;
+        config.validate_for(&optimizer)?;
+        // Birds born in the world come and go in any number
+        if !config.reproduction.enabled && predators.len() != config.predators.count {
+            return Err(SnapshotError::Predators { expected: config.predators.count, actual: predators.len() });
+        }
+        // Each piece of food gets its kind from its place in the config
+        if food.len() != config.world.food {
+            return Err(SnapshotError::Food { expected: config.world.food, actual: food.len() });
+        }
+        let world = World {
+            animals: AnimalSnapshot::restore_all(animals, &config, Species::Prey)?,
+            // Snapshots taken before food had a state only hold fresh food
+            food: food
+                .into_iter()
+                .zip(food::assign_kinds(&config.food.kinds(), config.world.food))
+                .enumerate()
+                .map(|(index, (position, kind))| {
+                    let mut food = Food::new(position, kind);
+                    if let Some(state) = food_state.get(index) {
+                        (food.eaten, food.regrows_in, food.age, food.in_season) = (state.eaten, state.regrows_in, state.age, state.in_season);
+                    }
+                    food
+                })
+                .collect(),
+            predators: AnimalSnapshot::restore_all(predators, &config, Species::Predator)?,
+            obstacles: config.world.obstacles.clone(),
+            food_patches,
+        };
+
+        let events = Arc::new(EventBus::default());
+        let mut evolution_algorithm: Box<dyn Optimizer<AnimalIndividual> + Send> = Box::new(optimizer);
+        evolution_algorithm.add_observer(Box::new(events.clone()));
+        Ok(Self {
+            lineage: events.subscribe(),
+            genealogy,
+            world,
+            seed,
+            rng,
+            predator_evolution: Simulation::predator_evolution(&config),
+            config,
+            evolution_algorithm,
+            replacement,
+            generation,
+            age,
+            events,
+            activity,
+            history,
+            predator_history,
+            last_generation,
+            champion,
+        })
+    }
+
+    /// Writes a snapshot of the simulation, see `Snapshot::save`
+    pub fn save(&self, out: impl Write) -> Result<(), SnapshotError> {
+        self.snapshot().save(out)
+    }
+
+    /// Resumes a simulation saved by `save`, see `Simulation::restore`
+    pub fn load(input: impl Read) -> Result<Self, SnapshotError> {
+        Self::restore(Snapshot::load(input)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::world_bits;
+
+    #[test]
+    fn resumes_exactly_where_it_stopped() {
+        let mut simulation = Simulation::random(42, 8, 20, 0.1, 0.5, 40)
+            .with_replacement(Replacement::GenerationalGap(0.5));
+        for _ in 0..100 {
+            simulation.step().unwrap();
+        }
+        let mut saved = vec![];
+        simulation.save(&mut saved).unwrap();
+        let mut resumed = Simulation::load(saved.as_slice()).unwrap();
+        assert_eq!(resumed.snapshot(), simulation.snapshot());
+
+        for _ in 0..100 {
+            assert_eq!(resumed.step().unwrap(), simulation.step().unwrap());
+        }
+        assert_eq!(world_bits(resumed.world()), world_bits(simulation.world()));
+        assert_eq!(resumed.generation(), 4);
+        assert_eq!(resumed.history(), simulation.history());
+        assert_eq!(resumed.genealogy(), simulation.genealogy());
+    }
+
+    #[test]
+    fn resumes_predators() {
+        let mut config = SimulationConfig::default();
+        config.evolution.generation_length = 30;
+        config.predators.count = 3;
+        let mut simulation = Simulation::from_config(42, config).unwrap();
+        for _ in 0..45 {
+            simulation.step().unwrap();
+        }
+        let mut saved = vec![];
+        simulation.save(&mut saved).unwrap();
+        let mut resumed = Simulation::load(saved.as_slice()).unwrap();
+        for _ in 0..45 {
+            assert_eq!(resumed.step().unwrap(), simulation.step().unwrap());
+        }
+        assert_eq!(world_bits(resumed.world()), world_bits(simulation.world()));
+        assert_eq!(resumed.predator_history(), simulation.predator_history());
+
+        let mut saved = serde_json::to_value(simulation.snapshot()).unwrap();
+        saved["predators"].as_array_mut().unwrap().pop();
+        let err = Simulation::restore(Snapshot::load(saved.to_string().as_bytes()).unwrap()).err().unwrap();
+        assert!(matches!(err, SnapshotError::Predators { expected: 3, actual: 2 }));
+    }
+
+    #[test]
+    fn rejects_missing_food() {
+        let mut saved = serde_json::to_value(Simulation::random(42, 2, 4, 0.1, 0.5, 10).snapshot()).unwrap();
+        saved["food"].as_array_mut().unwrap().pop();
+        let err = Simulation::restore(Snapshot::load(saved.to_string().as_bytes()).unwrap()).err().unwrap();
+        assert!(matches!(err, SnapshotError::Food { expected: 4, actual: 3 }));
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut saved = serde_json::to_value(Simulation::random(42, 2, 2, 0.1, 0.5, 10).snapshot()).unwrap();
+        saved["version"] = 99.into();
+        let err = Snapshot::load(saved.to_string().as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), format!("snapshot version 99 is not supported, expected {SNAPSHOT_VERSION}"));
+
+        saved["version"] = SNAPSHOT_VERSION.into();
+        saved["animals"][0]["brain"] = serde_json::json!([1.0]);
+        let err = Simulation::restore(Snapshot::load(saved.to_string().as_bytes()).unwrap()).err().unwrap();
+        assert!(matches!(err, SnapshotError::BrainSize { actual: 1, .. }));
+    }
+}
@@ -0,0 +1,170 @@
+use nalgebra::Point2;
+
+use crate::Topology;
+
+/// Uniform grid over the unit square of the `World`, measuring distances
+/// the way its `Topology` does, across the edges on a torus.
+///
+/// Points are bucketed once, then `within` only looks at the cells around a
+/// query instead of every point. The grid does not follow points that move,
+/// so it is rebuilt every step.
+#[derive(Debug, Clone)]
+pub struct SpatialGrid {
+    topology: Topology,
+    cells_per_side: usize,
+    points: Vec<Point2<f32>>,
+    // Indices of the points of cell `c` are `indices[starts[c]..starts[c + 1]]`
+    starts: Vec<usize>,
+    indices: Vec<usize>,
+}
+
+/// Points per cell the grid aims for when it picks its own resolution
+const POINTS_PER_CELL: usize = 2;
+const MAX_CELLS_PER_SIDE: usize = 256;
+
+impl SpatialGrid {
+    /// Buckets `points`, all expected inside the unit square
+    pub fn new(topology: Topology, points: &[Point2<f32>]) -> Self {
+        let cells_per_side = ((points.len() / POINTS_PER_CELL) as f32).sqrt().ceil() as usize;
+        Self::with_cells_per_side(topology, points, cells_per_side)
+    }
+
+    pub fn with_cells_per_side(topology: Topology, points: &[Point2<f32>], cells_per_side: usize) -> Self {
+        let cells_per_side = cells_per_side.clamp(1, MAX_CELLS_PER_SIDE);
+        let mut grid = Self { topology, cells_per_side, points: points.to_vec(), starts: vec![0; cells_per_side * cells_per_side + 1], indices: vec![] };
+        let cells: Vec<usize> = points.iter().map(|p| grid.cell_of(p)).collect();
+        // Counting sort of the points by cell
+        for &cell in &cells {
+            grid.starts[cell + 1] += 1;
+        }
+        for cell in 0..cells_per_side * cells_per_side {
+            grid.starts[cell + 1] += grid.starts[cell];
+        }
+        let mut next = grid.starts.clone();
+        grid.indices = vec![0; points.len()];
+        for (index, &cell) in cells.iter().enumerate() {
+            grid.indices[next[cell]] = index;
+            next[cell] += 1;
+        }
+        grid
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    // Points on the far edges of a world that does not wrap around, as
+    // birds against a wall, belong to the last cells rather than the first
+    fn axis_cell(&self, coordinate: f32) -> usize {
+        let coordinate = if self.topology.wraps() { coordinate.rem_euclid(1.0) } else { coordinate };
+        let cell = (coordinate * self.cells_per_side as f32) as usize;
+        cell.min(self.cells_per_side - 1)
+    }
+
+    fn cell_of(&self, point: &Point2<f32>) -> usize {
+        self.axis_cell(point.y) * self.cells_per_side + self.axis_cell(point.x)
+    }
+
+    /// Points no further than `radius` from `center`, by increasing index,
+    /// each with its position moved next to `center` (on a torus, the copy of
+    /// the point the shortest way round), so that plain euclidean geometry
+    /// works on it.
+    ///
+    /// When the query covers the whole grid, points are only checked as the
+    /// iterator is advanced.
+    pub fn within(&self, center: Point2<f32>, radius: f32) -> Box<dyn Iterator<Item = (usize, Point2<f32>)> + '_> {
+        let n = self.cells_per_side;
+        let reach = (radius * n as f32).ceil() as usize;
+        // Once the cells spanned on each axis make a whole side, every point
+        // is a candidate: scan them in order rather than cell by cell
+        if 2 * reach + 1 >= n {
+            return Box::new((0..self.points.len()).filter_map(move |i| {
+                let offset = self.topology.offset(center, self.points[i]);
+                (offset.norm_squared() <= radius * radius).then_some((i, center + offset))
+            }));
+        }
+        let around = |cell: usize| -> Vec<usize> {
+            if self.topology.wraps() {
+                (0..=2 * reach).map(|i| (cell + n + i - reach) % n).collect()
+            } else {
+                (cell.saturating_sub(reach)..=(cell + reach).min(n - 1)).collect()
+            }
+        };
+        let (columns, rows) = (around(self.axis_cell(center.x)), around(self.axis_cell(center.y)));
+
+        let mut found: Vec<usize> = vec![];
+        for &row in &rows {
+            for &column in &columns {
+                let cell = row * n + column;
+                found.extend(
+                    self.indices[self.starts[cell]..self.starts[cell + 1]]
+                        .iter()
+                        .copied()
+                        .filter(|&i| self.topology.offset(center, self.points[i]).norm_squared() <= radius * radius),
+                );
+            }
+        }
+        found.sort_unstable();
+        Box::new(found.into_iter().map(move |i| (i, center + self.topology.offset(center, self.points[i]))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    // What the grid should answer, by looking at every point
+    fn brute_force(topology: Topology, points: &[Point2<f32>], center: Point2<f32>, radius: f32) -> Vec<usize> {
+        (0..points.len()).filter(|&i| topology.distance(center, points[i]) <= radius).collect()
+    }
+
+    #[test]
+    fn finds_the_same_points_as_a_full_scan() {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let random: Vec<Point2<f32>> = (0..500).map(|_| rng.gen()).collect();
+        // Birds pressed against the walls, and food clamped there
+        let edges = [Point2::new(1.0, 0.5), Point2::new(0.5, 1.0), Point2::new(1.0, 1.0), Point2::new(0.0, 1.0)];
+        for topology in Topology::ALL {
+            let points: Vec<_> = if topology.wraps() { random.clone() } else { random.iter().chain(&edges).copied().collect() };
+            let grids = [
+                SpatialGrid::new(topology, &points),
+                SpatialGrid::with_cells_per_side(topology, &points, 1),
+                SpatialGrid::with_cells_per_side(topology, &points, 7),
+            ];
+            let centers: Vec<Point2<f32>> = (0..100).map(|_| rng.gen()).collect();
+            for center in centers.into_iter().chain(edges) {
+                for radius in [0.0, 0.01, 0.05, 0.3, 0.75] {
+                    let expected = brute_force(topology, &points, center, radius);
+                    for grid in &grids {
+                        let found: Vec<usize> = grid.within(center, radius).map(|(i, _)| i).collect();
+                        assert_eq!(found, expected, "{topology:?}, radius {radius} around {center}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sees_across_the_edges() {
+        let points = [Point2::new(0.99, 0.5), Point2::new(0.5, 0.02), Point2::new(0.5, 0.5)];
+        let grid = SpatialGrid::new(Topology::Torus, &points);
+        let found: Vec<_> = grid.within(Point2::new(0.01, 0.5), 0.05).collect();
+        assert_eq!(found.len(), 1);
+        let (index, position) = found[0];
+        assert_eq!(index, 0);
+        approx::assert_relative_eq!(position, Point2::new(-0.01, 0.5), epsilon = 1e-6);
+
+        let found: Vec<_> = grid.within(Point2::new(0.5, 0.98), 0.05).collect();
+        assert_eq!(found.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [1]);
+        approx::assert_relative_eq!(found[0].1, Point2::new(0.5, 1.02), epsilon = 1e-6);
+
+        let walled = SpatialGrid::new(Topology::Walls, &points);
+        assert_eq!(walled.within(Point2::new(0.01, 0.5), 0.05).count(), 0);
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use genetic_algorithm::Individual;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+/// Score percentiles of a generation, linearly interpolated between birds
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Percentiles {
+    pub p10: f32,
+    pub p25: f32,
+    pub p75: f32,
+    pub p90: f32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Statistics {
+    pub min_score: f32,
+    pub avg_score: f32,
+    pub max_score: f32,
+    pub median_score: f32,
+    pub std_dev_score: f32,
+    pub percentiles: Percentiles,
+    /// Mean euclidean distance between the genes of every pair of birds, or
+    /// of `DIVERSITY_PAIRS` random pairs in bigger populations
+    pub mean_pairwise_distance: f32,
+    /// Variance of each gene across the population, averaged over the genes
+    pub mean_gene_variance: f32,
+    /// Number of different brains in the population
+    pub distinct_brains: usize,
+    pub food_eaten: u32,
+    /// Number of times a bird bumped into another one
+    pub collisions: u32,
+    pub mean_speed: f32,
+    /// Number of birds that ran out of energy
+    #[serde(default)]
+    pub starved: u32,
+    /// Number of birds alive when the statistics were taken
+    #[serde(default)]
+    pub population: usize,
+    /// Number of birds born in the world, with `reproduction`
+    #[serde(default)]
+    pub births: u32,
+    /// Number of times food grew back
+    #[serde(default)]
+    pub food_regrown: u32,
+    /// Number of times food rotted before being eaten
+    #[serde(default)]
+    pub food_rotten: u32,
+    /// Food birds could see and eat, on average over the steps
+    #[serde(default)]
+    pub mean_food_available: f32,
+}
+
+/// What happened in the world during a generation, besides the scores
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Activity {
+    pub(crate) food_eaten: u32,
+    pub(crate) collisions: u32,
+    pub(crate) speed_sum: f32,
+    pub(crate) speed_samples: u32,
+    #[serde(default)]
+    pub(crate) starved: u32,
+    #[serde(default)]
+    pub(crate) births: u32,
+    #[serde(default)]
+    pub(crate) food_regrown: u32,
+    #[serde(default)]
+    pub(crate) food_rotten: u32,
+    #[serde(default)]
+    pub(crate) food_available_sum: u64,
+    #[serde(default)]
+    pub(crate) steps: u32,
+}
+
+/// Pairs of birds compared at most for `Statistics::mean_pairwise_distance`,
+/// which would otherwise grow with the square of the population
+pub const DIVERSITY_PAIRS: usize = 1024;
+
+impl Statistics {
+    /// Statistics of `population`, with `rng` picking the pairs of birds
+    /// compared when there are more than `DIVERSITY_PAIRS` of them
+    pub fn from_population<I>(population: &[I], rng: &mut dyn RngCore) -> Self
+    where
+        I: Individual,
+    {
+        let mut sorted_scores: Vec<f32> = population.iter().map(|i| i.fitness()).collect();
+        sorted_scores.sort_by(f32::total_cmp);
+        let avg_score = sorted_scores.iter().sum::<f32>() / sorted_scores.len() as f32;
+        let variance = sorted_scores.iter().map(|s| (s - avg_score).powi(2)).sum::<f32>() / sorted_scores.len().max(1) as f32;
+
+        Self {
+            min_score: sorted_scores.first().copied().unwrap_or(0.0),
+            max_score: sorted_scores.last().copied().unwrap_or(0.0),
+            avg_score,
+            median_score: percentile(&sorted_scores, 0.5),
+            std_dev_score: variance.sqrt(),
+            percentiles: Percentiles {
+                p10: percentile(&sorted_scores, 0.1),
+                p25: percentile(&sorted_scores, 0.25),
+                p75: percentile(&sorted_scores, 0.75),
+                p90: percentile(&sorted_scores, 0.9),
+            },
+            mean_pairwise_distance: mean_pairwise_distance(population, rng),
+            mean_gene_variance: mean_gene_variance(population),
+            distinct_brains: distinct_brains(population),
+            population: population.len(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn with_activity(self, activity: &Activity) -> Self {
+        Self {
+            food_eaten: activity.food_eaten,
+            collisions: activity.collisions,
+            mean_speed: activity.speed_sum / activity.speed_samples.max(1) as f32,
+            starved: activity.starved,
+            births: activity.births,
+            food_regrown: activity.food_regrown,
+            food_rotten: activity.food_rotten,
+            mean_food_available: activity.food_available_sum as f32 / activity.steps.max(1) as f32,
+            ..self
+        }
+    }
+}
+
+fn percentile(sorted: &[f32], p: f32) -> f32 {
+    match sorted {
+        [] => 0.0,
+        [only] => *only,
+        _ => {
+            let rank = p * (sorted.len() - 1) as f32;
+            let (low, high) = (rank.floor() as usize, rank.ceil() as usize);
+            sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f32)
+        }
+    }
+}
+
+fn mean_pairwise_distance<I: Individual>(population: &[I], rng: &mut dyn RngCore) -> f32 {
+    let distance = |a: &I, b: &I| {
+        a.chromosome().iter()
+            .zip(b.chromosome().iter())
+            .map(|(x, y)| (x - y).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    };
+    let size = population.len();
+    let pairs = size * size.saturating_sub(1) / 2;
+    if pairs == 0 {
+        return 0.0;
+    }
+    if pairs <= DIVERSITY_PAIRS {
+        let total: f32 = population.iter().enumerate()
+            .flat_map(|(i, a)| population[i + 1..].iter().map(move |b| (a, b)))
+            .map(|(a, b)| distance(a, b))
+            .sum();
+        return total / pairs as f32;
+    }
+    let total: f32 = (0..DIVERSITY_PAIRS)
+        .map(|_| {
+            let i = rng.gen_range(0..size);
+            // Any bird but the first one
+            let j = (i + rng.gen_range(1..size)) % size;
+            distance(&population[i], &population[j])
+        })
+        .sum();
+    total / DIVERSITY_PAIRS as f32
+}
+
+fn mean_gene_variance<I: Individual>(population: &[I]) -> f32 {
+    let Some(genes) = population.first().map(|i| i.chromosome().genes().len()) else {
+        return 0.0;
+    };
+    if genes == 0 {
+        return 0.0;
+    }
+    let size = population.len() as f32;
+    let total: f32 = (0..genes)
+        .map(|g| {
+            let mean = population.iter().map(|i| i.chromosome()[g]).sum::<f32>() / size;
+            population.iter().map(|i| (i.chromosome()[g] - mean).powi(2)).sum::<f32>() / size
+        })
+        .sum();
+    total / genes as f32
+}
+
+// Brains are told apart by a hash of their genes rather than by the genes
+// themselves, not to copy every chromosome
+fn distinct_brains<I: Individual>(population: &[I]) -> usize {
+    population
+        .iter()
+        .map(|i| {
+            let mut hasher = DefaultHasher::new();
+            i.chromosome().iter().for_each(|g| g.to_bits().hash(&mut hasher));
+            hasher.finish()
+        })
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Statistics of every generation of a simulation, oldest first
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatisticsHistory {
+    generations: Vec<Statistics>,
+}
+
+impl StatisticsHistory {
+    pub fn push(&mut self, statistics: Statistics) {
+        self.generations.push(statistics);
+    }
+
+    pub fn len(&self) -> usize {
+        self.generations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.generations.is_empty()
+    }
+
+    pub fn get(&self, generation: usize) -> Option<&Statistics> {
+        self.generations.get(generation)
+    }
+
+    pub fn last(&self) -> Option<&Statistics> {
+        self.generations.last()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Statistics> {
+        self.generations.iter()
+    }
+
+    /// One value per generation, e.g. `history.series(|s| s.avg_score)` to plot it
+    pub fn series<T>(&self, value: impl Fn(&Statistics) -> T) -> Vec<T> {
+        self.generations.iter().map(value).collect()
+    }
+
+    /// Generation with the highest `max_score`, the earliest one on ties
+    pub fn best_generation(&self) -> Option<usize> {
+        self.generations
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by(|(_, a), (_, b)| a.max_score.total_cmp(&b.max_score))
+            .map(|(generation, _)| generation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use genetic_algorithm::Chromosome;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    struct Bird {
+        score: f32,
+        chromosome: Chromosome,
+    }
+
+    impl Individual for Bird {
+        fn fitness(&self) -> f32 {
+            self.score
+        }
+
+        fn chromosome(&self) -> &Chromosome {
+            &self.chromosome
+        }
+
+        fn create(chromosome: Chromosome) -> Self {
+            Self { score: 0.0, chromosome }
+        }
+    }
+
+    fn bird(score: f32, genes: &[f32]) -> Bird {
+        Bird { score, chromosome: genes.iter().copied().collect() }
+    }
+
+    #[test]
+    fn score_statistics() {
+        let population: Vec<Bird> = [4.0, 1.0, 3.0, 2.0, 10.0].into_iter().map(|s| bird(s, &[])).collect();
+        let statistics = Statistics::from_population(&population, &mut ChaCha8Rng::seed_from_u64(42));
+        assert_eq!(statistics.min_score, 1.0);
+        assert_eq!(statistics.max_score, 10.0);
+        assert_eq!(statistics.avg_score, 4.0);
+        assert_eq!(statistics.median_score, 3.0);
+        approx::assert_relative_eq!(statistics.std_dev_score, 10.0_f32.sqrt());
+        approx::assert_relative_eq!(statistics.percentiles.p10, 1.4);
+        approx::assert_relative_eq!(statistics.percentiles.p25, 2.0);
+        approx::assert_relative_eq!(statistics.percentiles.p75, 4.0);
+        approx::assert_relative_eq!(statistics.percentiles.p90, 7.6);
+    }
+
+    #[test]
+    fn fractional_and_negative_scores() {
+        // Survival rewards and steady-state scaling make fitness fractional,
+        // poison makes it negative
+        let population: Vec<Bird> = [2.5, -1.5, 0.25, 3.75].into_iter().map(|s| bird(s, &[])).collect();
+        let statistics = Statistics::from_population(&population, &mut ChaCha8Rng::seed_from_u64(42));
+        assert_eq!(statistics.min_score, -1.5);
+        assert_eq!(statistics.max_score, 3.75);
+        assert_eq!(statistics.avg_score, 1.25);
+        assert_eq!(statistics.median_score, 1.375);
+        approx::assert_relative_eq!(statistics.std_dev_score, 2.0233018);
+        approx::assert_relative_eq!(statistics.percentiles.p25, -0.1875);
+        approx::assert_relative_eq!(statistics.percentiles.p90, 3.375);
+    }
+
+    #[test]
+    fn gene_statistics() {
+        let population = vec![
+            bird(0.0, &[0.0, 0.0]),
+            bird(0.0, &[0.0, 0.0]),
+            bird(0.0, &[3.0, 4.0]),
+        ];
+        let statistics = Statistics::from_population(&population, &mut ChaCha8Rng::seed_from_u64(42));
+        assert_eq!(statistics.distinct_brains, 2);
+        approx::assert_relative_eq!(statistics.mean_pairwise_distance, 10.0 / 3.0);
+        approx::assert_relative_eq!(statistics.mean_gene_variance, (2.0 + 32.0 / 9.0) / 2.0);
+    }
+
+    #[test]
+    fn gene_statistics_of_a_big_population() {
+        // Half of the 4950 pairs are 5 apart, more than are compared
+        let population: Vec<Bird> = (0..100).map(|i| if i % 2 == 0 { bird(0.0, &[0.0, 0.0]) } else { bird(0.0, &[3.0, 4.0]) }).collect();
+        let statistics = Statistics::from_population(&population, &mut ChaCha8Rng::seed_from_u64(42));
+        assert_eq!(statistics.distinct_brains, 2);
+        approx::assert_abs_diff_eq!(statistics.mean_pairwise_distance, 5.0 * 50.0 / 99.0, epsilon = 0.3);
+    }
+
+    #[test]
+    fn activity_statistics() {
+        let statistics = Statistics::default().with_activity(&Activity {
+            food_eaten: 12,
+            collisions: 3,
+            speed_sum: 1.0,
+            speed_samples: 4,
+            starved: 2,
+            food_available_sum: 30,
+            steps: 4,
+            ..Default::default()
+        });
+        assert_eq!((statistics.food_eaten, statistics.collisions, statistics.mean_speed, statistics.starved), (12, 3, 0.25, 2));
+        assert_eq!(statistics.mean_food_available, 7.5);
+    }
+
+    #[test]
+    fn simulation_collects_history() {
+        use crate::Simulation;
+
+        let mut simulation = Simulation::random(42, 10, 40, 0.1, 0.5, 200);
+        let mut generations = vec![];
+        while generations.len() < 2 {
+            generations.extend(simulation.step().unwrap());
+        }
+        assert_eq!(simulation.history().iter().copied().collect::<Vec<_>>(), generations);
+        for statistics in generations {
+            approx::assert_relative_eq!(statistics.food_eaten as f32, statistics.avg_score * 10.0, epsilon = 1e-3);
+            assert!(statistics.mean_speed > 0.0);
+        }
+    }
+
+    #[test]
+    fn history() {
+        let mut history = StatisticsHistory::default();
+        for max_score in [3.0, 7.0, 5.0, 7.0] {
+            history.push(Statistics { max_score, ..Default::default() });
+        }
+        assert_eq!(history.len(), 4);
+        assert_eq!(history.series(|s| s.max_score), vec![3.0, 7.0, 5.0, 7.0]);
+        assert_eq!(history.best_generation(), Some(1));
+    }
+}
@@ -0,0 +1,122 @@
+use nalgebra::{wrap, Point2, Rotation2, Vector2};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Shape of the unit square the birds live in: what happens at its edges,
+/// and so how far apart two points are.
+///
+/// Movement, eating, collisions and vision all go through it, so that a bird
+/// never sees food it could not fly to the way it sees it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Topology {
+    /// Leaving on one side comes back on the opposite one, and birds see and
+    /// reach across the edges
+    #[default]
+    Torus,
+    /// The edges stop birds, which keep their heading and slide along them
+    /// until their brain turns them away
+    Walls,
+    /// The edges bounce birds back like a mirror
+    Reflecting,
+}
+
+impl Topology {
+    pub const ALL: [Topology; 3] = [Topology::Torus, Topology::Walls, Topology::Reflecting];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Topology::Torus => "torus",
+            Topology::Walls => "walls",
+            Topology::Reflecting => "reflecting",
+        }
+    }
+
+    /// Whether the edges connect to the opposite ones
+    pub fn wraps(self) -> bool {
+        self == Topology::Torus
+    }
+
+    /// Shifts from the unit square to its copies that matter when measuring
+    /// across it: the eight neighbouring ones on a torus, none otherwise
+    pub(crate) fn images(self) -> impl Iterator<Item = Vector2<f32>> {
+        let shifts = if self.wraps() { -1..=1 } else { 0..=0 };
+        shifts.clone().flat_map(move |x| shifts.clone().map(move |y| Vector2::new(x as f32, y as f32)))
+    }
+
+    /// Shortest vector going from `from` to `to`
+    pub fn offset(self, from: Point2<f32>, to: Point2<f32>) -> Vector2<f32> {
+        let d = to - from;
+        match self {
+            Topology::Torus => Vector2::new(d.x - d.x.round(), d.y - d.y.round()),
+            Topology::Walls | Topology::Reflecting => d,
+        }
+    }
+
+    pub fn distance(self, a: Point2<f32>, b: Point2<f32>) -> f32 {
+        self.offset(a, b).norm()
+    }
+
+    /// Where a bird at `position` heading along `rotation` ends up after
+    /// flying `distance`, and which way it is heading then
+    pub fn advance(self, position: Point2<f32>, rotation: Rotation2<f32>, distance: f32) -> (Point2<f32>, Rotation2<f32>) {
+        let angle = rotation.angle();
+        let x = position.x + distance * angle.cos();
+        let y = position.y + distance * angle.sin();
+        match self {
+            Topology::Torus => (Point2::new(wrap(x, 0.0, 1.0), wrap(y, 0.0, 1.0)), rotation),
+            Topology::Walls => (Point2::new(x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)), rotation),
+            Topology::Reflecting => {
+                let (x, flip_x) = reflect(x);
+                let (y, flip_y) = reflect(y);
+                // Bouncing off a vertical edge mirrors the heading around the
+                // y axis, off a horizontal one around the x axis
+                let angle = if flip_x { PI - angle } else { angle };
+                let angle = if flip_y { -angle } else { angle };
+                (Point2::new(x, y), Rotation2::new(angle))
+            }
+        }
+    }
+}
+
+// Folds `coordinate` back into [0, 1] as many times as it went past an edge,
+// telling whether that was an odd number of times
+fn reflect(coordinate: f32) -> (f32, bool) {
+    let folded = coordinate.rem_euclid(2.0);
+    if folded > 1.0 {
+        (2.0 - folded, true)
+    } else {
+        (folded, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use test_case::test_case;
+
+    #[test_case(Topology::Torus, 0.02)]
+    #[test_case(Topology::Walls, 0.98)]
+    #[test_case(Topology::Reflecting, 0.98)]
+    fn measures_across_the_edges(topology: Topology, expected: f32) {
+        let distance = topology.distance(Point2::new(0.99, 0.5), Point2::new(0.01, 0.5));
+        assert_relative_eq!(distance, expected, epsilon = 1e-6);
+    }
+
+    #[test_case(Topology::Torus, (0.02, 0.5), 0.0)]
+    #[test_case(Topology::Walls, (1.0, 0.5), 0.0)]
+    #[test_case(Topology::Reflecting, (0.98, 0.5), PI)]
+    fn flies_into_the_right_edge(topology: Topology, expected: (f32, f32), heading: f32) {
+        let (position, rotation) = topology.advance(Point2::new(0.99, 0.5), Rotation2::new(0.0), 0.03);
+        assert_relative_eq!(position, Point2::new(expected.0, expected.1), epsilon = 1e-6);
+        assert_relative_eq!(rotation.angle().abs(), heading, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn bounces_off_a_corner() {
+        let (position, rotation) = Topology::Reflecting.advance(Point2::new(0.01, 0.01), Rotation2::new(-3.0 * PI / 4.0), 0.02_f32.hypot(0.02));
+        assert_relative_eq!(position, Point2::new(0.01, 0.01), epsilon = 1e-6);
+        assert_relative_eq!(rotation.angle(), PI / 4.0, epsilon = 1e-6);
+    }
+}
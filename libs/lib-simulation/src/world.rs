@@ -1,22 +1,50 @@
-use nalgebra::geometry::Point2;
-use nalgebra::{Rotation2, wrap, distance};
-use rand::{rngs, Rng, RngCore};
+use nalgebra::Point2;
+use rand::RngCore;
 
 use crate::animal::*;
-use crate::food::*;
+use crate::food::{self, Food};
+use crate::genealogy::AnimalId;
+use crate::obstacle;
+use crate::{FoodPlacement, Obstacle, SimulationConfig};
 
 #[derive(Debug)]
 pub struct World {
     pub(crate) animals: Vec<Animal>,
-    pub(crate) food: Vec<Food>
+    pub(crate) food: Vec<Food>,
+    pub(crate) predators: Vec<Animal>,
+    pub(crate) obstacles: Vec<Obstacle>,
+    /// Centers of the patches food grows in, if it grows in patches
+    pub(crate) food_patches: Vec<Point2<f32>>,
 }
 
 
 impl World {
     pub fn random(rng: &mut dyn RngCore, num_animals: usize, num_food: usize) -> Self {
+        let mut config = SimulationConfig::default();
+        config.world.animals = num_animals;
+        config.world.food = num_food;
+        Self::from_config(rng, &config)
+    }
+
+    pub(crate) fn from_config(rng: &mut dyn RngCore, config: &SimulationConfig) -> Self {
+        let obstacles = &config.world.obstacles;
+        let animals = (0..config.world.animals).map(|i| Animal { id: AnimalId(i as u64), ..Animal::random_with_config(rng, config, Species::Prey) }).collect();
+        let food_patches: Vec<Point2<f32>> = match config.food.placement {
+            FoodPlacement::Uniform => vec![],
+            FoodPlacement::Patches { count, .. } => (0..count).map(|_| obstacle::free_position(obstacles, config.world.topology, rng)).collect(),
+        };
         Self {
-            animals: (0..num_animals).map(|_| Animal::random(rng)).collect(),
-            food: (0..num_food).map(|_| Food::random(rng)).collect()
+            animals,
+            food: food::assign_kinds(&config.food.kinds(), config.world.food)
+                .into_iter()
+                .map(|kind| Food::new(food::grow_position(config, &food_patches, rng), kind))
+                .collect(),
+            // Numbered after the prey
+            predators: (0..config.predators.count)
+                .map(|i| Animal { id: AnimalId((config.world.animals + i) as u64), ..Animal::random_with_config(rng, config, Species::Predator) })
+                .collect(),
+            obstacles: obstacles.clone(),
+            food_patches,
         }
     }
 
@@ -28,4 +56,19 @@ impl World {
         &self.food
     }
 
+    /// Birds hunting the `animals`, if the config asks for some
+    pub fn predators(&self) -> &[Animal] {
+        &self.predators
+    }
+
+    pub fn obstacles(&self) -> &[Obstacle] {
+        &self.obstacles
+    }
+
+    /// Centers of the patches food grows in, empty unless the config asks
+    /// for patches
+    pub fn food_patches(&self) -> &[Point2<f32>] {
+        &self.food_patches
+    }
+
 }
@@ -1,21 +1,51 @@
 import * as simulationWasm from 'lib-simulation-wasm';
 
 let sim = null;
+let obstacles = [];
+let exhaustion = "die";
+let foodColors = [];
 let drawing = true;
 const generationNumberOutput = document.getElementById("generation-number")
 const generationAgeOutput = document.getElementById("generation-age")
 const generationScoreOutput = document.getElementById("generation-score")
 
-function drawAnimal(ctx, animal, canvasWidth, canvasHeight) {
-    ctx.fillStyle = "rgb(255, 0, 0)";
+function drawAnimal(ctx, animal, canvasWidth, canvasHeight, color = "rgb(255, 0, 0)") {
+    ctx.fillStyle = color;
     ctx.fillRect(animal.x * canvasWidth , animal.y * canvasHeight, 10, 10);
 }
 
 function drawFood(ctx, food, canvasWidth, canvasHeight) {
-    ctx.fillStyle = "rgb(0, 255, 0)";
+    // Plain food keeps its usual colour, kinds set in the config their own
+    ctx.fillStyle = foodColors[food.kind] ?? "rgb(0, 255, 0)";
     ctx.fillRect(food.x * canvasWidth, food.y * canvasHeight, 10, 10);
 }
 
+// Obstacles only come from the config, as described by `Obstacle` in
+// lib-simulation
+function drawObstacle(ctx, obstacle, canvasWidth, canvasHeight) {
+    ctx.fillStyle = "rgb(80, 80, 80)";
+    ctx.strokeStyle = "rgb(80, 80, 80)";
+    ctx.beginPath();
+    if (obstacle.shape === "circle") {
+        ctx.ellipse(obstacle.center[0] * canvasWidth, obstacle.center[1] * canvasHeight,
+            obstacle.radius * canvasWidth, obstacle.radius * canvasHeight, 0, 0, 2 * Math.PI);
+        ctx.fill();
+    } else if (obstacle.shape === "rectangle") {
+        const [w, h] = obstacle.size;
+        const cos = Math.cos(obstacle.angle), sin = Math.sin(obstacle.angle);
+        for (const [x, y] of [[-w, -h], [w, -h], [w, h], [-w, h]]) {
+            ctx.lineTo((obstacle.center[0] + (x * cos - y * sin) / 2) * canvasWidth,
+                (obstacle.center[1] + (x * sin + y * cos) / 2) * canvasHeight);
+        }
+        ctx.fill();
+    } else if (obstacle.shape === "polyline") {
+        for (const [x, y] of obstacle.points) {
+            ctx.lineTo(x * canvasWidth, y * canvasHeight);
+        }
+        ctx.stroke();
+    }
+}
+
 function drawFrame() {
     if (!drawing) {
          return window.requestAnimationFrame(drawFrame)
@@ -29,9 +59,21 @@ function drawFrame() {
         throw new Exception("Cannot load canvas")
     }
     ctx.clearRect(0, 0, canvasWidth, canvasHeight); // clear canvas
+    for (const obstacle of obstacles) {
+        drawObstacle(ctx, obstacle, canvasWidth, canvasHeight)
+    }
     const world = sim.world();
     for(const animal of world.animals) {
-        drawAnimal(ctx, animal, canvasWidth, canvasHeight)
+        if (!animal.exhausted) {
+            drawAnimal(ctx, animal, canvasWidth, canvasHeight)
+        } else if (exhaustion === "stop") {
+            drawAnimal(ctx, animal, canvasWidth, canvasHeight, "rgb(160, 160, 160)")
+        }
+    };
+    for(const predator of world.predators) {
+        if (!predator.exhausted || exhaustion === "stop") {
+            drawAnimal(ctx, predator, canvasWidth, canvasHeight, "rgb(0, 0, 0)")
+        }
     };
     for(const food of world.food) {
         drawFood(ctx, food, canvasWidth, canvasHeight)
@@ -40,7 +82,7 @@ function drawFrame() {
     generationAgeOutput.textContent = Number(generationAgeOutput.textContent) + 1;
     if (stats) {
         generationAgeOutput.textContent = 0;
-        generationNumberOutput.textContent = Number(generationNumberOutput.textContent) + 1;
+        generationNumberOutput.textContent = sim.generation() + 1;
         generationScoreOutput.textContent = stats.avg_score;
     }
     window.requestAnimationFrame(drawFrame)
@@ -53,7 +95,7 @@ function skipGeneration() {
         const stats = sim.step();
         if (stats) {
             skippedGeneration++
-            generationNumberOutput.textContent = Number(generationNumberOutput.textContent) + 1;
+            generationNumberOutput.textContent = sim.generation() + 1;
         };
     };
     generationAgeOutput.textContent = 0;
@@ -63,6 +105,10 @@ function skipGeneration() {
 
 function startSimulation(animals, food, mutRate, mutCoeff) {
     sim = new simulationWasm.Simulation(animals, food, mutRate, mutCoeff, 500);
+    const config = JSON.parse(sim.config());
+    obstacles = config.world.obstacles;
+    exhaustion = config.energy.exhaustion;
+    foodColors = config.food.kinds.map(({ color: [r, g, b] }) => `rgb(${r}, ${g}, ${b})`);
     generationAgeOutput.textContent = "0";
     generationNumberOutput.textContent = "1";
     generationScoreOutput.textContent = "0.00"